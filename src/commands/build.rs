@@ -6,6 +6,7 @@ use super::DEFAULT_CONFIG_PATH;
 use crate::build::build_target;
 use crate::settings::toml::Manifest;
 use crate::terminal::message::{Message, StdOut};
+use crate::upload;
 
 pub fn build(matches: &ArgMatches) -> Result<(), failure::Error> {
     log::info!("Getting project settings");
@@ -16,10 +17,15 @@ pub fn build(matches: &ArgMatches) -> Result<(), failure::Error> {
     let target = &manifest.get_target(env, false)?;
     let build_result = build_target(&target);
     match build_result {
-        Ok(msg) => {
-            StdOut::success(&msg);
-            Ok(())
-        }
-        Err(e) => Err(e),
+        Ok(msg) => StdOut::success(&msg),
+        Err(e) => return Err(e),
     }
+
+    if let Some(artifact) = matches.value_of("out") {
+        let artifact = Path::new(artifact);
+        upload::artifact::write(target, artifact)?;
+        StdOut::success(&format!("Wrote build artifact to {}", artifact.display()));
+    }
+
+    Ok(())
 }
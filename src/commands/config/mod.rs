@@ -1,3 +1,9 @@
+mod show;
+mod validate;
+
+pub use show::show;
+pub use validate::validate;
+
 #[cfg(not(target_os = "windows"))]
 use std::fs::File;
 #[cfg(not(target_os = "windows"))]
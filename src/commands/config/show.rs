@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::settings::toml::{Manifest, Resolution, ResolvedField};
+use crate::terminal::message::Output;
+
+#[derive(Serialize)]
+struct ShowJson<'a> {
+    environment: Option<&'a str>,
+    fields: &'a [ResolvedField],
+}
+
+/// `wrangler config show` prints the configuration an environment resolves to, noting
+/// where each field came from and whether environments inherit it.
+pub fn show(
+    config_path: &Path,
+    environment: Option<&str>,
+    output: Output,
+) -> Result<(), failure::Error> {
+    let manifest = Manifest::new(config_path)?;
+    let resolution = manifest.resolve(environment, false)?;
+
+    match output {
+        Output::Json => {
+            let json = ShowJson {
+                environment: resolution.environment.as_deref(),
+                fields: &resolution.fields,
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        Output::PlainText => print!("{}", annotated_toml(config_path, &resolution)?),
+    }
+
+    Ok(())
+}
+
+fn annotated_toml(config_path: &Path, resolution: &Resolution) -> Result<String, failure::Error> {
+    let scope = match &resolution.environment {
+        Some(environment) => format!("[env.{}]", environment),
+        None => "the top level".to_string(),
+    };
+    let mut annotated = format!(
+        "# Resolved configuration for {} of {}\n\n",
+        scope,
+        config_path.display()
+    );
+
+    // tables have to come after every plain key, or they would swallow them
+    let (tables, values): (Vec<&ResolvedField>, Vec<&ResolvedField>) = resolution
+        .fields
+        .iter()
+        .partition(|field| matches!(&field.value, Some(value) if is_table(value)));
+
+    for field in values.into_iter().chain(tables) {
+        annotated.push_str(&format!(
+            "# {}: {}, {}",
+            field.name, field.inheritance, field.source
+        ));
+        if let Some(file) = &field.file {
+            annotated.push_str(&format!(" of {}", file.display()));
+        }
+        annotated.push('\n');
+        if let Some(note) = &field.note {
+            annotated.push_str(&format!("# {}\n", note));
+        }
+        if let Some(value) = &field.value {
+            let mut table = toml::value::Table::new();
+            table.insert(field.name.to_string(), value.clone());
+            annotated.push_str(&toml::to_string(&table)?);
+        }
+        annotated.push('\n');
+    }
+
+    Ok(annotated)
+}
+
+fn is_table(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Table(_) => true,
+        toml::Value::Array(values) => !values.is_empty() && values.iter().all(is_table),
+        _ => false,
+    }
+}
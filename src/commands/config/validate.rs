@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use crate::settings::toml;
+use crate::terminal::message::{Message, StdErr, StdOut};
+
+pub fn validate(config_path: &Path) -> Result<(), failure::Error> {
+    let file_name = config_path.display();
+    failure::ensure!(config_path.exists(), "{} not found", file_name);
+
+    let diagnostics = toml::validate(config_path)?;
+
+    if diagnostics.is_empty() {
+        StdErr::success(&format!("{} is valid", file_name));
+        return Ok(());
+    }
+
+    // one problem per line, so CI logs and editors can pick out file:line:column
+    for diagnostic in &diagnostics {
+        StdOut::message(&diagnostic.to_string());
+    }
+
+    let problems = match diagnostics.len() {
+        1 => "1 problem".to_string(),
+        n => format!("{} problems", n),
+    };
+    failure::bail!("Found {} in {}", problems, file_name)
+}
@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+
+use prettytable::{Cell, Row, Table};
+
+use crate::deploy::history::Deployment;
+use crate::deploy::{self, history, RouteOptions};
+use crate::http;
+use crate::kv::store::ApiStore;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::sites::{
+    AssetEntry, AssetManifest, SiteVersions, MANIFEST_BINDING, MANIFEST_V2_BINDING,
+};
+use crate::terminal::message::{Message, Output, StdErr, StdOut};
+use crate::upload;
+use crate::upload::form::UploadForm;
+
+/// `wrangler deployments list` shows the deployments of a script recorded by `publish`,
+/// newest first.
+pub fn list(target: &Target, out: Output) -> Result<(), failure::Error> {
+    let deployments = history::list(target)?;
+
+    if out == Output::Json {
+        StdOut::as_json(&deployments);
+        return Ok(());
+    }
+    if deployments.is_empty() {
+        StdOut::info(&format!(
+            "There are no recorded deployments of {}. Deployments are recorded by `wrangler publish`.",
+            target.name
+        ));
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("ID"),
+        Cell::new("Published"),
+        Cell::new("Git commit"),
+        Cell::new("Deployed to"),
+    ]));
+    for deployment in &deployments {
+        let mut deployed_to: Vec<String> = deployment
+            .routes
+            .iter()
+            .flat_map(|zone| zone.patterns.clone())
+            .collect();
+        if deployment.workers_dev {
+            deployed_to.push("workers.dev".to_string());
+        }
+        if let Some(crons) = &deployment.crons {
+            deployed_to.extend(crons.iter().map(|cron| format!("cron \"{}\"", cron)));
+        }
+        let mut id = deployment.id.clone();
+        if let Some(rollback_of) = &deployment.rollback_of {
+            id.push_str(&format!("\n(rollback to {})", rollback_of));
+        }
+        let git_commit = deployment
+            .git_commit
+            .as_deref()
+            .map(|commit| &commit[..commit.len().min(12)])
+            .unwrap_or("");
+
+        table.add_row(Row::new(vec![
+            Cell::new(&id),
+            Cell::new(&deployment.timestamp),
+            Cell::new(git_commit),
+            Cell::new(&deployed_to.join("\n")),
+        ]));
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+/// `wrangler rollback` publishes a recorded deployment again, with the routes and schedules
+/// it had. Without `to`, it rolls back to the deployment before the latest one. Routes that
+/// were added since are left in place.
+pub fn rollback(
+    user: &GlobalUser,
+    target: &Target,
+    to: Option<&str>,
+) -> Result<(), failure::Error> {
+    let deployments = history::list(target)?;
+    let deployment = match to {
+        Some(id) => match deployments.iter().find(|deployment| deployment.id == id) {
+            Some(deployment) => deployment,
+            None => failure::bail!(
+                "There is no recorded deployment {} of {}. Run `wrangler deployments list` to see them.",
+                id,
+                target.name
+            ),
+        },
+        None => match deployments.get(1) {
+            Some(deployment) => deployment,
+            None => failure::bail!(
+                "There is no earlier recorded deployment of {} to roll back to.",
+                target.name
+            ),
+        },
+    };
+
+    let form = history::form(target, deployment)?;
+    check_site_files(user, target, deployment, &form)?;
+
+    StdErr::working(&format!(
+        "Rolling back {} to deployment {}",
+        target.name, deployment.id
+    ));
+    let upload_client = http::legacy_auth_client(user);
+    upload::script_form(&upload_client, target, form.to_form()?)?;
+
+    let deploy_targets = deployment.deploy_targets(target);
+    let results = deploy::worker(user, &deploy_targets, &RouteOptions::default())?;
+
+    match history::record(target, &form, &deploy_targets, Some(&deployment.id)) {
+        Ok(recorded) => log::info!("Recorded deployment {}", recorded.id),
+        Err(e) => StdErr::warn(&format!(
+            "Could not record this rollback in your deployment history: {}",
+            e
+        )),
+    }
+
+    let mut msg = format!(
+        "Rolled back {} to deployment {}",
+        target.name, deployment.id
+    );
+    if !results.urls.is_empty() {
+        msg.push_str(&format!("\n {}", results.urls.join("\n ")));
+    }
+    StdErr::success(&msg);
+
+    Ok(())
+}
+
+/// A deployment of a Workers Site can only be rolled back to while the files it published are
+/// still in the site's namespace. `publish` deletes those of earlier versions unless `[site]`
+/// retains them with `retain_versions` or `retain_for`.
+fn check_site_files(
+    user: &GlobalUser,
+    target: &Target,
+    deployment: &Deployment,
+    form: &UploadForm,
+) -> Result<(), failure::Error> {
+    // older deployments only have the manifest bound as __STATIC_CONTENT_MANIFEST
+    let manifest_part = match form
+        .part(MANIFEST_V2_BINDING)
+        .or_else(|| form.part(MANIFEST_BINDING))
+    {
+        Some(part) => part,
+        None => return Ok(()),
+    };
+    let asset_manifest: AssetManifest =
+        serde_json::from_str(&manifest_part.content.read_to_string()?)?;
+
+    let namespace_id = deployment
+        .bindings
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|binding| binding["type"] == "kv_namespace" && binding["name"] == "__STATIC_CONTENT")
+        .and_then(|binding| binding["namespace_id"].as_str());
+    let namespace_id = match namespace_id {
+        Some(namespace_id) => namespace_id,
+        None => return Ok(()),
+    };
+
+    let keys: HashSet<String> = asset_manifest
+        .values()
+        .flat_map(AssetEntry::keys)
+        .cloned()
+        .collect();
+    let store = ApiStore::new(target, user)?;
+    if !SiteVersions::get(&store, namespace_id)?.hold(&keys) {
+        failure::bail!(
+            "Deployment {} published a version of your site whose files have been deleted by a later publish, so rolling back to it would serve errors. Set retain_versions or retain_for in [site] to keep the files of earlier versions.",
+            deployment.id
+        )
+    }
+
+    Ok(())
+}
@@ -9,14 +9,17 @@ use crate::commands::dev::{socket, Protocol, ServerConfig};
 use crate::deploy::DeployTarget;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
+use crate::upload::form::SessionConfig;
 
 use tokio::runtime::Runtime as TokioRuntime;
 
 use std::sync::{Arc, Mutex};
 use std::thread;
 
+#[allow(clippy::too_many_arguments)]
 pub fn dev(
     target: Target,
+    session_config: SessionConfig,
     user: GlobalUser,
     server_config: ServerConfig,
     deploy_target: DeployTarget,
@@ -29,6 +32,7 @@ pub fn dev(
 
     let preview_token = upload(
         &mut target,
+        &session_config,
         &deploy_target,
         &user,
         session.preview_token.clone(),
@@ -44,6 +48,7 @@ pub fn dev(
         thread::spawn(move || {
             watch_for_changes(
                 target,
+                &session_config,
                 &deploy_target,
                 &user,
                 Arc::clone(&preview_token),
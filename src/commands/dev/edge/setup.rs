@@ -2,11 +2,13 @@ use std::path::Path;
 
 use crate::deploy::DeployTarget;
 use crate::kv::bulk;
+use crate::kv::store::ApiStore;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
-use crate::sites::{add_namespace, sync};
+use crate::sites::{add_namespace, file_pairs, finish_sync, sync};
 use crate::terminal::message::{Message, StdOut};
 use crate::upload;
+use crate::upload::form::SessionConfig;
 
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
@@ -14,6 +16,7 @@ use serde_json::json;
 
 pub(super) fn upload(
     target: &mut Target,
+    session_config: &SessionConfig,
     deploy_target: &DeployTarget,
     user: &GlobalUser,
     session_token: String,
@@ -21,28 +24,37 @@ pub(super) fn upload(
 ) -> Result<String, failure::Error> {
     let client = crate::http::legacy_auth_client(&user);
 
-    let (to_delete, asset_manifest, site_namespace_id) = if let Some(site_config) =
-        target.site.clone()
-    {
-        let site_namespace = add_namespace(user, target, true)?;
-        let path = Path::new(&site_config.bucket);
-        let (to_upload, to_delete, asset_manifest) = sync(target, user, &site_namespace.id, path)?;
+    let (to_delete, asset_manifest, site_namespace_id) =
+        if let Some(site_config) = target.site.clone() {
+            let site_namespace = add_namespace(user, target, true)?;
+            let path = Path::new(&site_config.bucket);
+            let (to_upload, to_delete, asset_manifest) =
+                sync(target, user, &site_namespace.id, path, false)?;
 
-        // First, upload all existing files in given directory
-        if verbose {
-            StdOut::info("Uploading updated files...");
-        }
+            // First, upload all existing files in given directory
+            if verbose {
+                StdOut::info("Uploading updated files...");
+            }
 
-        bulk::put(target, user, &site_namespace.id, to_upload, &None)?;
-        (to_delete, Some(asset_manifest), Some(site_namespace.id))
-    } else {
-        (Vec::new(), None, None)
-    };
+            bulk::put_with_metadata(
+                &ApiStore::new(target, user)?,
+                &site_namespace.id,
+                &path.display().to_string(),
+                file_pairs(to_upload),
+                &None,
+            )?;
+            (to_delete, Some(asset_manifest), Some(site_namespace.id))
+        } else {
+            (Vec::new(), None, None)
+        };
 
-    let session_config = get_session_config(deploy_target);
+    let session_config = SessionConfig {
+        config: Some(get_session_config(deploy_target)),
+        ..session_config.clone()
+    };
     let address = get_upload_address(target);
 
-    let script_upload_form = upload::form::build(target, asset_manifest, Some(session_config))?;
+    let script_upload_form = upload::form::build(target, asset_manifest, &session_config)?;
 
     let response = client
         .post(&address)
@@ -56,7 +68,20 @@ pub(super) fn upload(
             StdOut::info("Deleting stale files...");
         }
 
-        bulk::delete(target, user, &site_namespace_id.unwrap(), to_delete, &None)?;
+        bulk::delete(
+            &ApiStore::new(target, user)?,
+            site_namespace_id.as_ref().unwrap(),
+            &target.site.as_ref().unwrap().bucket.display().to_string(),
+            to_delete,
+            &None,
+        )?;
+    }
+    if let (Some(site_config), Some(site_namespace_id)) = (&target.site, &site_namespace_id) {
+        finish_sync(
+            &ApiStore::new(target, user)?,
+            &site_config.bucket,
+            site_namespace_id,
+        )?;
     }
 
     let text = &response.text()?;
@@ -4,10 +4,12 @@ use crate::commands::dev::edge::setup;
 use crate::deploy::DeployTarget;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
+use crate::upload::form::SessionConfig;
 use crate::watch::watch_and_build;
 
 pub fn watch_for_changes(
     target: Target,
+    session_config: &SessionConfig,
     deploy_target: &DeployTarget,
     user: &GlobalUser,
     preview_token: Arc<Mutex<String>>,
@@ -32,7 +34,14 @@ pub fn watch_for_changes(
         //
         // this allows the server to route subsequent requests
         // to the proper script
-        *preview_token = setup::upload(&mut target, &deploy_target, &user, session_token, verbose)?;
+        *preview_token = setup::upload(
+            &mut target,
+            session_config,
+            &deploy_target,
+            &user,
+            session_token,
+            verbose,
+        )?;
     }
 
     Ok(())
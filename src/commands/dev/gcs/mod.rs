@@ -8,6 +8,7 @@ use watch::watch_for_changes;
 
 use crate::commands::dev::{socket, Protocol, ServerConfig};
 use crate::settings::toml::Target;
+use crate::upload::form::SessionConfig;
 
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -18,6 +19,7 @@ use url::Url;
 /// that has a Cloudflare Workers runtime without access to zone-specific features
 pub fn dev(
     target: Target,
+    session_config: SessionConfig,
     server_config: ServerConfig,
     local_protocol: Protocol,
     verbose: bool,
@@ -30,6 +32,7 @@ pub fn dev(
     // upload the initial script
     let preview_id = get_preview_id(
         target.clone(),
+        &session_config,
         // there is no user for unauthenticated dev
         None,
         &server_config,
@@ -57,6 +60,7 @@ pub fn dev(
         thread::spawn(move || {
             watch_for_changes(
                 target,
+                &session_config,
                 &server_config,
                 Arc::clone(&preview_id),
                 &session_id,
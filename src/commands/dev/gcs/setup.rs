@@ -2,6 +2,7 @@ use crate::commands::dev::ServerConfig;
 use crate::preview::upload;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
+use crate::upload::form::SessionConfig;
 
 use uuid::Uuid;
 
@@ -18,6 +19,7 @@ pub(super) fn get_session_id() -> Result<String, failure::Error> {
 /// so it executes the correct Worker
 pub fn get_preview_id(
     mut target: Target,
+    session_config: &SessionConfig,
     user: Option<GlobalUser>,
     server_config: &ServerConfig,
     session_id: &str,
@@ -27,7 +29,13 @@ pub fn get_preview_id(
     // directing the user to open the browser to view the output
     // this message makes sense for `wrangler preview` but not `wrangler dev`
     let sites_preview = false;
-    let script_id = upload(&mut target, user.as_ref(), sites_preview, verbose)?;
+    let script_id = upload(
+        &mut target,
+        user.as_ref(),
+        sites_preview,
+        session_config,
+        verbose,
+    )?;
     Ok(format!(
         "{}{}{}{}",
         &script_id,
@@ -4,10 +4,12 @@ use crate::commands::dev::gcs::setup::get_preview_id;
 use crate::commands::dev::server_config::ServerConfig;
 
 use crate::settings::toml::Target;
+use crate::upload::form::SessionConfig;
 use crate::watch::watch_and_build;
 
 pub fn watch_for_changes(
     target: Target,
+    session_config: &SessionConfig,
     server_config: &ServerConfig,
     preview_id: Arc<Mutex<String>>,
     session_id: &str,
@@ -27,7 +29,14 @@ pub fn watch_for_changes(
         //
         // this allows the server to route subsequent requests
         // to the proper script
-        *preview_id = get_preview_id(target, None, server_config, session_id, verbose)?;
+        *preview_id = get_preview_id(
+            target,
+            session_config,
+            None,
+            server_config,
+            session_id,
+            verbose,
+        )?;
     }
 
     Ok(())
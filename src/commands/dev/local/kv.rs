@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::net::TcpListener;
+
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+
+use crate::kv::local::LocalNamespace;
+use crate::kv::store::{ListOptions, PutOptions};
+
+const METADATA_HEADER: &str = "cf-kv-metadata";
+
+/// serves the local KV namespaces to the runtime:
+///
+/// - `GET|PUT|DELETE /<namespace id>/values/<key>`, with the metadata as URI-encoded JSON
+///   in `cf-kv-metadata` and `expiration` or `expiration_ttl` query parameters on PUT
+/// - `GET /<namespace id>/keys?prefix=&limit=&cursor=`, which responds like the API's list
+pub async fn serve(listener: TcpListener) -> Result<(), failure::Error> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, failure::Error>(service_fn(|req| async {
+            let response = match respond(req).await {
+                Ok(response) => response,
+                Err(e) => Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(e.to_string()))?,
+            };
+            Ok::<_, failure::Error>(response)
+        }))
+    });
+
+    if let Err(e) = Server::from_tcp(listener)?.serve(make_service).await {
+        eprintln!("local KV error: {}", e);
+    }
+    Ok(())
+}
+
+async fn respond(req: Request<Body>) -> Result<Response<Body>, failure::Error> {
+    let (parts, body) = req.into_parts();
+    let path = parts
+        .uri
+        .path()
+        .trim_start_matches('/')
+        .splitn(3, '/')
+        .map(|segment| Ok(percent_decode_str(segment).decode_utf8()?.into_owned()))
+        .collect::<Result<Vec<String>, failure::Error>>()?;
+    let query: HashMap<String, String> =
+        url::form_urlencoded::parse(parts.uri.query().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+
+    let (namespace, rest) = match path.split_first() {
+        Some((namespace, rest)) => (LocalNamespace::open(namespace)?, rest),
+        None => failure::bail!("no namespace in {}", parts.uri),
+    };
+
+    match (&parts.method, rest) {
+        (&Method::GET, [keys]) if keys == "keys" => {
+            let limit = query.get("limit").map(|limit| limit.parse()).transpose()?;
+            let page = namespace.list(&ListOptions {
+                prefix: query.get("prefix").cloned(),
+                limit,
+                cursor: query.get("cursor").cloned(),
+            })?;
+            Ok(Response::new(Body::from(serde_json::to_vec(&page)?)))
+        }
+        (&Method::GET, [values, key]) if values == "values" => match namespace.get(key)? {
+            Some((value, metadata)) => {
+                let mut response = Response::new(Body::from(value));
+                if let Some(metadata) = metadata {
+                    let metadata = metadata.to_string();
+                    response.headers_mut().insert(
+                        HeaderName::from_static(METADATA_HEADER),
+                        HeaderValue::from_str(
+                            &utf8_percent_encode(&metadata, NON_ALPHANUMERIC).to_string(),
+                        )?,
+                    );
+                }
+                Ok(response)
+            }
+            None => Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())?),
+        },
+        (&Method::PUT, [values, key]) if values == "values" => {
+            let expiration = query.get("expiration").map(|e| e.parse()).transpose()?;
+            let expiration_ttl = query
+                .get("expiration_ttl")
+                .map(|ttl| ttl.parse())
+                .transpose()?;
+            let metadata = match parts.headers.get(METADATA_HEADER) {
+                Some(metadata) => Some(serde_json::from_str(
+                    &percent_decode_str(metadata.to_str()?).decode_utf8()?,
+                )?),
+                None => None,
+            };
+            let value = hyper::body::to_bytes(body).await?;
+            let options = PutOptions {
+                expiration,
+                expiration_ttl,
+                metadata,
+            };
+            namespace.put(key, &value, &options)?;
+            Ok(Response::new(Body::empty()))
+        }
+        (&Method::DELETE, [values, key]) if values == "values" => {
+            namespace.delete(key)?;
+            Ok(Response::new(Body::empty()))
+        }
+        _ => Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())?),
+    }
+}
@@ -0,0 +1,65 @@
+mod kv;
+mod runtime;
+mod server;
+mod watch;
+
+use runtime::{find_node, NodeRuntime};
+use watch::watch_for_changes;
+
+use crate::commands::dev::{Protocol, ServerConfig};
+use crate::settings::toml::{ScriptFormat, Target};
+use crate::upload::form::SessionConfig;
+
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::runtime::Runtime as TokioRuntime;
+
+/// run the Worker in a Node.js process on this machine, with its KV namespaces
+/// kept in the project's `.wrangler/state` directory
+pub fn dev(
+    target: Target,
+    session_config: SessionConfig,
+    server_config: ServerConfig,
+    local_protocol: Protocol,
+) -> Result<(), failure::Error> {
+    if target.site.is_some() {
+        failure::bail!("`wrangler dev --local` does not support Workers Sites yet")
+    }
+    let modules = matches!(
+        target.build.as_ref().map(|build| &build.upload_format),
+        Some(ScriptFormat::Modules)
+    );
+    let node = find_node(modules)?;
+
+    // the runtime reaches its KV namespaces through a server of our own
+    let kv_listener = TcpListener::bind("127.0.0.1:0")?;
+    kv_listener.set_nonblocking(true)?;
+    let kv_url = format!("http://{}", kv_listener.local_addr()?);
+
+    let runtime = Arc::new(Mutex::new(NodeRuntime::start(
+        &node,
+        &target,
+        &session_config,
+        &kv_url,
+    )?));
+    {
+        let runtime = runtime.clone();
+        thread::spawn(move || watch_for_changes(target, &session_config, &node, &kv_url, runtime));
+    }
+
+    let mut tokio_runtime = TokioRuntime::new()?;
+    tokio_runtime.block_on(async {
+        let kv_server = tokio::spawn(kv::serve(kv_listener));
+        let server = match local_protocol {
+            Protocol::Https => tokio::spawn(server::https(server_config, Arc::clone(&runtime))),
+            Protocol::Http => tokio::spawn(server::http(server_config, Arc::clone(&runtime))),
+        };
+
+        let res = tokio::try_join!(async { kv_server.await? }, async { server.await? });
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    })
+}
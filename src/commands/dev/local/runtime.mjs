@@ -0,0 +1,277 @@
+// The runtime for `wrangler dev --local`. It runs the worker in the upload form written to the
+// directory given as its argument, and serves it on a free port, printing `{"port":<port>}`
+// once it is ready. KV namespaces are backed by the store wrangler serves at WRANGLER_KV_URL.
+import { createServer } from "node:http";
+import { readFile } from "node:fs/promises";
+import * as nodeModule from "node:module";
+import { join } from "node:path";
+import { pathToFileURL } from "node:url";
+import vm from "node:vm";
+
+// A module loader hook, registered in its own thread, that loads modules from the form by
+// content type.
+const LOADER = `
+import { readFile } from "node:fs/promises";
+
+let types = {};
+
+export function initialize(data) {
+  types = data;
+}
+
+export async function load(url, context, nextLoad) {
+  switch (types[url]) {
+    case "application/javascript+module":
+      return { format: "module", source: await readFile(new URL(url)), shortCircuit: true };
+    case "application/javascript":
+      return nextLoad(url, { ...context, format: "commonjs" });
+    case "application/wasm":
+      return {
+        format: "module",
+        source: "const bytes = await (await import('node:fs/promises')).readFile(new URL(import.meta.url));" +
+          "export default new WebAssembly.Module(bytes);",
+        shortCircuit: true,
+      };
+    case "text/plain":
+      return {
+        format: "module",
+        source: "export default " + JSON.stringify(await readFile(new URL(url), "utf8")) + ";",
+        shortCircuit: true,
+      };
+    case "application/octet-stream":
+      return {
+        format: "module",
+        source: "export default new Uint8Array(" + JSON.stringify([...(await readFile(new URL(url)))]) + ").buffer;",
+        shortCircuit: true,
+      };
+    default:
+      return nextLoad(url, context);
+  }
+}
+`;
+
+const formDir = process.argv[2];
+const kvUrl = process.env.WRANGLER_KV_URL;
+
+const parts = JSON.parse(await readFile(join(formDir, "form.json"), "utf8"));
+const metadata = JSON.parse(await readFile(join(formDir, "metadata"), "utf8"));
+
+class KVNamespace {
+  #id;
+
+  constructor(id) {
+    this.#id = id;
+  }
+
+  #url(path, params = {}) {
+    const search = new URLSearchParams();
+    for (const [name, value] of Object.entries(params)) {
+      if (value !== undefined && value !== null) {
+        search.set(name, value);
+      }
+    }
+    return `${kvUrl}/${encodeURIComponent(this.#id)}/${path}?${search}`;
+  }
+
+  async get(key, options) {
+    return (await this.getWithMetadata(key, options)).value;
+  }
+
+  async getWithMetadata(key, options) {
+    const type = (typeof options === "string" ? options : options?.type) || "text";
+    const response = await fetch(this.#url(`values/${encodeURIComponent(key)}`));
+    if (response.status === 404) {
+      return { value: null, metadata: null };
+    }
+    await check(response);
+    const header = response.headers.get("cf-kv-metadata");
+    const metadata = header === null ? null : JSON.parse(decodeURIComponent(header));
+    switch (type) {
+      case "text":
+        return { value: await response.text(), metadata };
+      case "json":
+        return { value: await response.json(), metadata };
+      case "arrayBuffer":
+        return { value: await response.arrayBuffer(), metadata };
+      case "stream":
+        return { value: response.body, metadata };
+      default:
+        throw new TypeError(`Unknown response type "${type}"`);
+    }
+  }
+
+  async put(key, value, options = {}) {
+    const headers = {};
+    if (options.metadata !== undefined) {
+      headers["cf-kv-metadata"] = encodeURIComponent(JSON.stringify(options.metadata));
+    }
+    const url = this.#url(`values/${encodeURIComponent(key)}`, {
+      expiration: options.expiration,
+      expiration_ttl: options.expirationTtl,
+    });
+    await check(await fetch(url, { method: "PUT", headers, body: value, duplex: "half" }));
+  }
+
+  async delete(key) {
+    await check(await fetch(this.#url(`values/${encodeURIComponent(key)}`), { method: "DELETE" }));
+  }
+
+  async list(options = {}) {
+    const url = this.#url("keys", {
+      prefix: options.prefix,
+      limit: options.limit,
+      cursor: options.cursor,
+    });
+    const response = await fetch(url);
+    await check(response);
+    return response.json();
+  }
+}
+
+async function check(response) {
+  if (!response.ok) {
+    throw new Error(`KV ${response.status}: ${await response.text()}`);
+  }
+}
+
+// the Cache API is not emulated: nothing is ever cached
+const cache = {
+  async match() {
+    return undefined;
+  },
+  async put() {},
+  async delete() {
+    return false;
+  },
+};
+globalThis.caches = { default: cache, open: async () => cache };
+
+const bindings = {};
+for (const binding of metadata.bindings) {
+  switch (binding.type) {
+    case "plain_text":
+    case "secret_text":
+      bindings[binding.name] = binding.text;
+      break;
+    case "text_blob":
+      bindings[binding.name] = await readFile(join(formDir, binding.part), "utf8");
+      break;
+    case "wasm_module":
+      bindings[binding.name] = new WebAssembly.Module(await readFile(join(formDir, binding.part)));
+      break;
+    case "kv_namespace":
+      bindings[binding.name] = new KVNamespace(binding.namespace_id);
+      break;
+    default:
+      console.warn(`Bindings of type ${binding.type} are not supported locally`);
+  }
+}
+
+const context = {
+  waitUntil() {},
+  passThroughOnException() {},
+};
+
+let handle;
+if (metadata.main_module) {
+  // modules are loaded by their content type, like the Workers runtime does
+  const moduleTypes = {};
+  for (const part of parts) {
+    moduleTypes[pathToFileURL(join(formDir, part.name)).href] = part.content_type;
+  }
+  if (typeof nodeModule.register !== "function") {
+    throw new Error("Running modules workers locally needs Node.js 20.6 or later");
+  }
+  nodeModule.register(`data:text/javascript,${encodeURIComponent(LOADER)}`, { data: moduleTypes });
+
+  const { default: worker } = await import(pathToFileURL(join(formDir, metadata.main_module)).href);
+  handle = (request) => worker.fetch(request, bindings, context);
+} else {
+  const listeners = [];
+  globalThis.addEventListener = (type, listener) => {
+    if (type === "fetch") {
+      listeners.push(listener);
+    }
+  };
+  Object.assign(globalThis, bindings);
+
+  const script = parts.find((part) => part.name === metadata.body_part);
+  vm.runInThisContext(await readFile(join(formDir, script.name), "utf8"), {
+    filename: script.file_name,
+  });
+
+  handle = async (request) => {
+    let response;
+    const event = {
+      type: "fetch",
+      request,
+      respondWith(r) {
+        response = r;
+      },
+      ...context,
+    };
+    for (const listener of listeners) {
+      listener(event);
+      if (response !== undefined) {
+        return response;
+      }
+    }
+    // like a worker that doesn't respond, pass the request on to the origin
+    return fetch(request);
+  };
+}
+
+const server = createServer(async (req, res) => {
+  try {
+    const headers = new Headers();
+    for (const [name, value] of Object.entries(req.headers)) {
+      for (const v of Array.isArray(value) ? value : [value]) {
+        headers.append(name, v);
+      }
+    }
+    const chunks = [];
+    for await (const chunk of req) {
+      chunks.push(chunk);
+    }
+    const scheme = req.headers["x-forwarded-proto"] || "http";
+    const request = new Request(new URL(req.url, `${scheme}://${req.headers.host}`), {
+      method: req.method,
+      headers,
+      body: req.method === "GET" || req.method === "HEAD" ? undefined : Buffer.concat(chunks),
+    });
+
+    const response = await handle(request);
+
+    const responseHeaders = {};
+    response.headers.forEach((value, name) => {
+      if (name !== "set-cookie") {
+        responseHeaders[name] = value;
+      }
+    });
+    const cookies = response.headers.getSetCookie?.() || [];
+    if (cookies.length > 0) {
+      responseHeaders["set-cookie"] = cookies;
+    }
+    res.writeHead(response.status, response.statusText, responseHeaders);
+    if (response.body) {
+      for await (const chunk of response.body) {
+        res.write(chunk);
+      }
+    }
+    res.end();
+  } catch (e) {
+    console.error(e);
+    if (!res.headersSent) {
+      res.writeHead(500, { "content-type": "text/plain" });
+    }
+    res.end(`${e?.stack || e}\n`);
+  }
+});
+
+// wrangler holds our stdin open for as long as it runs
+process.stdin.on("end", () => process.exit());
+process.stdin.resume();
+
+server.listen(0, "127.0.0.1", () => {
+  console.log(JSON.stringify({ port: server.address().port }));
+});
@@ -0,0 +1,152 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+use serde::Deserialize;
+
+use crate::settings::toml::Target;
+use crate::settings::LOCAL_STATE_DIR;
+use crate::upload::form::{build_upload_form, SessionConfig};
+
+const RUNTIME: &str = include_str!("runtime.mjs");
+const RUNTIME_FILE: &str = "runtime.mjs";
+const FORM_DIR: &str = "form";
+const KV_URL_VAR: &str = "WRANGLER_KV_URL";
+// the oldest release with fetch, Request and Response as globals
+const MIN_NODE_VERSION: (u32, u32) = (18, 0);
+// the oldest release with `module.register`, which the runtime loads modules workers with
+const MIN_NODE_VERSION_MODULES: (u32, u32) = (20, 6);
+
+/// A Node.js process running a build of the worker.
+/// It is stopped when this is dropped.
+pub struct NodeRuntime {
+    child: Child,
+    pub port: u16,
+}
+
+#[derive(Deserialize)]
+struct Ready {
+    port: u16,
+}
+
+impl NodeRuntime {
+    /// Writes the upload form for `target` to `.wrangler/dev` and starts a runtime running
+    /// it, which uses the KV namespaces served at `kv_url`.
+    pub fn start(
+        node: &Path,
+        target: &Target,
+        session_config: &SessionConfig,
+        kv_url: &str,
+    ) -> Result<Self, failure::Error> {
+        let dir = Path::new(LOCAL_STATE_DIR).join("dev");
+        let form_dir = dir.join(FORM_DIR);
+        if form_dir.exists() {
+            fs::remove_dir_all(&form_dir)?;
+        }
+        build_upload_form(target, None, session_config)?.write(&form_dir)?;
+        let runtime_path = dir.join(RUNTIME_FILE);
+        fs::write(&runtime_path, RUNTIME)?;
+
+        let mut child = Command::new(node)
+            .arg(&runtime_path)
+            .arg(&form_dir)
+            .env(KV_URL_VAR, kv_url)
+            // the runtime exits when its stdin closes, so it never outlives wrangler
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        // the runtime prints the port it listens on once the worker has loaded,
+        // and everything after that is the worker's own output
+        let stdout = child.stdout.take().expect("the runtime's stdout is piped");
+        let mut lines = BufReader::new(stdout).lines();
+        let port = loop {
+            match lines.next() {
+                Some(line) => {
+                    let line = line?;
+                    match serde_json::from_str::<Ready>(&line) {
+                        Ok(ready) => break ready.port,
+                        Err(_) => println!("{}", line),
+                    }
+                }
+                None => {
+                    let status = child.wait()?;
+                    failure::bail!("The local runtime stopped before it was ready ({})", status)
+                }
+            }
+        };
+        thread::spawn(move || {
+            for line in lines {
+                match line {
+                    Ok(line) => println!("{}", line),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(NodeRuntime { child, port })
+    }
+}
+
+impl Drop for NodeRuntime {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The `node` to run workers with, if one recent enough for a modules worker, or a
+/// service-worker one, is installed.
+pub fn find_node(modules: bool) -> Result<PathBuf, failure::Error> {
+    let (min_major, min_minor) = if modules {
+        MIN_NODE_VERSION_MODULES
+    } else {
+        MIN_NODE_VERSION
+    };
+    let node = match which::which("node") {
+        Ok(node) => node,
+        Err(_) => failure::bail!(
+            "`wrangler dev --local` needs Node.js {}.{} or later, and `node` could not be found",
+            min_major,
+            min_minor
+        ),
+    };
+
+    let output = Command::new(&node).arg("--version").output()?;
+    // e.g. v18.12.1
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match parse_node_version(&version) {
+        Some(found) if found >= (min_major, min_minor) => Ok(node),
+        _ => failure::bail!(
+            "`wrangler dev --local` needs Node.js {}.{} or later{}, but {} is {}",
+            min_major,
+            min_minor,
+            if modules { " for modules workers" } else { "" },
+            node.display(),
+            version
+        ),
+    }
+}
+
+// The major and minor version of a `node --version`.
+fn parse_node_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_node_versions() {
+        assert_eq!(parse_node_version("v20.6.1"), Some((20, 6)));
+        assert!(parse_node_version("v20.5.1").unwrap() < MIN_NODE_VERSION_MODULES);
+        assert!(parse_node_version("v18.12.1").unwrap() >= MIN_NODE_VERSION);
+        assert_eq!(parse_node_version("node"), None);
+    }
+}
@@ -0,0 +1,120 @@
+use super::runtime_request;
+use crate::commands::dev::local::runtime::NodeRuntime;
+use crate::commands::dev::server_config::ServerConfig;
+use crate::commands::dev::tls;
+use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
+use crate::terminal::emoji;
+use crate::terminal::message::{Message, StdOut};
+
+use std::sync::{Arc, Mutex};
+
+use chrono::prelude::*;
+use futures_util::stream::StreamExt;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Client as HyperClient, Request, Server};
+use tokio::net::TcpListener;
+
+/// performs all logic that takes an incoming request
+/// and routes it to the local runtime
+pub async fn https(
+    server_config: ServerConfig,
+    runtime: Arc<Mutex<NodeRuntime>>,
+) -> Result<(), failure::Error> {
+    tls::generate_cert()?;
+
+    let client = HyperClient::new();
+
+    let listening_address = server_config.listening_address;
+
+    // create a closure that hyper will use later to handle HTTP requests
+    let service = make_service_fn(move |_| {
+        let client = client.to_owned();
+        let server_config = server_config.to_owned();
+        let runtime = runtime.to_owned();
+        async move {
+            Ok::<_, failure::Error>(service_fn(move |req| {
+                let client = client.to_owned();
+                let server_config = server_config.to_owned();
+                // the watcher holds the lock while it restarts the runtime
+                let port = runtime.lock().unwrap().port;
+                let version = req.version();
+
+                // record the time of the request
+                let now: DateTime<Local> = Local::now();
+
+                let (parts, body) = req.into_parts();
+                let local_host = server_config.listening_address.to_string();
+
+                let req_method = parts.method.to_string();
+                let path = get_path_as_str(&parts.uri);
+
+                async move {
+                    let mut resp = runtime_request(
+                        Request::from_parts(parts, body),
+                        client,
+                        &server_config,
+                        port,
+                    )
+                    .await?;
+                    rewrite_redirect(
+                        &mut resp,
+                        &server_config.host.to_string(),
+                        &local_host,
+                        true,
+                    );
+
+                    // print information about the response
+                    // [2020-04-20 15:25:54] GET example.com/ HTTP/1.1 200 OK
+                    println!(
+                        "[{}] {} {}{} {:?} {}",
+                        now.format("%Y-%m-%d %H:%M:%S"),
+                        req_method,
+                        server_config.host,
+                        path,
+                        version,
+                        resp.status()
+                    );
+                    Ok::<_, failure::Error>(resp)
+                }
+            }))
+        }
+    });
+
+    // Create a TCP listener via tokio.
+    let mut tcp = TcpListener::bind(&listening_address).await?;
+    let tls_acceptor = &tls::get_tls_acceptor()?;
+    let incoming_tls_stream = tcp
+        .incoming()
+        .filter_map(move |s| async move {
+            let client = match s {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Failed to accept client {}", e);
+                    return None;
+                }
+            };
+            match tls_acceptor.accept(client).await {
+                Ok(x) => Some(Ok(x)),
+                Err(e) => {
+                    eprintln!("Client connection error {}", e);
+                    StdOut::info("Make sure to use https and `--insecure` with curl");
+                    None
+                }
+            }
+        })
+        .boxed();
+
+    let server = Server::builder(tls::HyperAcceptor {
+        acceptor: incoming_tls_stream,
+    })
+    .serve(service);
+    println!("{} Listening on https://{}", emoji::EAR, listening_address);
+
+    StdOut::info("Generated certificate is not verified, browsers will give a warning and curl will require `--insecure`");
+
+    if let Err(e) = server.await {
+        eprintln!("{}", e);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+mod http;
+mod https;
+
+pub use self::http::http;
+pub use self::https::https;
+
+use crate::commands::dev::utils::get_path_as_str;
+use crate::commands::dev::ServerConfig;
+
+use hyper::client::{HttpConnector, ResponseFuture};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Body, Client as HyperClient, Request};
+
+/// sends a request to the local runtime as if it were for the host in `server_config`,
+/// so the worker sees the same URL it would in production
+fn runtime_request(
+    req: Request<Body>,
+    client: HyperClient<HttpConnector>,
+    server_config: &ServerConfig,
+    port: u16,
+) -> ResponseFuture {
+    let (mut parts, body) = req.into_parts();
+
+    let path = get_path_as_str(&parts.uri);
+    let scheme = if server_config.host.is_https() {
+        "https"
+    } else {
+        "http"
+    };
+
+    parts.headers.insert(
+        HeaderName::from_static("host"),
+        HeaderValue::from_str(&server_config.host.to_string())
+            .expect("Could not create host header"),
+    );
+
+    parts.headers.insert(
+        HeaderName::from_static("x-forwarded-proto"),
+        HeaderValue::from_static(scheme),
+    );
+
+    parts.uri = format!("http://127.0.0.1:{}{}", port, path)
+        .parse()
+        .expect("Could not construct local runtime url");
+
+    let req = Request::from_parts(parts, body);
+
+    client.request(req)
+}
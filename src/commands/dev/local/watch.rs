@@ -0,0 +1,34 @@
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+
+use crate::commands::dev::local::runtime::NodeRuntime;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdOut};
+use crate::upload::form::SessionConfig;
+use crate::watch::watch_and_build;
+
+pub fn watch_for_changes(
+    target: Target,
+    session_config: &SessionConfig,
+    node: &Path,
+    kv_url: &str,
+    runtime: Arc<Mutex<NodeRuntime>>,
+) -> Result<(), failure::Error> {
+    let (sender, receiver) = mpsc::channel();
+    watch_and_build(&target, Some(sender))?;
+
+    while receiver.recv().is_ok() {
+        // acquire the lock so incoming requests are halted
+        // until the new runtime is ready for them
+        let mut runtime = runtime.lock().unwrap();
+
+        // the old runtime keeps serving if the new build fails to start,
+        // and is stopped when it is replaced
+        match NodeRuntime::start(node, &target, session_config, kv_url) {
+            Ok(new_runtime) => *runtime = new_runtime,
+            Err(e) => StdOut::user_error(&e.to_string()),
+        }
+    }
+
+    Ok(())
+}
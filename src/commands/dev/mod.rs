@@ -1,5 +1,7 @@
 mod edge;
 mod gcs;
+mod local;
+mod scheduled;
 mod server_config;
 mod socket;
 mod tls;
@@ -14,9 +16,12 @@ use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdOut};
 use crate::terminal::styles;
+use crate::upload::form::SessionConfig;
 
 /// `wrangler dev` starts a server on a dev machine that routes incoming HTTP requests
-/// to a Cloudflare Workers runtime and returns HTTP responses
+/// to a Cloudflare Workers runtime and returns HTTP responses, or with `local`,
+/// to a runtime on the dev machine itself
+#[allow(clippy::too_many_arguments)]
 pub fn dev(
     target: Target,
     deployments: DeploymentSet,
@@ -24,11 +29,43 @@ pub fn dev(
     server_config: ServerConfig,
     local_protocol: Protocol,
     upstream_protocol: Protocol,
+    local: bool,
+    run_scheduled: bool,
+    session_config: SessionConfig,
     verbose: bool,
 ) -> Result<(), failure::Error> {
     // before serving requests we must first build the Worker
     build_target(&target)?;
 
+    let crons = deployments
+        .iter()
+        .find_map(|t| match t {
+            DeployTarget::Schedule(schedule) => Some(schedule.crons.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let host_str = styles::highlight("--host");
+    let local_str = styles::highlight("--local-protocol");
+    let upstream_str = styles::highlight("--upstream-protocol");
+
+    if server_config.host.is_https() != upstream_protocol.is_https() {
+        failure::bail!(format!(
+            "Protocol mismatch: protocol in {} and protocol in {} must match",
+            host_str, upstream_str
+        ))
+    } else if local_protocol.is_https() && upstream_protocol.is_http() {
+        failure::bail!("{} cannot be https if {} is http", local_str, upstream_str)
+    }
+
+    if session_config.test_scheduled {
+        scheduled::start(&server_config, local_protocol, crons, run_scheduled)?;
+    }
+
+    if local {
+        return local::dev(target, session_config, server_config, local_protocol);
+    }
+
     let deploy_target = {
         let valid_targets = deployments
             .into_iter()
@@ -51,24 +88,16 @@ pub fn dev(
         }
     };
 
-    let host_str = styles::highlight("--host");
-    let local_str = styles::highlight("--local-protocol");
-    let upstream_str = styles::highlight("--upstream-protocol");
-
-    if server_config.host.is_https() != upstream_protocol.is_https() {
-        failure::bail!(format!(
-            "Protocol mismatch: protocol in {} and protocol in {} must match",
-            host_str, upstream_str
-        ))
-    } else if local_protocol.is_https() && upstream_protocol.is_http() {
-        failure::bail!("{} cannot be https if {} is http", local_str, upstream_str)
-    }
-
     if let Some(user) = user {
         if server_config.host.is_default() {
             // Authenticated and no host provided, run on edge with user's zone
+            let mut deploy_target = deploy_target;
+            if let DeployTarget::Zoned(zoned) = &mut deploy_target {
+                zoned.resolve_zone(&user)?;
+            }
             return edge::dev(
                 target,
+                session_config,
                 user,
                 server_config,
                 deploy_target,
@@ -88,5 +117,11 @@ pub fn dev(
         );
     }
 
-    gcs::dev(target, server_config, local_protocol, verbose)
+    gcs::dev(
+        target,
+        session_config,
+        server_config,
+        local_protocol,
+        verbose,
+    )
 }
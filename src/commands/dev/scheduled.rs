@@ -0,0 +1,122 @@
+use std::io::{self, BufRead};
+use std::thread;
+
+use chrono::prelude::*;
+use reqwest::blocking::Client;
+use url::Url;
+
+use crate::commands::dev::{Protocol, ServerConfig};
+use crate::deploy::cron::Cron;
+use crate::terminal::message::{Message, StdOut};
+use crate::upload::form::SCHEDULED_PATH;
+
+// fired by the keyboard shortcut when there are no crons in [triggers]
+const DEFAULT_CRON: &str = "* * * * *";
+
+/// Fires scheduled events at the local dev server, which passes them on to the worker: one for
+/// each cron whenever `s` is entered, and each cron on its schedule when `on_schedule` is set.
+pub fn start(
+    server_config: &ServerConfig,
+    local_protocol: Protocol,
+    crons: Vec<String>,
+    on_schedule: bool,
+) -> Result<(), failure::Error> {
+    let scheme = if local_protocol.is_https() {
+        "https"
+    } else {
+        "http"
+    };
+    let url = Url::parse(&format!(
+        "{}://{}{}",
+        scheme, server_config.listening_address, SCHEDULED_PATH
+    ))?;
+    // the local https server uses a self-signed certificate
+    let client = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let crons: Vec<Cron> = if crons.is_empty() {
+        vec![DEFAULT_CRON.parse()?]
+    } else {
+        crons
+            .iter()
+            .map(|cron| cron.parse())
+            .collect::<Result<_, _>>()?
+    };
+
+    if on_schedule {
+        let url = url.clone();
+        let client = client.clone();
+        let crons = crons.clone();
+        thread::spawn(move || fire_on_schedule(&client, &url, &crons));
+    }
+
+    StdOut::info(&format!(
+        "Enter `s` to run the scheduled handler, or request {} with a `cron` query parameter",
+        url
+    ));
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) if line.trim() == "s" => {
+                    for cron in &crons {
+                        fire(&client, &url, cron);
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn fire_on_schedule(client: &Client, url: &Url, crons: &[Cron]) {
+    loop {
+        let now = Utc::now();
+        let next = crons
+            .iter()
+            .filter_map(|cron| cron.next_after(now).map(|time| (time, cron)))
+            .collect::<Vec<_>>();
+        let time = match next.iter().map(|(time, _)| *time).min() {
+            Some(time) => time,
+            None => return,
+        };
+
+        if let Ok(wait) = (time - Utc::now()).to_std() {
+            thread::sleep(wait);
+        }
+        for (_, cron) in next.iter().filter(|(next_time, _)| *next_time == time) {
+            fire(client, url, cron);
+        }
+    }
+}
+
+fn fire(client: &Client, url: &Url, cron: &Cron) {
+    let mut url = url.clone();
+    url.query_pairs_mut().append_pair("cron", &cron.to_string());
+    let now: DateTime<Local> = Local::now();
+
+    match client.get(url).send() {
+        // [2020-04-20 15:25:54] scheduled "*/5 * * * *" 200 OK
+        Ok(response) => {
+            let status = response.status();
+            println!(
+                "[{}] scheduled \"{}\" {}",
+                now.format("%Y-%m-%d %H:%M:%S"),
+                cron,
+                status
+            );
+            if !status.is_success() {
+                if let Ok(text) = response.text() {
+                    StdOut::warn(text.trim_end());
+                }
+            }
+        }
+        Err(e) => StdOut::warn(&format!(
+            "Could not run scheduled event \"{}\": {}",
+            cron, e
+        )),
+    }
+}
@@ -8,21 +8,13 @@ use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
 
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::commands::kv;
-use crate::kv::bulk::delete;
 use crate::kv::bulk::BATCH_KEY_MAX;
-use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
+use crate::kv::bulk::{delete, file_source};
+use crate::kv::key::KeyList;
+use crate::kv::store::KvStore;
 use crate::terminal::interactive;
 use crate::terminal::message::{Message, StdOut};
-pub fn run(
-    target: &Target,
-    user: &GlobalUser,
-    namespace_id: &str,
-    filename: &Path,
-) -> Result<(), failure::Error> {
-    kv::validate_target(target)?;
-
+pub fn run(store: &dyn KvStore, namespace_id: &str, filename: &Path) -> Result<(), failure::Error> {
     match interactive::confirm(&format!(
         "Are you sure you want to delete all keys in {}?",
         filename.display()
@@ -49,6 +41,54 @@ pub fn run(
         Err(e) => failure::bail!("{}", e),
     };
 
+    delete_keys(store, namespace_id, &file_source(filename)?, keys)
+}
+
+// Deletes every key starting with `prefix`, once the count of them is confirmed.
+pub fn run_prefix(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    prefix: &str,
+) -> Result<(), failure::Error> {
+    let keys = prefix_keys(store, namespace_id, prefix)?;
+    if keys.is_empty() {
+        StdOut::info(&format!("No keys start with \"{}\"", prefix));
+        return Ok(());
+    }
+
+    match interactive::confirm(&format!(
+        "Are you sure you want to delete all {} keys starting with \"{}\"?",
+        keys.len(),
+        prefix
+    )) {
+        Ok(true) => (),
+        Ok(false) => {
+            StdOut::info(&format!("Not deleting keys starting with \"{}\"", prefix));
+            return Ok(());
+        }
+        Err(e) => failure::bail!(e),
+    }
+
+    delete_keys(store, namespace_id, &format!("prefix {}", prefix), keys)
+}
+
+// The names of the keys starting with `prefix`.
+fn prefix_keys(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    prefix: &str,
+) -> Result<Vec<String>, failure::Error> {
+    KeyList::new(store, namespace_id, Some(prefix))
+        .map(|key| key.map(|key| key.name))
+        .collect()
+}
+
+fn delete_keys(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    source: &str,
+    keys: Vec<String>,
+) -> Result<(), failure::Error> {
     let len = keys.len();
 
     StdOut::working(&format!("deleting {} key value pairs", len));
@@ -61,7 +101,7 @@ pub fn run(
         None
     };
 
-    delete(target, user, namespace_id, keys, &progress_bar)?;
+    delete(store, namespace_id, source, keys, &progress_bar)?;
 
     if let Some(pb) = &progress_bar {
         pb.finish_with_message(&format!("deleted {} key value pairs", len));
@@ -70,3 +110,31 @@ pub fn run(
     StdOut::success("Success");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::kv::local::LocalStore;
+    use crate::kv::store::PutOptions;
+
+    #[test]
+    fn it_deletes_only_the_keys_with_the_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::in_dir(dir.path());
+        for key in &["a", "a/1", "a/2", "ab", "b/a/1"] {
+            store
+                .put("ns", key, b"1".to_vec(), &PutOptions::default())
+                .unwrap();
+        }
+
+        let keys = prefix_keys(&store, "ns", "a/").unwrap();
+        assert_eq!(keys, vec!["a/1", "a/2"]);
+
+        store.delete_batch("ns", &keys).unwrap();
+        assert_eq!(
+            prefix_keys(&store, "ns", "").unwrap(),
+            vec!["a", "ab", "b/a/1"]
+        );
+    }
+}
@@ -2,4 +2,7 @@ pub mod delete;
 pub mod put;
 
 pub use delete::run as delete;
+pub use delete::run_prefix as delete_prefix;
 pub use put::run as put;
+pub use put::run_csv as put_csv;
+pub use put::run_dir as put_dir;
@@ -1,60 +1,269 @@
-extern crate base64;
-
-use std::fs;
-use std::fs::metadata;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::iter;
 use std::path::Path;
-
-use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
 
-use crate::commands::kv::validate_target;
-use crate::kv::bulk::put;
-use crate::kv::bulk::BATCH_KEY_MAX;
-use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
+use crate::kv::bulk::{file_source, put_with_metadata};
+use crate::kv::pairs::PairReader;
+use crate::kv::store::{BulkPair, KvStore};
+use crate::sites;
 use crate::terminal::message::{Message, StdErr};
-pub fn run(
-    target: &Target,
-    user: &GlobalUser,
+
+// One row of a CSV file for `kv:bulk put --csv`. Empty cells are left out.
+#[derive(Deserialize)]
+struct CsvRow {
+    key: String,
+    value: String,
+    #[serde(default)]
+    expiration_ttl: Option<u64>,
+    // a JSON value
+    #[serde(default)]
+    metadata: Option<String>,
+}
+
+// A pair to upload, with how far it moves the progress bar along.
+type PairResult = Result<(BulkPair, u64), failure::Error>;
+
+pub fn run(store: &dyn KvStore, namespace_id: &str, filename: &Path) -> Result<(), failure::Error> {
+    let file = open(filename, "a JSON file")?;
+    let len = file.metadata()?.len();
+
+    StdErr::working(&format!(
+        "uploading key value pairs from {}",
+        filename.display()
+    ));
+    // pairs are read as they're uploaded, so the file never has to fit in memory
+    let pairs = PairReader::new(BufReader::new(file))
+        .map(|pair| pair.map(|(pair, size)| (BulkPair::from(pair), size)));
+    let count = upload(store, namespace_id, &file_source(filename)?, pairs, len)?;
+
+    StdErr::success(&format!("Success! Uploaded {} key value pairs", count));
+    Ok(())
+}
+
+// Uploads each file in `directory` to the key of its path in it after `prefix`.
+pub fn run_dir(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    directory: &Path,
+    prefix: &str,
+) -> Result<(), failure::Error> {
+    let files = sites::directory_keys(directory, prefix)?;
+    let mut len = 0;
+    for (path, _) in &files {
+        len += fs::metadata(path)?.len();
+    }
+
+    StdErr::working(&format!(
+        "uploading {} files from {}",
+        files.len(),
+        directory.display()
+    ));
+    // files are read as they're uploaded, and their values base64 encoded so they needn't be text
+    let pairs = files.into_iter().map(|(path, key)| {
+        let value = fs::read(&path)?;
+        let size = value.len() as u64;
+        let pair = BulkPair {
+            key,
+            value: base64::encode(&value),
+            expiration: None,
+            expiration_ttl: None,
+            base64: Some(true),
+            metadata: None,
+        };
+        Ok((pair, size))
+    });
+    let count = upload(store, namespace_id, &file_source(directory)?, pairs, len)?;
+
+    StdErr::success(&format!("Success! Uploaded {} files", count));
+    Ok(())
+}
+
+// Uploads the rows of a CSV file with a header of `key`, `value` and optionally `expiration_ttl`
+// and `metadata`.
+pub fn run_csv(
+    store: &dyn KvStore,
     namespace_id: &str,
     filename: &Path,
 ) -> Result<(), failure::Error> {
-    validate_target(target)?;
-
-    let pairs: Vec<KeyValuePair> = match &metadata(filename) {
-        Ok(file_type) if file_type.is_file() => {
-            let data = fs::read_to_string(filename)?;
-            let data_vec = serde_json::from_str(&data);
-            match data_vec {
-                Ok(data_vec) => Ok(data_vec),
-                Err(_) => Err(failure::format_err!("Failed to decode JSON. Please make sure to follow the format, [{{\"key\": \"test_key\", \"value\": \"test_value\"}}, ...]"))
+    let file = open(filename, "a CSV file")?;
+    let len = file.metadata()?.len();
+    let source = file_source(filename)?;
+    let pairs = csv_pairs(file, filename)?;
+
+    StdErr::working(&format!(
+        "uploading key value pairs from {}",
+        filename.display()
+    ));
+    let count = upload(store, namespace_id, &source, pairs, len)?;
+
+    StdErr::success(&format!("Success! Uploaded {} key value pairs", count));
+    Ok(())
+}
+
+// The rows of a CSV file read from `reader` as pairs, each with the number of bytes it was
+// read from. Rows are read as they're needed.
+fn csv_pairs<'a, R: Read + Send + 'a>(
+    reader: R,
+    filename: &'a Path,
+) -> Result<impl Iterator<Item = PairResult> + Send + 'a, failure::Error> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.byte_headers()?.clone();
+    for column in &["key", "value"] {
+        if !headers.iter().any(|header| header == column.as_bytes()) {
+            failure::bail!(
+                "{} has no {} column. Its first row should name the columns: key, value, and optionally expiration_ttl and metadata",
+                filename.display(),
+                column
+            );
+        }
+    }
+
+    let mut record = csv::ByteRecord::new();
+    let mut position = reader.position().byte();
+    Ok(iter::from_fn(move || {
+        let row = match reader.read_byte_record(&mut record) {
+            Ok(true) => record.deserialize::<CsvRow>(Some(&headers)),
+            Ok(false) => return None,
+            Err(e) => Err(e),
+        };
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                return Some(Err(failure::format_err!(
+                    "Failed to read {}: {}",
+                    filename.display(),
+                    e
+                )))
             }
+        };
+
+        let metadata = match row.metadata {
+            Some(metadata) => match serde_json::from_str(&metadata) {
+                Ok(metadata) => Some(metadata),
+                Err(e) => {
+                    return Some(Err(failure::format_err!(
+                        "The metadata of {} in {} isn't valid JSON: {}",
+                        row.key,
+                        filename.display(),
+                        e
+                    )))
+                }
+            },
+            None => None,
+        };
+        let pair = BulkPair {
+            key: row.key,
+            value: row.value,
+            expiration: None,
+            expiration_ttl: row.expiration_ttl.map(|ttl| ttl as i64),
+            base64: None,
+            metadata,
+        };
+        let read = reader.position().byte();
+        let progress = read - position;
+        position = read;
+        Some(Ok((pair, progress)))
+    }))
+}
+
+fn open(filename: &Path, kind: &str) -> Result<File, failure::Error> {
+    match File::open(filename) {
+        Ok(file) if file.metadata()?.is_file() => Ok(file),
+        Ok(_) => failure::bail!("{} should be {}, but is not", filename.display(), kind),
+        Err(e) => failure::bail!("{}", e),
+    }
+}
+
+// Uploads `pairs` read from `source` with a progress bar out of `len`, returning how many
+// there were.
+fn upload(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    source: &str,
+    pairs: impl Iterator<Item = PairResult> + Send,
+    len: u64,
+) -> Result<usize, failure::Error> {
+    let progress_bar = ProgressBar::new(len);
+    progress_bar.set_style(
+        ProgressStyle::default_bar().template("{wide_bar} {bytes}/{total_bytes}\n{msg}"),
+    );
+
+    let count = AtomicUsize::new(0);
+    let pairs = pairs.inspect(|pair| {
+        if pair.is_ok() {
+            count.fetch_add(1, Ordering::SeqCst);
         }
-        Ok(_) => Err(failure::format_err!(
-            "{} should be a JSON file, but is not",
-            filename.display()
-        )),
-        Err(e) => Err(failure::format_err!("{}", e)),
-    }?;
+    });
+    let result = put_with_metadata(
+        store,
+        namespace_id,
+        source,
+        pairs,
+        &Some(progress_bar.clone()),
+    );
+    progress_bar.finish_and_clear();
+    result?;
 
-    let len = pairs.len();
+    Ok(count.into_inner())
+}
 
-    StdErr::working(&format!("uploading {} key value pairs", len));
-    let progress_bar = if len > BATCH_KEY_MAX {
-        let pb = ProgressBar::new(len as u64);
-        pb.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
-        Some(pb)
-    } else {
-        None
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    put(target, &user, namespace_id, pairs, &progress_bar)?;
+    use serde_json::json;
 
-    if let Some(pb) = &progress_bar {
-        pb.finish_with_message(&format!("uploaded {} key value pairs", len));
+    use crate::kv::local::LocalStore;
+
+    fn read_csv(csv: &str) -> Result<Vec<BulkPair>, failure::Error> {
+        csv_pairs(csv.as_bytes(), Path::new("pairs.csv"))?
+            .map(|pair| pair.map(|(pair, _)| pair))
+            .collect()
     }
 
-    StdErr::success("Success");
-    Ok(())
+    #[test]
+    fn it_rejects_a_csv_without_a_key_or_value_column() {
+        assert!(read_csv("key,expiration_ttl\na,60\n").is_err());
+        assert!(read_csv("value,metadata\n1,{}\n").is_err());
+    }
+
+    #[test]
+    fn it_leaves_out_empty_cells() {
+        let pairs = read_csv("key,value,expiration_ttl,metadata\na,1,,\n").unwrap();
+        assert_eq!(pairs[0].key, "a");
+        assert_eq!(pairs[0].value, "1");
+        assert_eq!(pairs[0].expiration_ttl, None);
+        assert_eq!(pairs[0].metadata, None);
+    }
+
+    #[test]
+    fn it_names_the_key_with_invalid_metadata() {
+        let e = read_csv("key,value,metadata\ngood,1,{}\nbad,2,{oops\n").unwrap_err();
+        assert!(e.to_string().contains("The metadata of bad in pairs.csv"));
+    }
+
+    #[test]
+    fn it_writes_rows_with_their_metadata() {
+        let pairs = read_csv(
+            "key,value,expiration_ttl,metadata\nplain,1,,\ntagged,2,3600,\"{\"\"tag\"\": 1}\"\n",
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::in_dir(dir.path());
+        store.write_batch("ns", &pairs).unwrap();
+
+        let (plain, value) = store.get_with_metadata("ns", "plain").unwrap().unwrap();
+        assert_eq!(value, b"1");
+        assert_eq!(plain.metadata, None);
+        let (tagged, value) = store.get_with_metadata("ns", "tagged").unwrap().unwrap();
+        assert_eq!(value, b"2");
+        assert_eq!(tagged.metadata, Some(json!({ "tag": 1 })));
+        assert!(tagged.expiration.is_some());
+    }
 }
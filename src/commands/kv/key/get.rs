@@ -1,44 +1,134 @@
-// TODO:(gabbi) This file should use cloudflare-rs instead of our http::legacy_auth_client
-// when https://github.com/cloudflare/cloudflare-rs/issues/26 is handled (this is
-// because the GET key operation doesn't return json on success--just the raw
-// value).
-
-use cloudflare::framework::response::ApiFailure;
-
-use crate::commands::kv;
-use crate::http;
-use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
-
-pub fn get(target: &Target, user: &GlobalUser, id: &str, key: &str) -> Result<(), failure::Error> {
-    kv::validate_target(target)?;
-    let api_endpoint = format!(
-        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
-        target.account_id,
-        id,
-        kv::url_encode_key(key)
-    );
-
-    let client = http::legacy_auth_client(&user);
-
-    let res = client.get(&api_endpoint).send()?;
-
-    let response_status = res.status();
-    if response_status.is_success() {
-        let body_text = res.text()?;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::kv::snapshot::SnapshotEntry;
+use crate::kv::store::{KvStore, ListedKey};
+
+pub struct GetOptions {
+    /// Print the key, value, metadata and expiration as JSON.
+    pub with_metadata: bool,
+    /// Print the key and value as JSON.
+    pub json: bool,
+    /// Base64 encode the value, even if it is text.
+    pub base64: bool,
+    /// Write to this file instead of stdout.
+    pub output: Option<PathBuf>,
+}
+
+pub fn get(
+    store: &dyn KvStore,
+    id: &str,
+    key: &str,
+    options: &GetOptions,
+) -> Result<(), failure::Error> {
+    let found = if options.with_metadata {
+        store.get_with_metadata(id, key)?
+    } else {
+        store.get(id, key)?.map(|value| {
+            let listed = ListedKey {
+                name: key.to_string(),
+                expiration: None,
+                metadata: None,
+            };
+            (listed, value)
+        })
+    };
+    let (listed, value) = match found {
+        Some(found) => found,
+        None => failure::bail!(
+            "Key \"{}\" not found. Run `wrangler kv:key list` to see your existing keys",
+            key
+        ),
+    };
+
+    let output = if options.json || options.with_metadata {
+        let mut entry = SnapshotEntry::new(listed, value);
+        if options.base64 {
+            entry = entry.into_base64();
+        }
+        let mut json = serde_json::to_vec(&entry)?;
+        json.push(b'\n');
+        json
+    } else if options.base64 {
+        base64::encode(&value).into_bytes()
+    } else {
+        value
+    };
+
+    match &options.output {
+        Some(path) => fs::write(path, output)?,
         // We don't use message::success because we don't want to include the emoji/formatting
         // in case someone is piping this to stdin
-        print!("{}", &body_text);
-    } else {
-        // This is logic pulled from cloudflare-rs for pretty error formatting right now;
-        // it will be redundant when we switch to using cloudflare-rs for all API requests.
-        let parsed = res.json();
-        let errors = parsed.unwrap_or_default();
-        print!(
-            "{}",
-            kv::format_error(ApiFailure::Error(response_status, errors))
-        );
+        None => io::stdout().write_all(&output)?,
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::kv::local::LocalStore;
+    use crate::kv::store::PutOptions;
+
+    fn get_output(
+        store: &LocalStore,
+        key: &str,
+        with_metadata: bool,
+        json: bool,
+        base64: bool,
+    ) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("value");
+        let options = GetOptions {
+            with_metadata,
+            json,
+            base64,
+            output: Some(output.clone()),
+        };
+        get(store, "ns", key, &options).unwrap();
+        fs::read_to_string(output).unwrap()
+    }
+
+    #[test]
+    fn it_prints_the_value_as_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::in_dir(dir.path());
+        let options = PutOptions {
+            expiration: Some(4_000_000_000),
+            metadata: Some(json!({ "type": "greeting" })),
+            ..PutOptions::default()
+        };
+        store
+            .put("ns", "text", b"hello".to_vec(), &options)
+            .unwrap();
+
+        assert_eq!(get_output(&store, "text", false, false, false), "hello");
+        assert_eq!(get_output(&store, "text", false, false, true), "aGVsbG8=");
+        assert_eq!(
+            get_output(&store, "text", false, true, false),
+            "{\"key\":\"text\",\"value\":\"hello\"}\n"
+        );
+        assert_eq!(
+            get_output(&store, "text", true, false, true),
+            "{\"key\":\"text\",\"value\":\"aGVsbG8=\",\"base64\":true,\"expiration\":4000000000,\"metadata\":{\"type\":\"greeting\"}}\n"
+        );
+    }
+
+    #[test]
+    fn it_fails_for_a_missing_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::in_dir(dir.path());
+        let options = GetOptions {
+            with_metadata: false,
+            json: false,
+            base64: false,
+            output: None,
+        };
+        assert!(get(&store, "ns", "missing", &options).is_err());
+    }
+}
@@ -1,44 +1,138 @@
 extern crate serde_json;
 
-use crate::commands::kv;
-use crate::http;
+use std::io::{self, Write};
+
 use crate::kv::key::KeyList;
-use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
+use crate::kv::store::{KvStore, ListOptions, ListedKey};
 
 // Note: this function only prints keys in json form, given that
 // the number of entries in each json blob is variable (so csv and tsv
 // representation won't make sense)
+//
+// With a limit or a cursor, only that page of keys is listed, and it's printed with the
+// cursor for the next one.
 pub fn list(
-    target: &Target,
-    user: &GlobalUser,
+    store: &dyn KvStore,
+    namespace_id: &str,
+    prefix: Option<&str>,
+    limit: Option<usize>,
+    cursor: Option<&str>,
+    with_metadata: bool,
+) -> Result<(), failure::Error> {
+    let stdout = io::stdout();
+    write_list(
+        &mut stdout.lock(),
+        store,
+        namespace_id,
+        prefix,
+        limit,
+        cursor,
+        with_metadata,
+    )
+}
+
+fn write_list(
+    out: &mut dyn Write,
+    store: &dyn KvStore,
     namespace_id: &str,
     prefix: Option<&str>,
+    limit: Option<usize>,
+    cursor: Option<&str>,
+    with_metadata: bool,
 ) -> Result<(), failure::Error> {
-    kv::validate_target(target)?;
-    let client = http::cf_v4_client(&user)?;
-    let key_list = KeyList::new(target, client, namespace_id, prefix)?;
+    let strip = |mut key: ListedKey| {
+        if !with_metadata {
+            key.metadata = None;
+        }
+        key
+    };
+
+    if limit.is_some() || cursor.is_some() {
+        let mut page = store.list(
+            namespace_id,
+            &ListOptions {
+                prefix: prefix.map(str::to_string),
+                limit,
+                cursor: cursor.map(str::to_string),
+            },
+        )?;
+        page.keys = page.keys.into_iter().map(strip).collect();
+        write!(out, "{}", serde_json::to_string(&page)?)?;
+        return Ok(());
+    }
 
-    print!("["); // Open json list bracket
+    let key_list = KeyList::new(store, namespace_id, prefix);
+
+    write!(out, "[")?; // Open json list bracket
 
     let mut first_key = true;
 
     for key_result in key_list {
-        match key_result {
-            Ok(key) => {
-                if first_key {
-                    first_key = false;
-                } else {
-                    print!(",");
-                }
-
-                print!("{}", serde_json::to_string(&key)?);
-            }
-            Err(e) => print!("{}", kv::format_error(e)),
+        let key = strip(key_result?);
+        if first_key {
+            first_key = false;
+        } else {
+            write!(out, ",")?;
         }
+
+        write!(out, "{}", serde_json::to_string(&key)?)?;
     }
 
-    print!("]"); // Close json list bracket
+    write!(out, "]")?; // Close json list bracket
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::kv::local::LocalStore;
+    use crate::kv::store::{KeyPage, PutOptions};
+
+    fn list_json(
+        store: &LocalStore,
+        limit: Option<usize>,
+        cursor: Option<&str>,
+        with_metadata: bool,
+    ) -> String {
+        let mut out = Vec::new();
+        write_list(&mut out, store, "ns", None, limit, cursor, with_metadata).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn it_lists_a_page_at_a_time_with_a_limit_and_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::in_dir(dir.path());
+        for key in &["a", "b", "c"] {
+            let options = PutOptions {
+                metadata: Some(json!({ "key": key })),
+                ..PutOptions::default()
+            };
+            store.put("ns", key, b"1".to_vec(), &options).unwrap();
+        }
+
+        let first: KeyPage = serde_json::from_str(&list_json(&store, Some(2), None, true)).unwrap();
+        let names: Vec<_> = first.keys.iter().map(|k| k.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(first.keys[0].metadata, Some(json!({ "key": "a" })));
+        assert!(!first.list_complete);
+
+        let cursor = first.cursor.unwrap();
+        let second: KeyPage =
+            serde_json::from_str(&list_json(&store, None, Some(&cursor), false)).unwrap();
+        assert_eq!(second.keys.len(), 1);
+        assert_eq!(second.keys[0].name, "c");
+        assert_eq!(second.keys[0].metadata, None);
+        assert!(second.list_complete);
+        assert_eq!(second.cursor, None);
+
+        assert_eq!(
+            list_json(&store, None, None, false),
+            r#"[{"name":"a"},{"name":"b"},{"name":"c"}]"#
+        );
+    }
+}
@@ -4,6 +4,6 @@ mod list;
 mod put;
 
 pub use delete::delete;
-pub use get::get;
+pub use get::{get, GetOptions};
 pub use list::list;
 pub use put::{parse_metadata, put, KVMetaData};
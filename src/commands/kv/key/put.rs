@@ -1,20 +1,9 @@
-// TODO: (gabbi) This file should use cloudflare-rs instead of our http::legacy_auth_client
-// when https://github.com/cloudflare/cloudflare-rs/issues/26 is handled (this is
-// because the SET key request body is not json--it is the raw value).
-
 use std::fs;
 use std::fs::metadata;
 
-use cloudflare::framework::response::ApiFailure;
-use url::Url;
-
-use crate::commands::kv;
-use crate::http;
-use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
+use crate::kv::store::{KvStore, PutOptions};
 use crate::terminal::message::{Message, StdOut};
 use regex::Regex;
-use reqwest::blocking::multipart;
 
 pub struct KVMetaData {
     pub namespace_id: String,
@@ -47,65 +36,28 @@ pub fn parse_metadata(arg: Option<&str>) -> Result<Option<serde_json::Value>, fa
     }
 }
 
-pub fn put(target: &Target, user: &GlobalUser, data: KVMetaData) -> Result<(), failure::Error> {
-    kv::validate_target(target)?;
-
-    let api_endpoint = format!(
-        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
-        target.account_id,
-        &data.namespace_id,
-        kv::url_encode_key(&data.key)
-    );
-
-    // Add expiration and expiration_ttl query options as necessary.
-    let mut query_params: Vec<(&str, &str)> = vec![];
-
-    if let Some(exp) = &data.expiration {
-        query_params.push(("expiration", exp))
-    };
-    if let Some(ttl) = &data.expiration_ttl {
-        query_params.push(("expiration_ttl", ttl))
+pub fn put(store: &dyn KvStore, data: KVMetaData) -> Result<(), failure::Error> {
+    let options = PutOptions {
+        expiration: parse_seconds("--expiration", &data.expiration)?,
+        expiration_ttl: parse_seconds("--ttl", &data.expiration_ttl)?,
+        metadata: data.metadata.clone(),
     };
-    let url = Url::parse_with_params(&api_endpoint, query_params)?;
+    let value = get_request_body(&data)?;
 
-    let res = get_response(data, user, &url)?;
-
-    let response_status = res.status();
-    if response_status.is_success() {
-        StdOut::success("Success")
-    } else {
-        // This is logic pulled from cloudflare-rs for pretty error formatting right now;
-        // it will be redundant when we switch to using cloudflare-rs for all API requests.
-        let parsed = res.json();
-        let errors = parsed.unwrap_or_default();
-        print!(
-            "{}",
-            kv::format_error(ApiFailure::Error(response_status, errors))
-        );
-    }
+    store.put(&data.namespace_id, &data.key, value, &options)?;
+    StdOut::success("Success");
 
     Ok(())
 }
 
-fn get_response(
-    data: KVMetaData,
-    user: &GlobalUser,
-    url: &Url,
-) -> Result<reqwest::blocking::Response, failure::Error> {
-    let url_into_str = url.to_string();
-    let client = http::legacy_auth_client(user);
-    let value_body = get_request_body(&data)?;
-    let res = match &data.metadata {
-        Some(metadata) => {
-            let value_part = multipart::Part::bytes(value_body);
-            let form = multipart::Form::new()
-                .part("value", value_part)
-                .text("metadata", metadata.to_string());
-            client.put(&url_into_str).multipart(form).send()?
-        }
-        None => client.put(&url_into_str).body(value_body).send()?,
-    };
-    Ok(res)
+fn parse_seconds(arg: &str, value: &Option<String>) -> Result<Option<u64>, failure::Error> {
+    match value {
+        Some(value) => match value.parse() {
+            Ok(seconds) => Ok(Some(seconds)),
+            Err(_) => failure::bail!("{} expects a number of seconds, not {}", arg, value),
+        },
+        None => Ok(None),
+    }
 }
 
 // If is_file is true, overwrite value to be the contents of the given
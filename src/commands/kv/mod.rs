@@ -5,6 +5,9 @@ use cloudflare::framework::response::ApiFailure;
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 
 use crate::http;
+use crate::kv::local::LocalStore;
+use crate::kv::store::{ApiStore, KvStore};
+use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 
 pub mod bulk;
@@ -55,6 +58,17 @@ pub fn validate_target(target: &Target) -> Result<(), failure::Error> {
     }
 }
 
+// The store kv:key and kv:bulk commands work on: the one `wrangler dev --local` uses when
+// `local` is set, and Workers KV otherwise.
+pub fn store(target: &Target, local: bool) -> Result<Box<dyn KvStore>, failure::Error> {
+    if local {
+        Ok(Box::new(LocalStore::new()))
+    } else {
+        let user = GlobalUser::new()?;
+        Ok(Box::new(ApiStore::new(target, &user)?))
+    }
+}
+
 fn check_duplicate_namespaces(target: &Target) -> bool {
     // HashSet for detecting duplicate namespace bindings
     let mut binding_names: HashSet<String> = HashSet::new();
@@ -96,7 +110,7 @@ pub fn get_namespace_id(target: &Target, binding: &str) -> Result<String, failur
 
 const KV_ASCII_SET: &AsciiSet = &CONTROLS.add(b'/');
 
-fn url_encode_key(key: &str) -> String {
+pub(crate) fn url_encode_key(key: &str) -> String {
     utf8_percent_encode(key, KV_ASCII_SET).to_string()
 }
 
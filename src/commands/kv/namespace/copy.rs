@@ -0,0 +1,63 @@
+use crate::kv::bulk::{self, BATCH_KEY_MAX};
+use crate::kv::diff::NamespaceDiff;
+use crate::kv::snapshot::{self, SnapshotEntry};
+use crate::kv::store::KvStore;
+use crate::terminal::message::{Message, StdOut};
+
+use super::diff::print;
+
+// Makes the keys starting with `prefix` in the namespace `to_id` match those in `from_id`,
+// writing only the keys that differ.
+pub fn run(
+    from: &dyn KvStore,
+    from_id: &str,
+    to: &dyn KvStore,
+    to_id: &str,
+    prefix: Option<&str>,
+    dry_run: bool,
+) -> Result<(), failure::Error> {
+    StdOut::working(&format!("Comparing namespace {} with {}", from_id, to_id));
+    let diff = NamespaceDiff::new(from, from_id, to, to_id, prefix)?;
+    if diff.is_empty() {
+        StdOut::info("The namespaces have the same keys and values.");
+        return Ok(());
+    }
+    print(&diff, from_id, to_id);
+    if dry_run {
+        StdOut::info("--dry-run: no changes were made.");
+        return Ok(());
+    }
+
+    StdOut::working(&format!("Copying keys to namespace {}", to_id));
+    let keys: Vec<_> = diff.added.iter().chain(&diff.changed).collect();
+    let (mut copied, mut expired) = (0, 0);
+    let source = format!("copy from {}", from_id);
+    for batch in keys.chunks(BATCH_KEY_MAX) {
+        let mut entries = Vec::with_capacity(batch.len());
+        for key in batch {
+            // keys deleted since they were compared are left out
+            if let Some(value) = from.get(from_id, &key.name)? {
+                entries.push(SnapshotEntry::new((*key).clone(), value));
+            }
+        }
+        let entries = entries.into_iter().map(|entry| Ok((entry, 1)));
+        let (written, skipped) = snapshot::write(to, to_id, &source, entries, &None)?;
+        copied += written;
+        expired += skipped;
+    }
+    bulk::delete(to, to_id, &source, diff.removed.clone(), &None)?;
+
+    if expired > 0 {
+        StdOut::info(&format!(
+            "Skipped {} keys that have expired or are about to",
+            expired
+        ));
+    }
+    StdOut::success(&format!(
+        "Success! Copied {} and deleted {} key(s) in namespace {}.",
+        copied,
+        diff.removed.len(),
+        to_id
+    ));
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use crate::kv::diff::NamespaceDiff;
+use crate::kv::store::KvStore;
+use crate::terminal::message::{Message, StdOut};
+
+// Prints how the keys starting with `prefix` in the namespace `from_id` differ from those
+// in `to_id`.
+pub fn run(
+    from: &dyn KvStore,
+    from_id: &str,
+    to: &dyn KvStore,
+    to_id: &str,
+    prefix: Option<&str>,
+) -> Result<(), failure::Error> {
+    StdOut::working(&format!("Comparing namespace {} with {}", from_id, to_id));
+    let diff = NamespaceDiff::new(from, from_id, to, to_id, prefix)?;
+    if diff.is_empty() {
+        StdOut::info("The namespaces have the same keys and values.");
+    } else {
+        print(&diff, from_id, to_id);
+    }
+    Ok(())
+}
+
+pub(super) fn print(diff: &NamespaceDiff, from_id: &str, to_id: &str) {
+    StdOut::info(&format!(
+        "Keys in namespace {} that differ from {}:",
+        from_id, to_id
+    ));
+    for key in &diff.added {
+        println!("  + {} (added)", key.name);
+    }
+    for key in &diff.changed {
+        println!("  ~ {} (changed)", key.name);
+    }
+    for name in &diff.removed {
+        println!("  - {} (removed)", name);
+    }
+    StdOut::info(&format!(
+        "{} added, {} changed and {} removed",
+        diff.added.len(),
+        diff.changed.len(),
+        diff.removed.len()
+    ));
+}
@@ -0,0 +1,31 @@
+use std::io::{self, Write};
+
+use crate::kv::key::KeyList;
+use crate::kv::snapshot::SnapshotEntry;
+use crate::kv::store::KvStore;
+use crate::terminal::message::{Message, StdErr};
+
+// Writes every key in the namespace to stdout as JSON lines, one `SnapshotEntry` per key,
+// so the output can be redirected to a file and read back by `kv:namespace import`.
+pub fn run(store: &dyn KvStore, namespace_id: &str) -> Result<(), failure::Error> {
+    StdErr::working(&format!("Exporting namespace {}", namespace_id));
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let mut exported = 0;
+    for key in KeyList::new(store, namespace_id, None) {
+        let key = key?;
+        // keys deleted or expired since they were listed are left out
+        let value = match store.get(namespace_id, &key.name)? {
+            Some(value) => value,
+            None => continue,
+        };
+        serde_json::to_writer(&mut out, &SnapshotEntry::new(key, value))?;
+        writeln!(out)?;
+        exported += 1;
+    }
+    out.flush()?;
+
+    StdErr::success(&format!("Exported {} keys", exported));
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::kv::bulk;
+use crate::kv::snapshot::{self, SnapshotEntry};
+use crate::kv::store::KvStore;
+use crate::terminal::message::{Message, StdErr};
+
+// Loads a snapshot written by `kv:namespace export` into the namespace, reading it as it's
+// uploaded. Like any bulk upload, running it again after a failure skips the batches written.
+pub fn run(store: &dyn KvStore, namespace_id: &str, filename: &Path) -> Result<(), failure::Error> {
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(e) => failure::bail!("Could not open {}: {}", filename.display(), e),
+    };
+    let len = file.metadata()?.len();
+    let source = bulk::file_source(filename)?;
+
+    StdErr::working(&format!(
+        "Importing {} into namespace {}",
+        filename.display(),
+        namespace_id
+    ));
+    let progress_bar = ProgressBar::new(len);
+    progress_bar.set_style(
+        ProgressStyle::default_bar().template("{wide_bar} {bytes}/{total_bytes}\n{msg}"),
+    );
+
+    let entries = BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.trim().is_empty() {
+                return None;
+            }
+            match serde_json::from_str::<SnapshotEntry>(&line) {
+                Ok(entry) => Some(Ok((entry, line.len() as u64 + 1))),
+                Err(e) => Some(Err(failure::format_err!(
+                    "Line {} of {} is invalid: {}",
+                    i + 1,
+                    filename.display(),
+                    e
+                ))),
+            }
+        });
+    let (imported, expired) = snapshot::write(
+        store,
+        namespace_id,
+        &source,
+        entries,
+        &Some(progress_bar.clone()),
+    )?;
+
+    progress_bar.finish_and_clear();
+    if expired > 0 {
+        StdErr::info(&format!(
+            "Skipped {} keys that have expired or are about to",
+            expired
+        ));
+    }
+    StdErr::success(&format!("Imported {} keys", imported));
+    Ok(())
+}
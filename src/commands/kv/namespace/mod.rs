@@ -1,7 +1,15 @@
+mod copy;
 mod create;
 mod delete;
+mod diff;
+mod export;
+mod import;
 mod list;
 
+pub use copy::run as copy;
 pub use create::run as create;
 pub use delete::run as delete;
+pub use diff::run as diff;
+pub use export::run as export;
+pub use import::run as import;
 pub use list::run as list;
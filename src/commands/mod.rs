@@ -2,6 +2,7 @@ use std::process::Command;
 
 pub mod build;
 pub mod config;
+pub mod deployments;
 pub mod dev;
 pub mod generate;
 pub mod init;
@@ -13,6 +14,7 @@ pub mod route;
 pub mod secret;
 pub mod subdomain;
 pub mod tail;
+pub mod triggers;
 pub mod whoami;
 
 pub use self::config::global_config;
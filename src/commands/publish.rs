@@ -1,19 +1,23 @@
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 
 use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 
 use crate::build::build_target;
-use crate::deploy::{self, DeploymentSet};
+use crate::deploy::{self, history, DeployTarget, DeploymentSet, RouteOptions, RoutePlan};
 use crate::http::{self, Feature};
 use crate::kv::bulk;
+use crate::kv::store::ApiStore;
 use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
-use crate::sites;
+use crate::settings::toml::{KvNamespace, Route, Target};
+use crate::sites::{self, AssetManifest, SiteCache};
 use crate::terminal::emoji;
 use crate::terminal::message::{Message, Output, StdErr, StdOut};
 use crate::upload;
+use crate::upload::form::{SessionConfig, UploadForm};
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct PublishOutput {
@@ -26,13 +30,26 @@ pub struct PublishOutput {
 pub fn publish(
     user: &GlobalUser,
     target: &mut Target,
-    deployments: DeploymentSet,
+    mut deployments: DeploymentSet,
+    route_options: RouteOptions,
+    from_artifact: Option<&Path>,
+    force_full_sync: bool,
     out: Output,
 ) -> Result<(), failure::Error> {
     validate_target_required_fields_present(target)?;
+    deploy::resolve_zones(user, &mut deployments)?;
+    let unconfigured_zones = unconfigured_zones(target, &configured_zones(&deployments));
 
-    let deploy = |target: &Target| match deploy::worker(&user, &deployments) {
-        Ok(deploy::DeployResults { urls, schedules }) => {
+    let deploy = |target: &Target| match deploy::worker(&user, &deployments, &route_options) {
+        Ok(deploy::DeployResults {
+            mut urls,
+            schedules,
+        }) => {
+            for zone_id in &unconfigured_zones {
+                let results =
+                    deploy::prune_unconfigured_zone(user, zone_id, &target.name, &route_options)?;
+                urls.extend(results.iter().map(ToString::to_string));
+            }
             let result_msg = match (urls.as_slice(), schedules.as_slice()) {
                 ([], []) => "Successfully published your script".to_owned(),
                 ([], schedules) => format!(
@@ -63,29 +80,38 @@ pub fn publish(
         Err(e) => Err(e),
     };
 
-    // Build the script before uploading and log build result
-    let build_result = build_target(&target);
-    match build_result {
-        Ok(msg) => {
-            StdErr::success(&msg);
-            Ok(())
-        }
-        Err(e) => Err(e),
-    }?;
+    match from_artifact {
+        Some(artifact) => StdErr::info(&format!(
+            "Publishing the build artifact in {}",
+            artifact.display()
+        )),
+        None => {
+            // Build the script before uploading and log build result
+            let build_result = build_target(&target);
+            match build_result {
+                Ok(msg) => {
+                    StdErr::success(&msg);
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }?;
 
-    // We verify early here, so we don't perform pre-upload tasks if the upload will fail
-    if let Some(build_config) = &target.build {
-        build_config.verify_upload_dir()?;
+            // We verify early here, so we don't perform pre-upload tasks if the upload will fail
+            if let Some(build_config) = &target.build {
+                build_config.verify_upload_dir()?;
+            }
+        }
     }
 
     if let Some(site_config) = &target.site {
         let path = &site_config.bucket.clone();
         validate_bucket_location(path)?;
+        let retention = sites::Retention::new(site_config)?;
 
         let site_namespace = sites::add_namespace(user, target, false)?;
 
         let (to_upload, to_delete, asset_manifest) =
-            sites::sync(target, user, &site_namespace.id, &path)?;
+            sites::sync(target, user, &site_namespace.id, &path, force_full_sync)?;
 
         // First, upload all existing files in bucket directory
         StdErr::working("Uploading site files");
@@ -98,11 +124,13 @@ pub fn publish(
             None
         };
 
-        bulk::put(
-            target,
-            user,
+        let store = ApiStore::new(target, user)?;
+        let source = path.display().to_string();
+        bulk::put_with_metadata(
+            &store,
             &site_namespace.id,
-            to_upload,
+            &source,
+            sites::file_pairs(to_upload),
             &upload_progress_bar,
         )?;
 
@@ -113,9 +141,23 @@ pub fn publish(
         let upload_client = http::featured_legacy_auth_client(user, Feature::Sites);
 
         // Next, upload and deploy the worker with the updated asset_manifest
-        upload::script(&upload_client, &target, Some(asset_manifest))?;
+        let published = retention.as_ref().map(|_| asset_manifest.clone());
+        let form = upload_script(&upload_client, &target, Some(asset_manifest), from_artifact)?;
 
         deploy(target)?;
+        record_deployment(target, &form, &deployments);
+
+        // Record the files of this version, so that later publishes keep them
+        if let (Some(retention), Some(published)) = (retention, published) {
+            let mut versions = sites::SiteVersions::get(&store, &site_namespace.id)?;
+            versions.record(&published, &retention);
+            if let Err(e) = versions.put(&store, &site_namespace.id) {
+                StdErr::warn(&format!(
+                    "Could not record this version of the site, so the next publish may delete its files: {}",
+                    e
+                ));
+            }
+        }
 
         // Finally, remove any stale files
         if !to_delete.is_empty() {
@@ -132,9 +174,9 @@ pub fn publish(
             };
 
             bulk::delete(
-                target,
-                user,
+                &store,
                 &site_namespace.id,
+                &source,
                 to_delete,
                 &delete_progress_bar,
             )?;
@@ -143,11 +185,242 @@ pub fn publish(
                 pb.finish_with_message("Done deleting");
             }
         }
+        sites::finish_sync(&store, path, &site_namespace.id)?;
     } else {
         let upload_client = http::legacy_auth_client(user);
 
-        upload::script(&upload_client, &target, None)?;
+        let form = upload_script(&upload_client, &target, None, from_artifact)?;
         deploy(target)?;
+        record_deployment(target, &form, &deployments);
+    }
+
+    Ok(())
+}
+
+fn upload_script(
+    client: &Client,
+    target: &Target,
+    asset_manifest: Option<AssetManifest>,
+    from_artifact: Option<&Path>,
+) -> Result<UploadForm, failure::Error> {
+    let form = match from_artifact {
+        Some(artifact) => upload::artifact::read(artifact, target, asset_manifest)?,
+        None => upload::form::build_upload_form(target, asset_manifest, &SessionConfig::default())?,
+    };
+    upload::script_form(client, target, form.to_form()?)?;
+    Ok(form)
+}
+
+fn configured_zones(deployments: &[DeployTarget]) -> Vec<&str> {
+    deployments
+        .iter()
+        .filter_map(|deployment| match deployment {
+            DeployTarget::Zoned(zoned) => Some(zoned.zone_id.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+// The zones that the script's deployment history has routes in, but that it has no routes
+// configured in now, so that routes left there can be reported or pruned.
+fn unconfigured_zones(target: &Target, configured: &[&str]) -> Vec<String> {
+    match history::zones(target) {
+        Ok(zones) => zones
+            .into_iter()
+            .filter(|zone_id| !configured.contains(&zone_id.as_str()))
+            .collect(),
+        Err(e) => {
+            log::info!("Could not read the deployment history: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Not being able to record a deployment shouldn't fail an otherwise successful publish.
+fn record_deployment(target: &Target, form: &UploadForm, deployments: &[DeployTarget]) {
+    match history::record(target, form, deployments, None) {
+        Ok(deployment) => log::info!("Recorded deployment {}", deployment.id),
+        Err(e) => StdErr::warn(&format!(
+            "Could not record this deployment in your deployment history: {}",
+            e
+        )),
+    }
+}
+
+#[derive(Serialize)]
+struct DryRunPlan {
+    name: String,
+    site: Option<SitePlan>,
+    deployments: Vec<PlannedDeployment>,
+}
+
+#[derive(Serialize)]
+struct SitePlan {
+    /// Unset when the namespace doesn't exist yet, and would be created by publishing.
+    namespace_id: Option<String>,
+    upload: Vec<String>,
+    delete: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PlannedDeployment {
+    Zoned {
+        zone_id: String,
+        zone_name: Option<String>,
+        routes: Vec<Route>,
+        /// How the routes compare to the routes already in the zone, when credentials are
+        /// available to look them up.
+        changes: Option<RoutePlan>,
+    },
+    WorkersDev {
+        script_name: String,
+    },
+    Schedule {
+        crons: Vec<String>,
+    },
+}
+
+/// `wrangler publish --dry-run` builds the worker and writes everything publishing would send
+/// to `outdir`, without changing anything:
+///
+/// - `form/`: each part of the script upload form, with `form/form.json` listing them
+/// - `asset-manifest.json`: the site's asset manifest, for projects with a `[site]`
+/// - `plan.json`: the site files that would be uploaded and deleted, and where the worker
+///   would be deployed
+///
+/// Credentials are optional. With them, the plan is compared to what is already deployed.
+pub fn dry_run(
+    user: Option<&GlobalUser>,
+    target: &mut Target,
+    deployments: DeploymentSet,
+    outdir: &Path,
+    out: Output,
+) -> Result<(), failure::Error> {
+    validate_target_required_fields_present(target)?;
+
+    let msg = build_target(target)?;
+    StdErr::success(&msg);
+
+    if let Some(build_config) = &target.build {
+        build_config.verify_upload_dir()?;
+    }
+
+    let (site, asset_manifest) = match &target.site {
+        Some(site_config) => {
+            let path = &site_config.bucket.clone();
+            validate_bucket_location(path)?;
+
+            let site_namespace = match user {
+                Some(user) => sites::find_namespace(user, target, false)?,
+                None => None,
+            };
+
+            let (to_upload, to_delete, asset_manifest) = match (&site_namespace, user) {
+                (Some(site_namespace), Some(user)) => {
+                    sites::plan_sync(target, user, &site_namespace.id, path)?
+                }
+                _ => {
+                    let (to_upload, asset_manifest, _) =
+                        sites::directory_files(target, path, &mut SiteCache::default())?;
+                    (to_upload, Vec::new(), asset_manifest)
+                }
+            };
+
+            let namespace_id = site_namespace.map(|namespace| namespace.id);
+            target.add_kv_namespace(KvNamespace {
+                binding: "__STATIC_CONTENT".to_string(),
+                id: namespace_id.clone().unwrap_or_default(),
+            });
+
+            let site = SitePlan {
+                namespace_id,
+                upload: to_upload.into_iter().map(|file| file.key).collect(),
+                delete: to_delete,
+            };
+            (Some(site), Some(asset_manifest))
+        }
+        None => (None, None),
+    };
+
+    fs::create_dir_all(outdir)?;
+    if let Some(asset_manifest) = &asset_manifest {
+        fs::write(
+            outdir.join("asset-manifest.json"),
+            serde_json::to_string_pretty(asset_manifest)?,
+        )?;
+    }
+
+    let form = upload::form::build_upload_form(target, asset_manifest, &SessionConfig::default())?;
+    form.write(&outdir.join("form"))?;
+
+    let mut planned_deployments = Vec::new();
+    let mut configured = Vec::new();
+    for deployment in deployments {
+        let planned = match deployment {
+            DeployTarget::Zoned(mut zoned) => {
+                let changes = match user {
+                    Some(user) => {
+                        zoned.resolve_zone(user)?;
+                        configured.push(zoned.zone_id.clone());
+                        Some(deploy::plan_routes(user, &zoned)?)
+                    }
+                    None => None,
+                };
+                PlannedDeployment::Zoned {
+                    zone_id: zoned.zone_id,
+                    zone_name: zoned.zone_name,
+                    routes: zoned.routes,
+                    changes,
+                }
+            }
+            DeployTarget::Zoneless(zoneless) => PlannedDeployment::WorkersDev {
+                script_name: zoneless.script_name,
+            },
+            DeployTarget::Schedule(schedule) => PlannedDeployment::Schedule {
+                crons: schedule.crons,
+            },
+        };
+        planned_deployments.push(planned);
+    }
+    if let Some(user) = user {
+        let configured: Vec<&str> = configured.iter().map(String::as_str).collect();
+        for zone_id in unconfigured_zones(target, &configured) {
+            let changes = deploy::plan_unconfigured_zone(user, &zone_id, &target.name)?;
+            if !changes.orphaned.is_empty() {
+                planned_deployments.push(PlannedDeployment::Zoned {
+                    zone_id,
+                    zone_name: None,
+                    routes: Vec::new(),
+                    changes: Some(changes),
+                });
+            }
+        }
+    }
+
+    let plan = DryRunPlan {
+        name: target.name.clone(),
+        site,
+        deployments: planned_deployments,
+    };
+    fs::write(
+        outdir.join("plan.json"),
+        serde_json::to_string_pretty(&plan)?,
+    )?;
+
+    StdErr::success(&format!(
+        "Dry run complete! Wrote what would be published to {}",
+        outdir.display()
+    ));
+    if let Some(site) = &plan.site {
+        StdErr::info(&format!(
+            "{} site file(s) would be uploaded and {} deleted",
+            site.upload.len(),
+            site.delete.len()
+        ));
+    }
+    if out == Output::Json {
+        StdOut::as_json(&plan);
     }
 
     Ok(())
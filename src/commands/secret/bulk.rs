@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use cloudflare::endpoints::workers::{CreateSecretParams, DeleteSecret, ListSecrets};
+use cloudflare::framework::apiclient::ApiClient;
+
+use super::{format_error, is_missing_worker, put_secret, validate_target};
+use crate::http;
+use crate::settings::dev_vars;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdOut};
+
+/// What `wrangler secret bulk` will do to make a script's secrets match a file.
+///
+/// Secret values can't be read back from the API, so every secret in the file that
+/// already exists is updated.
+#[derive(Debug, Default, PartialEq)]
+struct Plan {
+    create: Vec<String>,
+    update: Vec<String>,
+    delete: Vec<String>,
+    // secrets that only exist remotely, and are left alone without `--prune`
+    keep: Vec<String>,
+}
+
+impl Plan {
+    fn new(secrets: &BTreeMap<String, String>, remote: &BTreeSet<String>, prune: bool) -> Plan {
+        let mut plan = Plan::default();
+        for name in secrets.keys() {
+            if remote.contains(name) {
+                plan.update.push(name.clone());
+            } else {
+                plan.create.push(name.clone());
+            }
+        }
+        for name in remote.iter().filter(|name| !secrets.contains_key(*name)) {
+            if prune {
+                plan.delete.push(name.clone());
+            } else {
+                plan.keep.push(name.clone());
+            }
+        }
+        plan
+    }
+
+    fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.update.is_empty() && self.delete.is_empty()
+    }
+
+    fn print(&self, script_name: &str) {
+        StdOut::info(&format!(
+            "Planned changes to secrets on script {}:",
+            script_name
+        ));
+        for name in &self.create {
+            println!("  + {} (create)", name);
+        }
+        for name in &self.update {
+            println!("  ~ {} (update)", name);
+        }
+        for name in &self.delete {
+            println!("  - {} (delete)", name);
+        }
+        if !self.keep.is_empty() {
+            StdOut::info(&format!(
+                "Not deleting {}, which {} not in the file; run with --prune to delete them.",
+                self.keep.join(", "),
+                if self.keep.len() == 1 { "is" } else { "are" }
+            ));
+        }
+    }
+}
+
+/// `wrangler secret bulk` makes a script's secrets match a JSON or dotenv file, or stdin
+/// when `path` is `-`.
+pub fn bulk(
+    path: &str,
+    prune: bool,
+    dry_run: bool,
+    user: &GlobalUser,
+    target: &Target,
+) -> Result<(), failure::Error> {
+    validate_target(target)?;
+
+    let secrets = read_secrets(path)?;
+    let client = http::cf_v4_client(user)?;
+    let remote = list_secret_names(&client, target)?;
+
+    let plan = Plan::new(&secrets, &remote, prune);
+    if plan.is_empty() {
+        StdOut::info("There are no secrets to change.");
+        return Ok(());
+    }
+    plan.print(&target.name);
+    if dry_run {
+        StdOut::info("--dry-run: no changes were made.");
+        return Ok(());
+    }
+
+    for name in plan.create.iter().chain(&plan.update) {
+        StdOut::working(&format!("Uploading secret {}", name));
+        let params = CreateSecretParams {
+            name: name.clone(),
+            text: secrets[name].clone(),
+            secret_type: "secret_text".to_string(),
+        };
+        put_secret(&client, user, target, params)?;
+    }
+
+    for name in &plan.delete {
+        StdOut::working(&format!("Deleting secret {}", name));
+        let response = client.request(&DeleteSecret {
+            account_identifier: &target.account_id,
+            script_name: &target.name,
+            secret_name: name,
+        });
+        if let Err(e) = response {
+            failure::bail!(format_error(e))
+        }
+    }
+
+    StdOut::success(&format!(
+        "Success! Created {}, updated {} and deleted {} secret(s) on script {}.",
+        plan.create.len(),
+        plan.update.len(),
+        plan.delete.len(),
+        target.name
+    ));
+
+    Ok(())
+}
+
+fn list_secret_names(
+    client: &impl ApiClient,
+    target: &Target,
+) -> Result<BTreeSet<String>, failure::Error> {
+    let response = client.request(&ListSecrets {
+        account_identifier: &target.account_id,
+        script_name: &target.name,
+    });
+
+    match response {
+        Ok(success) => Ok(success.result.into_iter().map(|s| s.name).collect()),
+        // a draft of the worker is created along with the first secret
+        Err(e) if is_missing_worker(&e) => Ok(BTreeSet::new()),
+        Err(e) => failure::bail!(format_error(e)),
+    }
+}
+
+fn read_secrets(path: &str) -> Result<BTreeMap<String, String>, failure::Error> {
+    let (contents, is_json) = if path == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        let is_json = contents.trim_start().starts_with('{');
+        (contents, is_json)
+    } else {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => failure::bail!("Could not read {}: {}", path, e),
+        };
+        let is_json = match Path::new(path).extension() {
+            Some(extension) => extension == "json",
+            None => contents.trim_start().starts_with('{'),
+        };
+        (contents, is_json)
+    };
+
+    let source = if path == "-" { "stdin" } else { path };
+    match parse_secrets(&contents, is_json) {
+        Ok(secrets) => Ok(secrets),
+        Err(e) => failure::bail!("Could not read secrets from {}: {}", source, e),
+    }
+}
+
+// Parses a JSON object of names to values, or a dotenv file.
+fn parse_secrets(
+    contents: &str,
+    is_json: bool,
+) -> Result<BTreeMap<String, String>, failure::Error> {
+    let secrets: BTreeMap<String, String> = if is_json {
+        let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(contents)?;
+        let mut secrets = BTreeMap::new();
+        for (name, value) in object {
+            match value {
+                serde_json::Value::String(value) => secrets.insert(name, value),
+                _ => failure::bail!("the value of {} must be a string", name),
+            };
+        }
+        secrets
+    } else {
+        dev_vars::parse(contents)?.into_iter().collect()
+    };
+
+    if let Some((name, _)) = secrets.iter().find(|(_, value)| value.is_empty()) {
+        failure::bail!("the secret {} is empty", name)
+    }
+
+    Ok(secrets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn it_plans_changes_against_remote_secrets() {
+        let secrets = parse_secrets("A=1\nB=2", false).unwrap();
+        let remote: BTreeSet<String> = names(&["B", "C"]).into_iter().collect();
+
+        assert_eq!(
+            Plan::new(&secrets, &remote, false),
+            Plan {
+                create: names(&["A"]),
+                update: names(&["B"]),
+                delete: vec![],
+                keep: names(&["C"]),
+            }
+        );
+        assert_eq!(Plan::new(&secrets, &remote, true).delete, names(&["C"]));
+    }
+
+    #[test]
+    fn it_parses_json_and_dotenv_secrets() {
+        let json = parse_secrets(r#"{"API_KEY": "abc", "TOKEN": "xyz"}"#, true).unwrap();
+        let dotenv = parse_secrets("API_KEY=abc\nTOKEN='xyz'", false).unwrap();
+        assert_eq!(json, dotenv);
+
+        assert!(parse_secrets(r#"{"PORT": 8080}"#, true).is_err());
+        assert!(parse_secrets("EMPTY=", false).is_err());
+    }
+}
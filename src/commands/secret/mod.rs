@@ -1,3 +1,7 @@
+mod bulk;
+
+pub use bulk::bulk;
+
 use cloudflare::endpoints::workers::{CreateSecret, CreateSecretParams, DeleteSecret, ListSecrets};
 use cloudflare::framework::apiclient::ApiClient;
 use cloudflare::framework::response::ApiFailure;
@@ -53,18 +57,20 @@ pub fn upload_draft_worker(
     user: &GlobalUser,
     target: &Target,
 ) -> Option<Result<(), failure::Error>> {
+    if is_missing_worker(e) {
+        StdOut::working(&format!("Worker {} doesn't exist in the API yet. Creating a draft Worker so we can create new secret.", target.name));
+        let upload_client = http::legacy_auth_client(user);
+        Some(upload::script(&upload_client, target, None))
+    } else {
+        None
+    }
+}
+
+// The API returns error code 10007 for scripts that don't exist.
+fn is_missing_worker(e: &ApiFailure) -> bool {
     match e {
-        ApiFailure::Error(_, api_errors) => {
-            let error = &api_errors.errors[0];
-            if error.code == 10007 {
-                StdOut::working(&format!("Worker {} doesn't exist in the API yet. Creating a draft Worker so we can create new secret.", target.name));
-                let upload_client = http::legacy_auth_client(user);
-                Some(upload::script(&upload_client, target, None))
-            } else {
-                None
-            }
-        }
-        ApiFailure::Invalid(_) => None,
+        ApiFailure::Error(_, api_errors) => api_errors.errors[0].code == 10007,
+        ApiFailure::Invalid(_) => false,
     }
 }
 
@@ -93,6 +99,20 @@ pub fn create_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
         secret_type: "secret_text".to_string(),
     };
 
+    put_secret(&client, user, target, params)?;
+    StdOut::success(&format!("Success! Uploaded secret {}.", name));
+
+    Ok(())
+}
+
+// put_secret creates or updates a secret, creating a draft of the worker first if it doesn't
+// exist yet.
+fn put_secret(
+    client: &impl ApiClient,
+    user: &GlobalUser,
+    target: &Target,
+    params: CreateSecretParams,
+) -> Result<(), failure::Error> {
     let response = client.request(&CreateSecret {
         account_identifier: &target.account_id,
         script_name: &target.name,
@@ -100,7 +120,7 @@ pub fn create_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
     });
 
     match response {
-        Ok(_) => StdOut::success(&format!("Success! Uploaded secret {}.", name)),
+        Ok(_) => Ok(()),
         Err(e) => match upload_draft_worker(&e, user, target) {
             None => failure::bail!(format_error(e)),
             Some(draft_upload_response) => match draft_upload_response {
@@ -112,7 +132,7 @@ pub fn create_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
                     });
 
                     match retry_response {
-                        Ok(_) => StdOut::success(&format!("Success! Uploaded secret {}.", name)),
+                        Ok(_) => Ok(()),
                         Err(e) => failure::bail!(format_error(e)),
                     }
                 }
@@ -120,8 +140,6 @@ pub fn create_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
             },
         },
     }
-
-    Ok(())
 }
 
 pub fn delete_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(), failure::Error> {
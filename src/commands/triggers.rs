@@ -0,0 +1,39 @@
+use chrono::{Local, Utc};
+
+use crate::deploy::cron::Cron;
+use crate::deploy::{DeployTarget, DeploymentSet};
+use crate::terminal::message::{Message, StdOut};
+
+/// `wrangler triggers next` prints the next `count` times each of the crons in `[triggers]`
+/// fires, in UTC and local time.
+pub fn next(deployments: &DeploymentSet, count: usize) -> Result<(), failure::Error> {
+    let crons = deployments.iter().find_map(|deployment| match deployment {
+        DeployTarget::Schedule(schedule) => Some(&schedule.crons),
+        _ => None,
+    });
+    let crons = match crons {
+        Some(crons) if !crons.is_empty() => crons,
+        _ => failure::bail!("There are no crons in the [triggers] of your configuration file."),
+    };
+
+    let now = Utc::now();
+    for expression in crons {
+        let cron: Cron = expression.parse()?;
+        let times: Vec<_> = cron.upcoming(now).take(count).collect();
+        if times.is_empty() {
+            StdOut::warn(&format!("\"{}\" will never fire", cron));
+            continue;
+        }
+
+        StdOut::info(&format!("\"{}\" fires next at:", cron));
+        for time in times {
+            println!(
+                "  {}  ({})",
+                time.format("%a %Y-%m-%d %H:%M UTC"),
+                time.with_timezone(&Local).format("%a %Y-%m-%d %H:%M %:z")
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,398 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+
+// how far ahead `next_after` looks before deciding an expression never fires again; long
+// enough for the calendar to repeat, so expressions like `0 0 29 2 5#5` are found
+const SEARCH_DAYS: i64 = 366 * 30;
+
+/// A cron expression in the dialect accepted by Workers: five fields for the minute, hour,
+/// day of month, month and day of week, evaluated in UTC.
+///
+/// Each field is `*` or a list of values and ranges, each with an optional `/step`. Months
+/// can be named (`JAN`-`DEC`), and so can days of the week (`SUN`-`SAT`, where `1` is Sunday
+/// and `7` is Saturday). The day of month also takes `L` (the last day), `LW` (the last
+/// weekday) and `15W` (the weekday nearest the 15th); the day of week takes `5L` (the last
+/// Thursday of the month) and `2#3` (the third Monday). When both day fields are restricted,
+/// a day matching either of them fires.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: DaysOfMonth,
+    months: u64,
+    days_of_week: DaysOfWeek,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct DaysOfMonth {
+    any: bool,
+    days: u64,
+    last: bool,
+    last_weekday: bool,
+    // the days given as `<day>W`
+    nearest_weekday: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct DaysOfWeek {
+    any: bool,
+    days: u64,
+    // the days given as `<day>L`
+    last: u64,
+    // `<day>#<n>`, as (day, n)
+    nth: Vec<(u32, u32)>,
+}
+
+struct Field {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+}
+
+const MINUTE: Field = Field {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const HOUR: Field = Field {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+const DAY_OF_MONTH: Field = Field {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+const MONTH: Field = Field {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &[
+        "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+    ],
+};
+const DAY_OF_WEEK: Field = Field {
+    name: "day of week",
+    min: 1,
+    max: 7,
+    names: &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"],
+};
+
+impl Field {
+    fn value(&self, value: &str) -> Result<u32, failure::Error> {
+        let upper = value.to_ascii_uppercase();
+        if let Some(i) = self.names.iter().position(|name| *name == upper) {
+            return Ok(self.min + i as u32);
+        }
+        let parsed = match value.parse::<u32>() {
+            Ok(parsed) => parsed,
+            Err(_) => failure::bail!("{} is not a valid {}", value, self.name),
+        };
+        if parsed < self.min || parsed > self.max {
+            failure::bail!(
+                "{} is out of range for the {} ({}-{})",
+                value,
+                self.name,
+                self.min,
+                self.max
+            )
+        }
+        Ok(parsed)
+    }
+
+    // `*`, `5`, `1-5` or `MON-FRI`, each with an optional `/step`
+    fn range(&self, item: &str) -> Result<u64, failure::Error> {
+        let (range, step) = match item.find('/') {
+            Some(i) => {
+                let step = &item[i + 1..];
+                match step.parse::<u32>() {
+                    Ok(step) if step > 0 => (&item[..i], Some(step)),
+                    _ => failure::bail!("{} is not a valid step for the {}", step, self.name),
+                }
+            }
+            None => (item, None),
+        };
+
+        let (start, end) = if range == "*" {
+            (self.min, self.max)
+        } else if let Some(i) = range.find('-') {
+            (self.value(&range[..i])?, self.value(&range[i + 1..])?)
+        } else {
+            let start = self.value(range)?;
+            // `5/15` is every 15 from 5 to the end of the range
+            (start, if step.is_some() { self.max } else { start })
+        };
+        if start > end {
+            failure::bail!("{} is not a valid range for the {}", range, self.name)
+        }
+
+        let mut mask = 0;
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << value;
+        }
+        Ok(mask)
+    }
+
+    fn parse(&self, field: &str) -> Result<u64, failure::Error> {
+        let mut mask = 0;
+        for item in field.split(',') {
+            mask |= self.range(item)?;
+        }
+        Ok(mask)
+    }
+}
+
+impl DaysOfMonth {
+    fn parse(field: &str) -> Result<Self, failure::Error> {
+        let mut days = DaysOfMonth {
+            any: field == "*" || field == "?",
+            ..DaysOfMonth::default()
+        };
+        if field == "?" {
+            return Ok(days);
+        }
+        for item in field.split(',') {
+            let upper = item.to_ascii_uppercase();
+            if upper == "L" {
+                days.last = true;
+            } else if upper == "LW" {
+                days.last_weekday = true;
+            } else if let Some(day) = upper.strip_suffix('W') {
+                days.nearest_weekday |= 1 << DAY_OF_MONTH.value(day)?;
+            } else {
+                days.days |= DAY_OF_MONTH.range(item)?;
+            }
+        }
+        Ok(days)
+    }
+
+    fn matches(&self, date: NaiveDate) -> bool {
+        let day = date.day();
+        let last_day = last_day_of_month(date);
+        self.days & (1 << day) != 0
+            || (self.last && day == last_day)
+            || (self.last_weekday && nearest_weekday(date, last_day) == Some(day))
+            || (1..=31).any(|n| {
+                self.nearest_weekday & (1 << n) != 0 && nearest_weekday(date, n) == Some(day)
+            })
+    }
+}
+
+impl DaysOfWeek {
+    fn parse(field: &str) -> Result<Self, failure::Error> {
+        let mut days = DaysOfWeek {
+            any: field == "*" || field == "?",
+            ..DaysOfWeek::default()
+        };
+        if field == "?" {
+            return Ok(days);
+        }
+        for item in field.split(',') {
+            let upper = item.to_ascii_uppercase();
+            if upper == "L" {
+                // on its own, the last day of the week
+                days.days |= 1 << DAY_OF_WEEK.max;
+            } else if let Some(day) = upper.strip_suffix('L') {
+                days.last |= 1 << DAY_OF_WEEK.value(day)?;
+            } else if let Some(i) = upper.find('#') {
+                let day = DAY_OF_WEEK.value(&upper[..i])?;
+                match upper[i + 1..].parse::<u32>() {
+                    Ok(n) if (1..=5).contains(&n) => days.nth.push((day, n)),
+                    _ => failure::bail!("{} is not a valid day of week", item),
+                }
+            } else {
+                days.days |= DAY_OF_WEEK.range(item)?;
+            }
+        }
+        Ok(days)
+    }
+
+    fn matches(&self, date: NaiveDate) -> bool {
+        let day = date.weekday().number_from_sunday();
+        let week = (date.day() - 1) / 7 + 1;
+        self.days & (1 << day) != 0
+            || (self.last & (1 << day) != 0 && date.day() + 7 > last_day_of_month(date))
+            || self.nth.contains(&(day, week))
+    }
+}
+
+impl Cron {
+    /// The first time after `after` that the expression fires, if it ever does.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc() + Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.months & (1 << date.month()) != 0 && self.day_matches(date) {
+                let from = if date == start.date() {
+                    (start.hour(), start.minute())
+                } else {
+                    (0, 0)
+                };
+                if let Some((hour, minute)) = self.first_time_from(from) {
+                    return Some(Utc.from_utc_datetime(&date.and_hms_opt(hour, minute, 0)?));
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// Every time after `after` that the expression fires, in order.
+    pub fn upcoming(&self, after: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        std::iter::successors(self.next_after(after), move |time| self.next_after(*time))
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        match (self.days_of_month.any, self.days_of_week.any) {
+            (true, true) => true,
+            (true, false) => self.days_of_week.matches(date),
+            (false, true) => self.days_of_month.matches(date),
+            (false, false) => self.days_of_month.matches(date) || self.days_of_week.matches(date),
+        }
+    }
+
+    fn first_time_from(&self, (from_hour, from_minute): (u32, u32)) -> Option<(u32, u32)> {
+        for hour in (from_hour..24).filter(|hour| self.hours & (1 << hour) != 0) {
+            let from = if hour == from_hour { from_minute } else { 0 };
+            if let Some(minute) = (from..60).find(|minute| self.minutes & (1 << minute) != 0) {
+                return Some((hour, minute));
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for Cron {
+    type Err = failure::Error;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        match parse_fields(expression) {
+            Ok(cron) => Ok(cron),
+            Err(e) => failure::bail!("invalid cron expression \"{}\": {}", expression, e),
+        }
+    }
+}
+
+fn parse_fields(expression: &str) -> Result<Cron, failure::Error> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    match fields.as_slice() {
+        [minute, hour, day_of_month, month, day_of_week] => Ok(Cron {
+            expression: expression.to_string(),
+            minutes: MINUTE.parse(minute)?,
+            hours: HOUR.parse(hour)?,
+            days_of_month: DaysOfMonth::parse(day_of_month)?,
+            months: MONTH.parse(month)?,
+            days_of_week: DaysOfWeek::parse(day_of_week)?,
+        }),
+        _ => failure::bail!(
+            "expected 5 fields (minute, hour, day of month, month and day of week), found {}",
+            fields.len()
+        ),
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(28, |last| last.day())
+}
+
+// The weekday nearest to `day` in `date`'s month, without leaving the month.
+fn nearest_weekday(date: NaiveDate, day: u32) -> Option<u32> {
+    let last_day = last_day_of_month(date);
+    if day > last_day {
+        return None;
+    }
+    let weekday = date.with_day(day)?.weekday().number_from_sunday();
+    Some(match weekday {
+        // Saturday
+        7 if day == 1 => 3,
+        7 => day - 1,
+        // Sunday
+        1 if day == last_day => day - 2,
+        1 => day + 1,
+        _ => day,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(expression: &str, after: &str, count: usize) -> Vec<String> {
+        let cron: Cron = expression.parse().unwrap();
+        let after = DateTime::parse_from_rfc3339(after)
+            .unwrap()
+            .with_timezone(&Utc);
+        cron.upcoming(after)
+            .take(count)
+            .map(|time| time.format("%Y-%m-%d %H:%M %a").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_finds_the_next_times_an_expression_fires() {
+        assert_eq!(
+            next("*/20 9-10 * * MON-FRI", "2021-01-01T10:30:00Z", 3),
+            vec![
+                "2021-01-01 10:40 Fri",
+                "2021-01-04 09:00 Mon",
+                "2021-01-04 09:20 Mon"
+            ]
+        );
+        assert_eq!(
+            next("0 0 L * *", "2020-01-31T00:00:00Z", 2),
+            vec!["2020-02-29 00:00 Sat", "2020-03-31 00:00 Tue"]
+        );
+        assert_eq!(
+            next("0 12 15W,LW * *", "2021-05-01T00:00:00Z", 2),
+            vec!["2021-05-14 12:00 Fri", "2021-05-31 12:00 Mon"]
+        );
+        assert_eq!(
+            next("30 8 * JAN 2#1,6L", "2021-01-01T00:00:00Z", 2),
+            vec!["2021-01-04 08:30 Mon", "2021-01-29 08:30 Fri"]
+        );
+        // either day field matches when both are restricted
+        assert_eq!(
+            next("0 0 1 * SUN", "2021-02-25T00:00:00Z", 2),
+            vec!["2021-02-28 00:00 Sun", "2021-03-01 00:00 Mon"]
+        );
+        assert_eq!(next("0 0 30 2 *", "2021-01-01T00:00:00Z", 1).len(), 0);
+    }
+
+    #[test]
+    fn it_rejects_invalid_expressions() {
+        for expression in &[
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * * MON#6",
+            "* * * FOO *",
+        ] {
+            assert!(expression.parse::<Cron>().is_err(), "{}", expression);
+        }
+    }
+}
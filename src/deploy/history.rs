@@ -0,0 +1,265 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{DeployTarget, DeploymentSet, ScheduleTarget, ZonedTarget, ZonelessTarget};
+use crate::settings::get_wrangler_home_dir;
+use crate::settings::toml::{Route, Target};
+use crate::upload::form::UploadForm;
+
+const DEPLOYMENT_FILE: &str = "deployment.json";
+const FORM_DIR: &str = "form";
+// older deployments are removed once a script has this many
+const HISTORY_LIMIT: usize = 20;
+
+/// A record of one publish of a script, kept under
+/// `$WRANGLER_HOME/deployments/<account_id>/<script name>/<id>/` along with the form that
+/// was uploaded, so that it can be published again.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Deployment {
+    /// Starts with the time of the deployment, so ids sort oldest first.
+    pub id: String,
+    /// RFC 3339
+    pub timestamp: String,
+    pub form_hash: String,
+    pub bindings: serde_json::Value,
+    pub workers_dev: bool,
+    pub routes: Vec<ZoneRoutes>,
+    /// Unset when the deployment didn't change the script's schedules.
+    pub crons: Option<Vec<String>>,
+    pub git_commit: Option<String>,
+    /// The deployment this one rolled back to, if it was made by `wrangler rollback`.
+    pub rollback_of: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ZoneRoutes {
+    pub zone_id: String,
+    pub patterns: Vec<String>,
+}
+
+impl Deployment {
+    /// Where the deployment was published, to deploy it there again.
+    pub fn deploy_targets(&self, target: &Target) -> DeploymentSet {
+        let mut deploy_targets = Vec::new();
+        for zone in &self.routes {
+            let routes = zone
+                .patterns
+                .iter()
+                .map(|pattern| Route {
+                    id: None,
+                    script: Some(target.name.clone()),
+                    pattern: pattern.clone(),
+                })
+                .collect();
+            deploy_targets.push(DeployTarget::Zoned(ZonedTarget {
+                zone_id: zone.zone_id.clone(),
+                zone_name: None,
+                routes,
+            }));
+        }
+        if self.workers_dev {
+            deploy_targets.push(DeployTarget::Zoneless(ZonelessTarget {
+                account_id: target.account_id.clone(),
+                script_name: target.name.clone(),
+            }));
+        }
+        if let Some(crons) = &self.crons {
+            deploy_targets.push(DeployTarget::Schedule(ScheduleTarget {
+                account_id: target.account_id.clone(),
+                script_name: target.name.clone(),
+                crons: crons.clone(),
+            }));
+        }
+        deploy_targets
+    }
+}
+
+/// Records that `form` was uploaded as `target`'s script and deployed to `deploy_targets`.
+pub fn record(
+    target: &Target,
+    form: &UploadForm,
+    deploy_targets: &[DeployTarget],
+    rollback_of: Option<&str>,
+) -> Result<Deployment, failure::Error> {
+    record_in(&history_dir(target)?, form, deploy_targets, rollback_of)
+}
+
+fn record_in(
+    history_dir: &Path,
+    form: &UploadForm,
+    deploy_targets: &[DeployTarget],
+    rollback_of: Option<&str>,
+) -> Result<Deployment, failure::Error> {
+    let now = Utc::now();
+    let form_hash = form.content_hash()?;
+
+    let bindings = match form.part("metadata") {
+        Some(metadata) => {
+            let metadata: serde_json::Value =
+                serde_json::from_str(&metadata.content.read_to_string()?)?;
+            metadata["bindings"].clone()
+        }
+        None => serde_json::Value::Null,
+    };
+
+    let mut deployment = Deployment {
+        id: format!("{}-{}", now.format("%Y%m%dT%H%M%S%6fZ"), &form_hash[..8]),
+        timestamp: now.to_rfc3339(),
+        form_hash,
+        bindings,
+        workers_dev: false,
+        routes: Vec::new(),
+        crons: None,
+        git_commit: git_commit(),
+        rollback_of: rollback_of.map(str::to_string),
+    };
+    for deploy_target in deploy_targets {
+        match deploy_target {
+            DeployTarget::Zoned(zoned) => deployment.routes.push(ZoneRoutes {
+                zone_id: zoned.zone_id.clone(),
+                patterns: zoned.routes.iter().map(|r| r.pattern.clone()).collect(),
+            }),
+            DeployTarget::Zoneless(_) => deployment.workers_dev = true,
+            DeployTarget::Schedule(schedule) => deployment.crons = Some(schedule.crons.clone()),
+        }
+    }
+
+    let dir = history_dir.join(&deployment.id);
+    form.write(&dir.join(FORM_DIR))?;
+    fs::write(
+        dir.join(DEPLOYMENT_FILE),
+        serde_json::to_string_pretty(&deployment)?,
+    )?;
+
+    let history = list_in(history_dir)?;
+    for old in history.iter().skip(HISTORY_LIMIT) {
+        log::info!("Removing deployment {} from history", old.id);
+        fs::remove_dir_all(history_dir.join(&old.id))?;
+    }
+
+    Ok(deployment)
+}
+
+/// Every recorded deployment of `target`'s script, newest first.
+pub fn list(target: &Target) -> Result<Vec<Deployment>, failure::Error> {
+    list_in(&history_dir(target)?)
+}
+
+/// The ids of the zones that `target`'s recorded deployments had routes in.
+pub fn zones(target: &Target) -> Result<Vec<String>, failure::Error> {
+    let mut zones: Vec<String> = Vec::new();
+    for deployment in list(target)? {
+        for zone in deployment.routes {
+            if !zones.contains(&zone.zone_id) {
+                zones.push(zone.zone_id);
+            }
+        }
+    }
+    Ok(zones)
+}
+
+fn list_in(history_dir: &Path) -> Result<Vec<Deployment>, failure::Error> {
+    if !history_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut deployments = Vec::new();
+    for entry in fs::read_dir(history_dir)? {
+        let path = entry?.path().join(DEPLOYMENT_FILE);
+        if path.is_file() {
+            match serde_json::from_str(&fs::read_to_string(&path)?) {
+                Ok(deployment) => deployments.push(deployment),
+                Err(e) => log::info!("Skipping {}: {}", path.display(), e),
+            }
+        }
+    }
+    deployments.sort_by(|a: &Deployment, b: &Deployment| b.id.cmp(&a.id));
+
+    Ok(deployments)
+}
+
+/// The form that was uploaded by a recorded deployment.
+pub fn form(target: &Target, deployment: &Deployment) -> Result<UploadForm, failure::Error> {
+    UploadForm::read(&history_dir(target)?.join(&deployment.id).join(FORM_DIR))
+}
+
+fn history_dir(target: &Target) -> Result<PathBuf, failure::Error> {
+    Ok(get_wrangler_home_dir()?
+        .join("deployments")
+        .join(&target.account_id)
+        .join(&target.name))
+}
+
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upload::form::PartContent;
+
+    #[test]
+    fn it_records_deployments_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = Target {
+            account_id: "account".to_string(),
+            name: "worker".to_string(),
+            ..Target::default()
+        };
+        let deploy_targets = vec![
+            DeployTarget::Zoned(ZonedTarget {
+                zone_id: "zone".to_string(),
+                zone_name: None,
+                routes: vec![Route {
+                    id: None,
+                    script: Some("worker".to_string()),
+                    pattern: "example.com/*".to_string(),
+                }],
+            }),
+            DeployTarget::Schedule(ScheduleTarget {
+                account_id: "account".to_string(),
+                script_name: "worker".to_string(),
+                crons: vec!["0 * * * *".to_string()],
+            }),
+        ];
+
+        let mut form = UploadForm::default();
+        form.text(
+            "metadata",
+            "metadata.json",
+            "application/json",
+            r#"{"body_part":"index","bindings":[{"type":"plain_text","name":"A","text":"1"}]}"#
+                .to_string(),
+        );
+        form.text(
+            "index",
+            "index.js",
+            "application/javascript",
+            "1".to_string(),
+        );
+        let first = record_in(dir.path(), &form, &deploy_targets, None).unwrap();
+
+        form.parts[1].content = PartContent::Text("2".to_string());
+        let second = record_in(dir.path(), &form, &[], Some(&first.id)).unwrap();
+        assert_ne!(first.form_hash, second.form_hash);
+
+        let history = list_in(dir.path()).unwrap();
+        assert_eq!(history, vec![second, first.clone()]);
+        assert_eq!(first.bindings[0]["name"], "A");
+        assert_eq!(first.deploy_targets(&target), deploy_targets);
+    }
+}
@@ -1,9 +1,14 @@
+pub mod cron;
+pub mod history;
 mod schedule;
 mod zoned;
 mod zoneless;
 
 pub use schedule::ScheduleTarget;
-pub use zoned::ZonedTarget;
+pub use zoned::{
+    plan_routes, plan_unconfigured_zone, prune_unconfigured_zone, RouteAction, RoutePlan,
+    RouteUploadResult, ZonedTarget,
+};
 pub use zoneless::ZonelessTarget;
 
 use crate::settings::global_user::GlobalUser;
@@ -18,15 +23,38 @@ pub enum DeployTarget {
     Schedule(ScheduleTarget),
 }
 
+/// What to do with routes in a zone that differ from the configured ones.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RouteOptions {
+    /// Delete routes that point to the script, but are no longer configured.
+    pub prune: bool,
+    /// Reassign configured routes that point to another script.
+    pub force: bool,
+}
+
+/// Looks up the ids of zones that routes are deployed to by name.
+pub fn resolve_zones(
+    user: &GlobalUser,
+    deploy_targets: &mut [DeployTarget],
+) -> Result<(), failure::Error> {
+    for target in deploy_targets {
+        if let DeployTarget::Zoned(zoned) = target {
+            zoned.resolve_zone(user)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn worker(
     user: &GlobalUser,
     deploy_targets: &[DeployTarget],
+    route_options: &RouteOptions,
 ) -> Result<DeployResults, failure::Error> {
     let mut results = DeployResults::default();
     for target in deploy_targets {
         match target {
             DeployTarget::Zoned(zoned) => {
-                let route_urls = zoned.deploy(user)?;
+                let route_urls = zoned.deploy(user, route_options)?;
                 results.urls.extend(route_urls);
             }
             DeployTarget::Zoneless(zoneless) => {
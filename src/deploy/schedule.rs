@@ -1,3 +1,4 @@
+use super::cron::Cron;
 use crate::http;
 use crate::settings::global_user::GlobalUser;
 
@@ -14,8 +15,9 @@ impl ScheduleTarget {
         script_name: String,
         crons: Vec<String>,
     ) -> Result<Self, failure::Error> {
-        // TODO: add validation for expressions before pushing them to the API
-        // we can do this once the cron parser is open sourced
+        for cron in &crons {
+            cron.parse::<Cron>()?;
+        }
         Ok(Self {
             account_id,
             script_name,
@@ -2,83 +2,314 @@ use std::fmt;
 
 use serde::Serialize;
 
-use cloudflare::endpoints::workers::{CreateRoute, CreateRouteParams, ListRoutes};
+use cloudflare::endpoints::workers::{CreateRoute, CreateRouteParams, DeleteRoute, ListRoutes};
+use cloudflare::endpoints::zone::{ListZones, ListZonesParams};
 use cloudflare::framework::apiclient::ApiClient;
 
 use crate::http;
 use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::{Route, RouteConfig};
-use crate::terminal::message::{Message, StdOut};
+use crate::settings::toml::{Route, RouteConfig, Zone};
+use crate::terminal::message::{Message, StdErr, StdOut};
+
+use super::RouteOptions;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ZonedTarget {
+    /// Empty until `resolve_zone` looks it up, when the zone was given by `zone_name`.
     pub zone_id: String,
+    pub zone_name: Option<String>,
     pub routes: Vec<Route>,
 }
 
 impl ZonedTarget {
-    pub fn build(script_name: &str, route_config: &RouteConfig) -> Result<Self, failure::Error> {
-        match route_config.zone_id.as_ref() {
-            Some(zone_id) if !zone_id.is_empty() => {
-                let new_route = |route: &String| Route {
-                    id: None,
-                    script: Some(script_name.to_string()),
-                    pattern: route.to_string(),
-                };
-                let routes: Vec<Route> = route_config
-                    .route
-                    .iter()
-                    .map(new_route)
-                    .chain(route_config.routes.iter().flatten().filter_map(|route| {
-                        if route.is_empty() {
-                            StdOut::warn("your configuration file contains an empty route");
-                            None
-                        } else {
-                            Some(new_route(route))
-                        }
-                    }))
-                    .collect();
-
-                if routes.is_empty() {
-                    failure::bail!("No routes specified");
+    /// Builds a target for each zone that `route_config`'s routes are deployed to, in the order
+    /// the zones are first used.
+    pub fn build(
+        script_name: &str,
+        route_config: &RouteConfig,
+    ) -> Result<Vec<Self>, failure::Error> {
+        let default_zone = route_config
+            .zone_id
+            .as_ref()
+            .filter(|zone_id| !zone_id.is_empty())
+            .map(|zone_id| Zone::Id(zone_id.clone()));
+
+        let patterns = route_config
+            .route
+            .iter()
+            .map(|route| (route.as_str(), None))
+            .chain(route_config.routes.iter().flatten().filter_map(|route| {
+                if route.pattern().is_empty() {
+                    StdOut::warn("your configuration file contains an empty route");
+                    None
+                } else {
+                    Some((route.pattern(), route.zone()))
                 }
+            }));
 
-                Ok(Self {
-                    zone_id: zone_id.to_owned(),
-                    routes,
-                })
+        let mut zones: Vec<(Zone, Vec<Route>)> = Vec::new();
+        for (pattern, zone) in patterns {
+            let zone = match zone.or_else(|| default_zone.clone()) {
+                Some(zone) => zone,
+                None => failure::bail!("field `zone_id` is required to deploy to routes"),
+            };
+            let route = Route {
+                id: None,
+                script: Some(script_name.to_string()),
+                pattern: pattern.to_string(),
+            };
+            match zones.iter_mut().find(|(existing, _)| *existing == zone) {
+                Some((_, routes)) => routes.push(route),
+                None => zones.push((zone, vec![route])),
             }
-            _ => failure::bail!("field `zone_id` is required to deploy to routes"),
         }
+
+        if zones.is_empty() {
+            failure::bail!("No routes specified");
+        }
+
+        Ok(zones
+            .into_iter()
+            .map(|(zone, routes)| match zone {
+                Zone::Id(zone_id) => Self {
+                    zone_id,
+                    zone_name: None,
+                    routes,
+                },
+                Zone::Name(zone_name) => Self {
+                    zone_id: String::new(),
+                    zone_name: Some(zone_name),
+                    routes,
+                },
+            })
+            .collect())
     }
 
-    pub fn deploy(&self, user: &GlobalUser) -> Result<Vec<String>, failure::Error> {
+    /// Looks up the id of a zone given by name.
+    pub fn resolve_zone(&mut self, user: &GlobalUser) -> Result<(), failure::Error> {
+        if let (true, Some(zone_name)) = (self.zone_id.is_empty(), &self.zone_name) {
+            self.zone_id = zone_id_by_name(user, zone_name)?;
+            log::info!("zone {} has id {}", zone_name, self.zone_id);
+        }
+        Ok(())
+    }
+
+    pub fn deploy(
+        &self,
+        user: &GlobalUser,
+        options: &RouteOptions,
+    ) -> Result<Vec<String>, failure::Error> {
         log::info!("publishing to zone {}", self.zone_id);
 
-        let published_routes = publish_routes(&user, self)?;
+        let published_routes = publish_routes(&user, self, options)?;
 
         let display_results: Vec<String> = published_routes.iter().map(|r| r.to_string()).collect();
 
         Ok(display_results)
     }
+
+    fn zone_label(&self) -> &str {
+        self.zone_name.as_deref().unwrap_or(&self.zone_id)
+    }
+
+    // Every route in a target points to the same script.
+    fn script_name(&self) -> Option<&str> {
+        self.routes.iter().find_map(|route| route.script.as_deref())
+    }
+}
+
+/// How the routes already in a zone compare to the ones configured for a script.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RoutePlan {
+    /// Configured routes that don't exist yet.
+    pub create: Vec<Route>,
+    /// Configured routes that already point to the script.
+    pub keep: Vec<Route>,
+    /// Configured routes that exist, but point to another script (or none), as they are now.
+    pub conflict: Vec<Route>,
+    /// Routes that point to the script, but are no longer configured.
+    pub orphaned: Vec<Route>,
+}
+
+impl RoutePlan {
+    pub fn new(zoned_config: &ZonedTarget, existing_routes: &[Route]) -> RoutePlan {
+        let mut plan = RoutePlan::default();
+        for route in &zoned_config.routes {
+            match existing_routes.iter().find(|e| e.pattern == route.pattern) {
+                Some(existing) if existing.script == route.script => {
+                    plan.keep.push(existing.clone())
+                }
+                Some(existing) => plan.conflict.push(existing.clone()),
+                None => plan.create.push(route.clone()),
+            }
+        }
+
+        if let Some(script_name) = zoned_config.script_name() {
+            plan.orphaned = existing_routes
+                .iter()
+                .filter(|existing| existing.script.as_deref() == Some(script_name))
+                .filter(|existing| {
+                    !zoned_config
+                        .routes
+                        .iter()
+                        .any(|route| route.pattern == existing.pattern)
+                })
+                .cloned()
+                .collect();
+        }
+
+        plan
+    }
+
+    /// How the routes in a zone compare for a script with no routes configured in it: every
+    /// route that points to the script is orphaned.
+    pub fn unconfigured(script_name: &str, existing_routes: &[Route]) -> RoutePlan {
+        RoutePlan {
+            orphaned: existing_routes
+                .iter()
+                .filter(|existing| existing.script.as_deref() == Some(script_name))
+                .cloned()
+                .collect(),
+            ..RoutePlan::default()
+        }
+    }
+
+    fn print(&self, zone: &str, options: &RouteOptions) {
+        StdErr::info(&format!("Routes in zone {}:", zone));
+        for route in &self.create {
+            eprintln!("  + {} (create)", route.pattern);
+        }
+        for route in &self.keep {
+            eprintln!("  = {} (keep)", route.pattern);
+        }
+        for route in &self.conflict {
+            let action = if options.force {
+                "reassign"
+            } else {
+                "conflict, use --force to reassign"
+            };
+            eprintln!(
+                "  ! {} ({} from {})",
+                route.pattern,
+                action,
+                route.script.as_deref().unwrap_or("null worker")
+            );
+        }
+        for route in &self.orphaned {
+            let action = if options.prune {
+                "delete"
+            } else {
+                "no longer configured, use --prune-routes to delete"
+            };
+            eprintln!("  - {} ({})", route.pattern, action);
+        }
+    }
+}
+
+/// What `publish_routes` would do, without changing anything.
+pub fn plan_routes(
+    user: &GlobalUser,
+    zoned_config: &ZonedTarget,
+) -> Result<RoutePlan, failure::Error> {
+    let existing_routes = fetch_all(user, &zoned_config.zone_id)?;
+    Ok(RoutePlan::new(zoned_config, &existing_routes))
+}
+
+/// What `prune_unconfigured_zone` would do, without changing anything.
+pub fn plan_unconfigured_zone(
+    user: &GlobalUser,
+    zone_id: &str,
+    script_name: &str,
+) -> Result<RoutePlan, failure::Error> {
+    let existing_routes = fetch_all(user, zone_id)?;
+    Ok(RoutePlan::unconfigured(script_name, &existing_routes))
+}
+
+/// Reports the routes that still point to `script_name` in a zone it no longer has routes
+/// configured in, deleting them with `--prune-routes`.
+pub fn prune_unconfigured_zone(
+    user: &GlobalUser,
+    zone_id: &str,
+    script_name: &str,
+    options: &RouteOptions,
+) -> Result<Vec<RouteUploadResult>, failure::Error> {
+    let plan = plan_unconfigured_zone(user, zone_id, script_name)?;
+    if plan.orphaned.is_empty() {
+        return Ok(Vec::new());
+    }
+    plan.print(zone_id, options);
+
+    Ok(plan
+        .orphaned
+        .into_iter()
+        .map(|route| prune(user, zone_id, route, options))
+        .collect())
 }
 
 pub fn publish_routes(
     user: &GlobalUser,
     zoned_config: &ZonedTarget,
+    options: &RouteOptions,
 ) -> Result<Vec<RouteUploadResult>, failure::Error> {
     // For the moment, we'll just make this call once and make all our decisions based on the response.
     // There is a possibility of race conditions, but we just report back the results and allow the
     // user to decide how to proceed.
     let existing_routes = fetch_all(user, &zoned_config.zone_id)?;
+    let plan = RoutePlan::new(zoned_config, &existing_routes);
+    plan.print(zoned_config.zone_label(), options);
+
+    let zone_id = &zoned_config.zone_id;
+    let mut results: Vec<RouteUploadResult> =
+        plan.keep.into_iter().map(RouteUploadResult::Same).collect();
 
-    let deployed_routes = zoned_config
-        .routes
-        .iter()
-        .map(|route| deploy_route(user, &zoned_config.zone_id, route, &existing_routes))
-        .collect();
+    for route in plan.create {
+        // we want to show the new route along with its id, or which route failed
+        results.push(match create(user, zone_id, &route) {
+            Ok(created) => RouteUploadResult::New(created),
+            Err(e) => RouteUploadResult::Error((route, RouteAction::Create, e.to_string())),
+        });
+    }
 
-    Ok(deployed_routes)
+    let script = zoned_config.script_name().map(str::to_string);
+    for route in plan.conflict {
+        results.push(if options.force {
+            let reassigned = Route {
+                script: script.clone(),
+                ..route
+            };
+            match reassign(user, zone_id, &reassigned) {
+                Ok(()) => RouteUploadResult::Reassigned(reassigned),
+                Err(e) => {
+                    RouteUploadResult::Error((reassigned, RouteAction::Reassign, e.to_string()))
+                }
+            }
+        } else {
+            RouteUploadResult::Conflict(route)
+        });
+    }
+
+    for route in plan.orphaned {
+        results.push(prune(user, zone_id, route, options));
+    }
+
+    Ok(results)
+}
+
+// Deletes an orphaned route with `--prune-routes`, or otherwise leaves it to be reported.
+fn prune(
+    user: &GlobalUser,
+    zone_id: &str,
+    route: Route,
+    options: &RouteOptions,
+) -> RouteUploadResult {
+    if options.prune {
+        match delete(user, zone_id, &route) {
+            Ok(()) => RouteUploadResult::Deleted(route),
+            Err(e) => RouteUploadResult::Error((route, RouteAction::Delete, e.to_string())),
+        }
+    } else {
+        RouteUploadResult::Orphaned(route)
+    }
 }
 
 fn fetch_all(user: &GlobalUser, zone_identifier: &str) -> Result<Vec<Route>, failure::Error> {
@@ -92,6 +323,32 @@ fn fetch_all(user: &GlobalUser, zone_identifier: &str) -> Result<Vec<Route>, fai
     Ok(routes)
 }
 
+fn zone_id_by_name(user: &GlobalUser, zone_name: &str) -> Result<String, failure::Error> {
+    let client = http::cf_v4_client(user)?;
+
+    let zones = match client.request(&ListZones {
+        params: ListZonesParams {
+            name: Some(zone_name.to_string()),
+            ..Default::default()
+        },
+    }) {
+        Ok(success) => success.result,
+        Err(e) => failure::bail!("{}", http::format_error(e, None)),
+    };
+
+    match zones.as_slice() {
+        [zone] => Ok(zone.id.clone()),
+        [] => failure::bail!(
+            "Could not find a zone named {} that you have access to",
+            zone_name
+        ),
+        _ => failure::bail!(
+            "More than one zone is named {}; use `zone_id` in your configuration file instead",
+            zone_name
+        ),
+    }
+}
+
 fn create(
     user: &GlobalUser,
     zone_identifier: &str,
@@ -116,6 +373,53 @@ fn create(
     }
 }
 
+// The API client has no endpoint for updating routes, so this uses the legacy client.
+fn reassign(user: &GlobalUser, zone_identifier: &str, route: &Route) -> Result<(), failure::Error> {
+    let route_id = match &route.id {
+        Some(route_id) => route_id,
+        None => failure::bail!("route {} has no id to update", route.pattern),
+    };
+    let route_addr = format!(
+        "https://api.cloudflare.com/client/v4/zones/{}/workers/routes/{}",
+        zone_identifier, route_id
+    );
+
+    log::info!("Reassigning your route {:#?}", &route.pattern);
+    let client = http::legacy_auth_client(user);
+    let res = client
+        .put(&route_addr)
+        .header("Content-Type", "application/json")
+        .body(serde_json::json!({ "pattern": route.pattern, "script": route.script }).to_string())
+        .send()?;
+
+    if !res.status().is_success() {
+        failure::bail!(
+            "Something went wrong! Status: {}, Details {}",
+            res.status(),
+            res.text()?
+        )
+    }
+
+    Ok(())
+}
+
+fn delete(user: &GlobalUser, zone_identifier: &str, route: &Route) -> Result<(), failure::Error> {
+    let route_id = match &route.id {
+        Some(route_id) => route_id,
+        None => failure::bail!("route {} has no id to delete", route.pattern),
+    };
+    let client = http::cf_v4_client(user)?;
+
+    log::info!("Deleting your route {:#?}", &route.pattern);
+    match client.request(&DeleteRoute {
+        zone_identifier,
+        identifier: route_id,
+    }) {
+        Ok(_) => Ok(()),
+        Err(e) => failure::bail!("{}", http::format_error(e, None)),
+    }
+}
+
 // TODO: improve this error message to reference wrangler route commands
 fn routes_error_help(error_code: u16) -> &'static str {
     match error_code {
@@ -135,7 +439,18 @@ pub enum RouteUploadResult {
     Same(Route),
     Conflict(Route),
     New(Route),
-    Error((Route, String)),
+    Reassigned(Route),
+    Orphaned(Route),
+    Deleted(Route),
+    Error((Route, RouteAction, String)),
+}
+
+/// What was being done to a route when it failed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum RouteAction {
+    Create,
+    Reassign,
+    Delete,
 }
 
 impl fmt::Display for RouteUploadResult {
@@ -149,51 +464,92 @@ impl fmt::Display for RouteUploadResult {
                 route.script.as_ref().unwrap_or(&"null worker".to_string())
             ),
             RouteUploadResult::New(route) => write!(f, "{} => created", route.pattern),
-            RouteUploadResult::Error((route, message)) => {
-                write!(f, "{} => creation failed: {}", route.pattern, message)
+            RouteUploadResult::Reassigned(route) => write!(f, "{} => reassigned", route.pattern),
+            RouteUploadResult::Orphaned(route) => write!(
+                f,
+                "{} => is no longer in your configuration, but still points to this worker",
+                route.pattern
+            ),
+            RouteUploadResult::Deleted(route) => write!(f, "{} => deleted", route.pattern),
+            RouteUploadResult::Error((route, action, message)) => {
+                let action = match action {
+                    RouteAction::Create => "creation",
+                    RouteAction::Reassign => "reassignment",
+                    RouteAction::Delete => "deletion",
+                };
+                write!(f, "{} => {} failed: {}", route.pattern, action, message)
             }
         }
     }
 }
 
-fn deploy_route(
-    user: &GlobalUser,
-    zone_id: &str,
-    route: &Route,
-    existing_routes: &[Route],
-) -> RouteUploadResult {
-    for existing_route in existing_routes {
-        if route.pattern == existing_route.pattern {
-            // if the route is already assigned, we don't need to call the api.
-            // if the script names match, it's a no-op.
-            if route.script == existing_route.script {
-                return RouteUploadResult::Same(Route {
-                    id: existing_route.id.clone(),
-                    script: existing_route.script.clone(),
-                    pattern: existing_route.pattern.clone(),
-                });
-            }
-            // if the script names do not match, we want to know which script is conflicting.
-            return RouteUploadResult::Conflict(Route {
-                id: existing_route.id.clone(),
-                script: existing_route.script.clone(),
-                pattern: existing_route.pattern.clone(),
-            });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(id: Option<&str>, pattern: &str, script: Option<&str>) -> Route {
+        Route {
+            id: id.map(str::to_string),
+            pattern: pattern.to_string(),
+            script: script.map(str::to_string),
         }
     }
 
-    // if none of the existing routes match this one, we should create a new route
-    match create(user, zone_id, &route) {
-        // we want to show the new route along with its id
-        Ok(created) => RouteUploadResult::New(created),
-        // if there is an error, we want to know which route triggered it
-        Err(e) => RouteUploadResult::Error((
-            Route {
-                id: None,
-                script: route.script.clone(),
-                pattern: route.pattern.clone(),
-            },
-            e.to_string(),
-        )),
+    #[test]
+    fn it_names_the_action_that_failed() {
+        let failed = RouteUploadResult::Error((
+            route(Some("1"), "example.com/*", Some("other")),
+            RouteAction::Reassign,
+            "forbidden".to_string(),
+        ));
+        assert_eq!(
+            failed.to_string(),
+            "example.com/* => reassignment failed: forbidden"
+        );
+    }
+
+    #[test]
+    fn it_plans_routes_against_existing_routes() {
+        let zoned = ZonedTarget {
+            zone_id: "zone".to_string(),
+            zone_name: None,
+            routes: vec![
+                route(None, "example.com/new/*", Some("worker")),
+                route(None, "example.com/same/*", Some("worker")),
+                route(None, "example.com/taken/*", Some("worker")),
+            ],
+        };
+        let existing = vec![
+            route(Some("1"), "example.com/same/*", Some("worker")),
+            route(Some("2"), "example.com/taken/*", Some("other")),
+            route(Some("3"), "example.com/old/*", Some("worker")),
+            route(Some("4"), "example.com/unrelated/*", Some("other")),
+        ];
+
+        assert_eq!(
+            RoutePlan::new(&zoned, &existing),
+            RoutePlan {
+                create: vec![route(None, "example.com/new/*", Some("worker"))],
+                keep: vec![route(Some("1"), "example.com/same/*", Some("worker"))],
+                conflict: vec![route(Some("2"), "example.com/taken/*", Some("other"))],
+                orphaned: vec![route(Some("3"), "example.com/old/*", Some("worker"))],
+            }
+        );
+    }
+
+    #[test]
+    fn it_orphans_every_route_to_the_script_in_an_unconfigured_zone() {
+        let existing = vec![
+            route(Some("1"), "example.com/old/*", Some("worker")),
+            route(Some("2"), "example.com/other/*", Some("other")),
+        ];
+
+        assert_eq!(
+            RoutePlan::unconfigured("worker", &existing),
+            RoutePlan {
+                orphaned: vec![route(Some("1"), "example.com/old/*", Some("worker"))],
+                ..RoutePlan::default()
+            }
+        );
     }
 }
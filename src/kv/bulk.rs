@@ -1,18 +1,24 @@
-use std::time::Duration;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
 
 use indicatif::ProgressBar;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
 
-use cloudflare::endpoints::workerskv::delete_bulk::DeleteBulk;
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
-use cloudflare::endpoints::workerskv::write_bulk::WriteBulk;
-use cloudflare::framework::apiclient::ApiClient;
-use cloudflare::framework::auth::Credentials;
-use cloudflare::framework::{Environment, HttpApiClient, HttpApiClientConfig};
 
-use crate::commands::kv::format_error;
-use crate::http::feature::headers;
-use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::Target;
+use crate::kv::store::{BulkPair, KvStore, TransientError};
+use crate::settings::LOCAL_STATE_DIR;
 
 const API_MAX_PAIRS: usize = 10000;
 // The consts below are halved from the API's true capacity to help avoid
@@ -20,125 +26,561 @@ const API_MAX_PAIRS: usize = 10000;
 pub const BATCH_KEY_MAX: usize = API_MAX_PAIRS / 2;
 const UPLOAD_MAX_SIZE: usize = 50 * 1024 * 1024;
 
-// Create a special API client that has a longer timeout than usual, given that KV operations
-// can be lengthy if payloads are large.
-fn bulk_api_client(user: &GlobalUser) -> Result<HttpApiClient, failure::Error> {
-    let config = HttpApiClientConfig {
-        http_timeout: Duration::from_secs(5 * 60),
-        default_headers: headers(None),
-    };
+// How many batches are sent at once.
+const CONCURRENCY: usize = 4;
+// How many times a batch is sent before giving up on it, when it fails in a way worth retrying.
+const MAX_ATTEMPTS: u32 = 5;
+// The wait before the first retry, which doubles with each one after it.
+const BACKOFF: Duration = Duration::from_millis(500);
+// How many of a failed batch's keys are named in its error.
+const KEYS_IN_ERROR: usize = 5;
 
-    HttpApiClient::new(
-        Credentials::from(user.to_owned()),
-        config,
-        Environment::Production,
-    )
-}
+// The operations batches are checkpointed for.
+const PUT: &str = "put";
+const DELETE: &str = "delete";
 
+/// Writes `pairs` to a namespace in batches, several at a time, retrying batches that are
+/// rate limited or hit an API error. `progress_bar` counts pairs.
+///
+/// The batches that succeed are recorded under `.wrangler/state/bulk/` until all of them have,
+/// so that running the same upload again after a failure skips them. `source` names what the
+/// pairs came from, such as `file_source` of a file; batches recorded for another source, or
+/// before a bulk delete from the namespace, aren't skipped.
 pub fn put(
-    target: &Target,
-    user: &GlobalUser,
+    store: &dyn KvStore,
     namespace_id: &str,
+    source: &str,
     pairs: Vec<KeyValuePair>,
     progress_bar: &Option<ProgressBar>,
 ) -> Result<(), failure::Error> {
-    let client = bulk_api_client(user)?;
-
-    for b in batch_keys_values(pairs) {
-        match client.request(&WriteBulk {
-            account_identifier: &target.account_id,
-            namespace_identifier: namespace_id,
-            bulk_key_value_pairs: b.to_owned(),
-        }) {
-            Ok(_) => {}
-            Err(e) => failure::bail!("{}", format_error(e)),
-        }
+    put_stream(
+        store,
+        namespace_id,
+        source,
+        pairs.into_iter().map(|pair| Ok((pair, 1))),
+        progress_bar,
+    )
+}
 
-        if let Some(pb) = &progress_bar {
-            pb.inc(b.len() as u64);
-        }
-    }
+/// Writes pairs to a namespace like `put_stream`, with their metadata.
+pub fn put_with_metadata(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    source: &str,
+    pairs: impl Iterator<Item = Result<(BulkPair, u64), failure::Error>> + Send,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<(), failure::Error> {
+    let checkpoint = Checkpoint::open(&checkpoint_dir(), namespace_id, PUT, source);
+    put_with_checkpoint(store, namespace_id, pairs, progress_bar, checkpoint)
+}
 
-    Ok(())
+/// Writes pairs to a namespace like `put`, taking them from `pairs` only as they're needed
+/// to fill the next batch. Each pair comes with how far it moves `progress_bar` along, such
+/// as the number of bytes it was read from.
+pub fn put_stream(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    source: &str,
+    pairs: impl Iterator<Item = Result<(KeyValuePair, u64), failure::Error>> + Send,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<(), failure::Error> {
+    let pairs = pairs.map(|pair| pair.map(|(pair, progress)| (BulkPair::from(pair), progress)));
+    put_with_metadata(store, namespace_id, source, pairs, progress_bar)
 }
 
+/// Deletes `keys` from a namespace in batches, like `put` writes them.
 pub fn delete(
-    target: &Target,
-    user: &GlobalUser,
+    store: &dyn KvStore,
     namespace_id: &str,
+    source: &str,
     keys: Vec<String>,
     progress_bar: &Option<ProgressBar>,
 ) -> Result<(), failure::Error> {
-    let client = bulk_api_client(user)?;
-
-    for b in batch_keys(keys) {
-        match client.request(&DeleteBulk {
-            account_identifier: &target.account_id,
-            namespace_identifier: namespace_id,
-            bulk_keys: b.to_owned(),
-        }) {
-            Ok(_) => {}
-            Err(e) => failure::bail!("{}", format_error(e)),
-        }
-
-        if let Some(pb) = &progress_bar {
-            pb.inc(b.len() as u64);
-        }
-    }
-
-    Ok(())
-}
-
-fn batch_keys_values(mut pairs: Vec<KeyValuePair>) -> Vec<Vec<KeyValuePair>> {
-    let mut batches: Vec<Vec<KeyValuePair>> = Vec::new();
-
-    if !pairs.is_empty() {
-        // Iterate over all key-value pairs and create batches of uploads, each of which are
-        // maximum 5K key-value pairs in size OR maximum ~50MB in size. Upload each batch
-        // as it is created.
-        let mut key_count = 0;
-        let mut key_pair_bytes = 0;
-        let mut key_value_batch: Vec<KeyValuePair> = Vec::new();
-
-        while !(pairs.is_empty() && key_value_batch.is_empty()) {
-            if pairs.is_empty() {
-                // Last batch to upload
-                batches.push(key_value_batch.to_vec());
-                key_value_batch.clear();
-            } else {
-                let pair = pairs.pop().unwrap();
-                if key_count + 1 > BATCH_KEY_MAX
-                // Keep upload size small to keep KV bulk API happy
-                || key_pair_bytes + pair.key.len() + pair.value.len() > UPLOAD_MAX_SIZE
-                {
-                    batches.push(key_value_batch.to_vec());
-                    key_count = 0;
-                    key_pair_bytes = 0;
-                    key_value_batch.clear();
+    let checkpoint = Checkpoint::open(&checkpoint_dir(), namespace_id, DELETE, source);
+    run_batches(
+        batch_keys(keys),
+        |key| key,
+        |key, hasher| hasher.write(key.as_bytes()),
+        |batch| store.delete_batch(namespace_id, batch),
+        "delete",
+        progress_bar,
+        checkpoint,
+    )
+}
+
+/// The `source` of pairs or keys read from a file: its path, size and modification time, so
+/// that a checkpoint isn't resumed once the file has changed.
+pub fn file_source(path: &Path) -> Result<String, failure::Error> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(format!(
+        "{} {} {}",
+        fs::canonicalize(path)?.display(),
+        metadata.len(),
+        modified.as_nanos()
+    ))
+}
+
+fn put_with_checkpoint(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    pairs: impl Iterator<Item = Result<(BulkPair, u64), failure::Error>> + Send,
+    progress_bar: &Option<ProgressBar>,
+    checkpoint: Checkpoint,
+) -> Result<(), failure::Error> {
+    run_batches(
+        PairBatches::new(pairs),
+        |pair| &pair.key,
+        hash_pair,
+        |batch| store.write_batch(namespace_id, batch),
+        "write",
+        progress_bar,
+        checkpoint,
+    )
+}
+
+// A batch of pairs or keys, and how far sending it moves the progress bar along.
+struct Batch<T> {
+    items: Vec<T>,
+    progress: u64,
+}
+
+// Sends `batches` with `send`, CONCURRENCY at a time, skipping those `checkpoint` has recorded.
+// Batches are only made as they're sent. After a batch fails for good no more are started,
+// and the error names some of its keys.
+fn run_batches<T: Send + Sync>(
+    batches: impl Iterator<Item = Result<Batch<T>, failure::Error>> + Send,
+    key: impl Fn(&T) -> &str + Sync,
+    hash: impl Fn(&T, &mut XxHash64) + Sync,
+    send: impl Fn(&[T]) -> Result<(), failure::Error> + Sync,
+    action: &str,
+    progress_bar: &Option<ProgressBar>,
+    checkpoint: Checkpoint,
+) -> Result<(), failure::Error> {
+    let batches = Mutex::new(batches);
+    let failed = AtomicBool::new(false);
+    let errors = Mutex::new(Vec::new());
+
+    let run = || {
+        while !failed.load(Ordering::SeqCst) {
+            let next = batches.lock().unwrap().next();
+            let batch = match next {
+                Some(Ok(batch)) => batch,
+                Some(Err(e)) => {
+                    failed.store(true, Ordering::SeqCst);
+                    errors.lock().unwrap().push(e.to_string());
+                    break;
                 }
+                None => break,
+            };
+
+            let digest = batch_digest(&batch.items, &hash);
+            if !checkpoint.contains(&digest) {
+                let result =
+                    with_retries(|| send(&batch.items)).and_then(|_| checkpoint.record(digest));
+                if let Err(e) = result {
+                    failed.store(true, Ordering::SeqCst);
+                    let keys: Vec<&str> = batch.items.iter().map(&key).collect();
+                    errors.lock().unwrap().push(format!(
+                        "Failed to {} {}: {}",
+                        action,
+                        describe_keys(&keys),
+                        e
+                    ));
+                    break;
+                }
+            }
+
+            if let Some(pb) = progress_bar {
+                pb.inc(batch.progress);
+            }
+        }
+    };
+    let scoped = crossbeam_utils::thread::scope(|scope| {
+        for _ in 0..CONCURRENCY {
+            scope.spawn(|_| run());
+        }
+    });
+    if scoped.is_err() {
+        failure::bail!("A bulk {} panicked", action)
+    }
+
+    let errors = errors.into_inner().unwrap();
+    if errors.is_empty() {
+        checkpoint.remove()
+    } else {
+        failure::bail!(
+            "{}\nRunning this again will skip the batches that succeeded.",
+            errors.join("\n")
+        )
+    }
+}
+
+// Calls `f` until it succeeds or fails with an error that isn't a `TransientError`, waiting
+// longer between each attempt.
+fn with_retries(f: impl Fn() -> Result<(), failure::Error>) -> Result<(), failure::Error> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e) if attempt < MAX_ATTEMPTS && e.downcast_ref::<TransientError>().is_some() => {
+                // jittered, so that concurrent batches don't all retry at once
+                let backoff = BACKOFF * 2u32.pow(attempt - 1)
+                    + Duration::from_millis(rand::thread_rng().gen_range(0, 250));
+                log::info!("retrying in {:?} after: {}", backoff, e);
+                thread::sleep(backoff);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn describe_keys(keys: &[&str]) -> String {
+    let named: Vec<String> = keys
+        .iter()
+        .take(KEYS_IN_ERROR)
+        .map(|key| format!("\"{}\"", key))
+        .collect();
+    if keys.len() > KEYS_IN_ERROR {
+        format!(
+            "{} and {} more keys",
+            named.join(", "),
+            keys.len() - KEYS_IN_ERROR
+        )
+    } else {
+        named.join(", ")
+    }
+}
+
+// Identifies a batch by what it sends. Batches are made the same way every time, so the same
+// upload has the same batches when it's run again.
+fn batch_digest<T>(batch: &[T], hash: impl Fn(&T, &mut XxHash64)) -> String {
+    let mut hasher = XxHash64::default();
+    for item in batch {
+        hash(item, &mut hasher);
+        hasher.write_u8(0);
+    }
+    format!("{:x}", hasher.finish())
+}
+
+fn hash_pair(pair: &BulkPair, hasher: &mut XxHash64) {
+    hasher.write(pair.key.as_bytes());
+    hasher.write_u8(0);
+    hasher.write(pair.value.as_bytes());
+    hasher.write_i64(pair.expiration.unwrap_or(-1));
+    hasher.write_i64(pair.expiration_ttl.unwrap_or(-1));
+    hasher.write_u8(pair.base64.unwrap_or_default() as u8);
+    if let Some(metadata) = &pair.metadata {
+        hasher.write(metadata.to_string().as_bytes());
+    }
+}
+
+fn checkpoint_dir() -> PathBuf {
+    Path::new(LOCAL_STATE_DIR).join("state").join("bulk")
+}
+
+// The batches of a bulk write or delete to a namespace that have succeeded, kept until
+// all of them have.
+struct Checkpoint {
+    path: PathBuf,
+    source: String,
+    done: Mutex<HashSet<String>>,
+}
+
+// A checkpoint as it's kept on disk.
+#[derive(Deserialize, Serialize)]
+struct Recorded<'a> {
+    source: Cow<'a, str>,
+    done: Cow<'a, HashSet<String>>,
+}
 
-                // Add the popped key-value pair to the running batch of key-value pair uploads
-                key_count += 1;
-                key_pair_bytes = key_pair_bytes + pair.key.len() + pair.value.len();
-                key_value_batch.push(pair);
+impl Checkpoint {
+    // Opens the checkpoint of `operation` on a namespace, which is only resumed if it was
+    // recorded for the same `source`. A write and a delete to the same namespace undo each
+    // other, so the checkpoint of the other operation is dropped.
+    fn open(dir: &Path, namespace_id: &str, operation: &str, source: &str) -> Self {
+        let other = if operation == PUT { DELETE } else { PUT };
+        let other = dir.join(format!("{}.{}.json", namespace_id, other));
+        if other.exists() {
+            if let Err(e) = fs::remove_file(&other) {
+                log::info!("Could not remove {}: {}", other.display(), e);
             }
         }
+
+        let path = dir.join(format!("{}.{}.json", namespace_id, operation));
+        let done = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Recorded>(&data).ok())
+            .filter(|recorded| recorded.source == source)
+            .map(|recorded| recorded.done.into_owned())
+            .unwrap_or_default();
+        Checkpoint {
+            path,
+            source: source.to_string(),
+            done: Mutex::new(done),
+        }
+    }
+
+    fn contains(&self, digest: &str) -> bool {
+        self.done.lock().unwrap().contains(digest)
+    }
+
+    fn record(&self, digest: String) -> Result<(), failure::Error> {
+        let mut done = self.done.lock().unwrap();
+        done.insert(digest);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let recorded = Recorded {
+            source: Cow::Borrowed(&self.source),
+            done: Cow::Borrowed(&*done),
+        };
+        fs::write(&self.path, serde_json::to_vec(&recorded)?)?;
+        Ok(())
     }
 
-    batches
+    fn remove(self) -> Result<(), failure::Error> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+// Groups pairs into batches of at most BATCH_KEY_MAX pairs and UPLOAD_MAX_SIZE bytes of JSON,
+// reading only as many pairs as the next batch needs.
+struct PairBatches<I> {
+    pairs: I,
+    // the pair that didn't fit in the last batch
+    next: Option<(BulkPair, u64)>,
 }
 
-fn batch_keys(mut keys: Vec<String>) -> Vec<Vec<String>> {
-    let mut batches = Vec::new();
-    while !keys.is_empty() {
-        let k: Vec<String> = if keys.len() > BATCH_KEY_MAX {
-            keys.drain(0..BATCH_KEY_MAX).collect()
+impl<I> PairBatches<I> {
+    fn new(pairs: I) -> Self {
+        PairBatches { pairs, next: None }
+    }
+}
+
+impl<I> Iterator for PairBatches<I>
+where
+    I: Iterator<Item = Result<(BulkPair, u64), failure::Error>>,
+{
+    type Item = Result<Batch<BulkPair>, failure::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut batch = Batch {
+            items: Vec::new(),
+            progress: 0,
+        };
+        // the brackets around the array of pairs
+        let mut size = 2;
+        loop {
+            let (pair, progress) = match self.next.take().map(Ok).or_else(|| self.pairs.next()) {
+                Some(Ok(next)) => next,
+                Some(Err(e)) => return Some(Err(e)),
+                None => break,
+            };
+            // with the comma between it and the pair before
+            let pair_size = json_size(&pair) + 1;
+            if !batch.items.is_empty()
+                && (batch.items.len() == BATCH_KEY_MAX || size + pair_size > UPLOAD_MAX_SIZE)
+            {
+                self.next = Some((pair, progress));
+                break;
+            }
+            size += pair_size;
+            batch.items.push(pair);
+            batch.progress += progress;
+        }
+
+        if batch.items.is_empty() {
+            None
         } else {
-            keys.drain(0..).collect()
+            Some(Ok(batch))
+        }
+    }
+}
+
+// The length of `pair` as JSON, as it's sent to the API.
+fn json_size(pair: &BulkPair) -> usize {
+    struct Counter(usize);
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    // serializing into a counter can't fail
+    let _ = serde_json::to_writer(&mut counter, pair);
+    counter.0
+}
+
+fn batch_keys(keys: Vec<String>) -> impl Iterator<Item = Result<Batch<String>, failure::Error>> {
+    let mut keys = keys.into_iter().peekable();
+    iter::from_fn(move || {
+        keys.peek()?;
+        let items: Vec<String> = keys.by_ref().take(BATCH_KEY_MAX).collect();
+        let progress = items.len() as u64;
+        Some(Ok(Batch { items, progress }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::kv::store::{KeyPage, ListOptions, PutOptions};
+
+    // Fails the first batch it's sent once, with an error worth retrying, and every batch
+    // with `bad_key` in it.
+    struct FlakyStore {
+        bad_key: Option<String>,
+        failed_once: AtomicBool,
+        written: Mutex<Vec<usize>>,
+    }
+
+    impl FlakyStore {
+        fn new(bad_key: Option<&str>) -> Self {
+            FlakyStore {
+                bad_key: bad_key.map(str::to_string),
+                failed_once: AtomicBool::new(false),
+                written: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl KvStore for FlakyStore {
+        fn get(&self, _: &str, _: &str) -> Result<Option<Vec<u8>>, failure::Error> {
+            unimplemented!()
+        }
+
+        fn put(&self, _: &str, _: &str, _: Vec<u8>, _: &PutOptions) -> Result<(), failure::Error> {
+            unimplemented!()
+        }
+
+        fn delete(&self, _: &str, _: &str) -> Result<(), failure::Error> {
+            unimplemented!()
+        }
+
+        fn list(&self, _: &str, _: &ListOptions) -> Result<KeyPage, failure::Error> {
+            unimplemented!()
+        }
+
+        fn write_batch(&self, _: &str, pairs: &[BulkPair]) -> Result<(), failure::Error> {
+            if !self.failed_once.swap(true, Ordering::SeqCst) {
+                return Err(TransientError("rate limited".to_string()).into());
+            }
+            if pairs
+                .iter()
+                .any(|pair| Some(&pair.key) == self.bad_key.as_ref())
+            {
+                failure::bail!("invalid key")
+            }
+            self.written.lock().unwrap().push(pairs.len());
+            Ok(())
+        }
+
+        fn delete_batch(&self, _: &str, _: &[String]) -> Result<(), failure::Error> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn it_retries_batches_and_skips_the_ones_that_succeeded_when_run_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let pairs: Vec<KeyValuePair> = (0..BATCH_KEY_MAX * 2 + 1)
+            .map(|i| KeyValuePair {
+                key: if i == 0 {
+                    "bad".to_string()
+                } else {
+                    i.to_string()
+                },
+                value: "value".to_string(),
+                expiration: None,
+                expiration_ttl: None,
+                base64: None,
+            })
+            .collect();
+        let checkpoint = || Checkpoint::open(dir.path(), "ns", PUT, "pairs.json");
+
+        let store = FlakyStore::new(Some("bad"));
+        let stream = |pairs: &Vec<KeyValuePair>| {
+            pairs
+                .clone()
+                .into_iter()
+                .map(|pair| Ok((BulkPair::from(pair), 1)))
         };
+        let e = put_with_checkpoint(&store, "ns", stream(&pairs), &None, checkpoint())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            e.contains("Failed to write \"bad\", \"1\", \"2\", \"3\", \"4\" and 4995 more keys: invalid key"),
+            "{}",
+            e
+        );
+        let written: usize = store.written.lock().unwrap().iter().sum();
+
+        let store = FlakyStore::new(None);
+        put_with_checkpoint(&store, "ns", stream(&pairs), &None, checkpoint()).unwrap();
+        let rewritten: usize = store.written.lock().unwrap().iter().sum();
+        assert_eq!(written + rewritten, BATCH_KEY_MAX * 2 + 1);
+        assert!(!dir.path().join("ns.put.json").exists());
+    }
+
+    #[test]
+    fn it_only_resumes_a_checkpoint_for_the_same_source_until_a_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = Checkpoint::open(dir.path(), "ns", PUT, "pairs.json 1");
+        checkpoint.record("batch".to_string()).unwrap();
 
-        batches.push(k);
+        assert!(Checkpoint::open(dir.path(), "ns", PUT, "pairs.json 1").contains("batch"));
+        assert!(!Checkpoint::open(dir.path(), "ns", PUT, "pairs.json 2").contains("batch"));
+
+        Checkpoint::open(dir.path(), "ns", DELETE, "pairs.json 1");
+        assert!(!Checkpoint::open(dir.path(), "ns", PUT, "pairs.json 1").contains("batch"));
     }
 
-    batches
+    #[test]
+    fn it_batches_pairs_by_their_size_as_json() {
+        let pair = |key: &str, len: usize| {
+            Ok((
+                BulkPair {
+                    key: key.to_string(),
+                    value: "a".repeat(len),
+                    expiration: None,
+                    expiration_ttl: None,
+                    base64: None,
+                    metadata: None,
+                },
+                len as u64,
+            ))
+        };
+        let third = UPLOAD_MAX_SIZE / 3;
+        let pairs = vec![
+            pair("a", third),
+            pair("b", third),
+            pair("c", third),
+            pair("d", 1),
+        ];
+
+        let batches: Vec<Batch<BulkPair>> = PairBatches::new(pairs.into_iter())
+            .map(Result::unwrap)
+            .collect();
+        let keys: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|batch| batch.items.iter().map(|pair| pair.key.as_str()).collect())
+            .collect();
+        // three values fit in an upload, but not with the rest of their JSON
+        assert_eq!(keys, vec![vec!["a", "b"], vec!["c", "d"]]);
+        assert_eq!(batches[1].progress, third as u64 + 1);
+    }
 }
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+
+use twox_hash::XxHash64;
+
+use crate::kv::key::KeyList;
+use crate::kv::store::{KvStore, ListedKey};
+
+/// How the keys in one namespace differ from those in another.
+///
+/// Values are compared by hash, so they don't have to be kept in memory, and expirations
+/// are not compared.
+#[derive(Debug, Default, PartialEq)]
+pub struct NamespaceDiff {
+    /// Keys only in the source namespace, as listed there.
+    pub added: Vec<ListedKey>,
+    /// Keys in both namespaces whose value or metadata differ, as listed in the source.
+    pub changed: Vec<ListedKey>,
+    /// Keys only in the destination namespace.
+    pub removed: Vec<String>,
+}
+
+impl NamespaceDiff {
+    /// Compares the keys starting with `prefix` in the namespace `from_id` with those
+    /// in `to_id`.
+    pub fn new(
+        from: &dyn KvStore,
+        from_id: &str,
+        to: &dyn KvStore,
+        to_id: &str,
+        prefix: Option<&str>,
+    ) -> Result<Self, failure::Error> {
+        let mut to_keys = BTreeMap::new();
+        for key in KeyList::new(to, to_id, prefix) {
+            let key = key?;
+            to_keys.insert(key.name.clone(), key);
+        }
+
+        let mut diff = NamespaceDiff::default();
+        for key in KeyList::new(from, from_id, prefix) {
+            let key = key?;
+            let to_key = match to_keys.remove(&key.name) {
+                Some(to_key) => to_key,
+                None => {
+                    diff.added.push(key);
+                    continue;
+                }
+            };
+            if key.metadata != to_key.metadata {
+                diff.changed.push(key);
+                continue;
+            }
+
+            // keys deleted since they were listed count as missing
+            match (
+                get_digest(from, from_id, &key.name)?,
+                get_digest(to, to_id, &key.name)?,
+            ) {
+                (Some(from_digest), Some(to_digest)) if from_digest == to_digest => {}
+                (Some(_), Some(_)) => diff.changed.push(key),
+                (Some(_), None) => diff.added.push(key),
+                (None, Some(_)) => diff.removed.push(key.name),
+                (None, None) => {}
+            }
+        }
+        diff.removed.extend(to_keys.keys().cloned());
+        diff.removed.sort();
+
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+fn get_digest(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    key: &str,
+) -> Result<Option<u64>, failure::Error> {
+    Ok(store.get(namespace_id, key)?.map(|value| {
+        let mut hasher = XxHash64::default();
+        hasher.write(&value);
+        hasher.finish()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::kv::local::LocalStore;
+    use crate::kv::store::PutOptions;
+
+    #[test]
+    fn it_finds_added_changed_and_removed_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::in_dir(dir.path());
+        let put = |namespace_id: &str, key: &str, value: &str| {
+            store
+                .put(
+                    namespace_id,
+                    key,
+                    value.as_bytes().to_vec(),
+                    &PutOptions::default(),
+                )
+                .unwrap()
+        };
+        put("from", "a/same", "1");
+        put("from", "a/changed", "1");
+        put("from", "a/added", "1");
+        put("from", "b/added", "1");
+        put("to", "a/same", "1");
+        put("to", "a/changed", "2");
+        put("to", "a/removed", "1");
+
+        let diff = NamespaceDiff::new(&store, "from", &store, "to", Some("a/")).unwrap();
+        let names = |keys: &[ListedKey]| keys.iter().map(|k| k.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.added), vec!["a/added"]);
+        assert_eq!(names(&diff.changed), vec!["a/changed"]);
+        assert_eq!(diff.removed, vec!["a/removed"]);
+    }
+}
@@ -1,117 +1,57 @@
-use serde_json::value::Value as JsonValue;
+use crate::kv::store::{KeyPage, KvStore, ListOptions, ListedKey};
 
-use cloudflare::endpoints::workerskv::list_namespace_keys::ListNamespaceKeys;
-use cloudflare::endpoints::workerskv::list_namespace_keys::ListNamespaceKeysParams;
-use cloudflare::endpoints::workerskv::Key;
-use cloudflare::framework::apiclient::ApiClient;
-use cloudflare::framework::response::ApiFailure;
-use cloudflare::framework::HttpApiClient;
-
-use crate::settings::toml::Target;
-
-pub struct KeyList {
-    keys_result: Option<Vec<Key>>,
-    prefix: Option<String>,
-    client: HttpApiClient,
-    account_id: String,
+/// Every key in a namespace, in order, fetched a page at a time.
+pub struct KeyList<'a> {
+    store: &'a dyn KvStore,
     namespace_id: String,
-    cursor: Option<String>,
-    init_fetch: bool,
+    options: ListOptions,
+    keys: std::vec::IntoIter<ListedKey>,
+    done: bool,
 }
 
-impl KeyList {
-    pub fn new(
-        target: &Target,
-        client: HttpApiClient,
-        namespace_id: &str,
-        prefix: Option<&str>,
-    ) -> Result<KeyList, failure::Error> {
-        let iter = KeyList {
-            keys_result: None,
-            prefix: prefix.map(str::to_string),
-            client,
-            account_id: target.account_id.to_owned(),
+impl<'a> KeyList<'a> {
+    pub fn new(store: &'a dyn KvStore, namespace_id: &str, prefix: Option<&str>) -> KeyList<'a> {
+        KeyList {
+            store,
             namespace_id: namespace_id.to_string(),
-            cursor: None,
-            init_fetch: false,
-        };
-        Ok(iter)
-    }
-
-    fn request_params(&self) -> ListNamespaceKeys {
-        let params = ListNamespaceKeysParams {
-            limit: None, // Defaults to 1000 (the maximum)
-            cursor: self.cursor.to_owned(),
-            prefix: self.prefix.to_owned(),
-        };
-
-        ListNamespaceKeys {
-            account_identifier: &self.account_id,
-            namespace_identifier: &self.namespace_id,
-            params,
-        }
-    }
-
-    fn get_batch(&mut self) -> Result<Vec<Key>, ApiFailure> {
-        let response = self.client.request(&self.request_params());
-
-        match response {
-            Ok(success) => {
-                self.cursor = extract_cursor(success.result_info.clone());
-                log::info!("{:?}", self.cursor);
-                Ok(success.result)
-            }
-            Err(e) => Err(e),
+            options: ListOptions {
+                prefix: prefix.map(str::to_string),
+                ..ListOptions::default()
+            },
+            keys: Vec::new().into_iter(),
+            done: false,
         }
     }
 }
 
-impl Iterator for KeyList {
-    type Item = Result<Key, ApiFailure>;
+impl<'a> Iterator for KeyList<'a> {
+    type Item = Result<ListedKey, failure::Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Attempt to extract next key from vector of keys in KeyList.
-        // If no key vector or no keys left, go to fallback case below to
-        // attempt to fetch the next page of keys from the Workers KV API.
-        if let Some(mut keys) = self.keys_result.to_owned() {
-            let key = keys.pop();
-            self.keys_result = Some(keys);
-
-            if let Some(k) = key {
-                return Some(Ok(k));
+        loop {
+            if let Some(key) = self.keys.next() {
+                return Some(Ok(key));
             }
-        }
-        // Fallback case (if no remaining keys are found)
-        if self.cursor.is_none() && self.init_fetch {
-            None // Nothing left to fetch
-        } else {
-            if !self.init_fetch {
-                // At this point, initial fetch is being performed.
-                self.init_fetch = true;
+            if self.done {
+                return None;
             }
-            match self.get_batch() {
-                Ok(mut keys) => {
-                    let key = keys.pop();
-                    self.keys_result = Some(keys);
-                    match key {
-                        Some(k) => Some(Ok(k)),
-                        None => None,
-                    }
+
+            // fetch the next page of keys
+            match self.store.list(&self.namespace_id, &self.options) {
+                Ok(KeyPage {
+                    keys,
+                    list_complete,
+                    cursor,
+                }) => {
+                    self.done = list_complete || cursor.is_none();
+                    self.options.cursor = cursor;
+                    self.keys = keys.into_iter();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
                 }
-                Err(e) => Some(Err(e)),
             }
         }
     }
 }
-
-// Returns Some(cursor) if cursor is non-empty, otherwise returns None.
-fn extract_cursor(result_info: Option<JsonValue>) -> Option<String> {
-    let result_info = result_info.unwrap();
-    let returned_cursor_value = &result_info["cursor"];
-    let returned_cursor = returned_cursor_value.as_str().unwrap().to_string();
-    if returned_cursor.is_empty() {
-        None
-    } else {
-        Some(returned_cursor)
-    }
-}
@@ -0,0 +1,488 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::kv::store::{BulkPair, KeyPage, KvStore, ListOptions, ListedKey, PutOptions};
+use crate::settings::LOCAL_STATE_DIR;
+
+const INDEX_FILE: &str = "keys.json";
+const LOCK_FILE: &str = ".lock";
+const BLOB_DIR: &str = "blobs";
+
+/// The most keys a single list returns, as in the API.
+pub const LIST_LIMIT: usize = 1000;
+const KEY_MAX_SIZE: usize = 512;
+const VALUE_MAX_SIZE: usize = 25 * 1024 * 1024;
+const METADATA_MAX_SIZE: usize = 1024;
+// expirations must be at least this many seconds in the future
+const EXPIRATION_MIN_TTL: u64 = 60;
+
+/// A value and its metadata.
+pub type ValueWithMetadata = (Vec<u8>, Option<serde_json::Value>);
+
+/// A KV namespace stored on disk under `.wrangler/state/kv/<namespace id>/`, used by
+/// `wrangler dev --local` in place of the real one.
+///
+/// Values are kept in their own files under `blobs/`, and `keys.json` maps each key to its
+/// value file, expiration and metadata. Every operation takes a lock on the namespace, so
+/// several processes can share it.
+#[derive(Clone, Debug)]
+pub struct LocalNamespace {
+    dir: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Entry {
+    blob: String,
+    expiration: Option<u64>,
+    metadata: Option<serde_json::Value>,
+}
+
+impl LocalNamespace {
+    /// The namespace with `id` in the project in the current directory.
+    pub fn open(id: &str) -> Result<Self, failure::Error> {
+        Self::open_in(&default_kv_dir(), id)
+    }
+
+    /// The namespace with `id` in `kv_dir`. The id names the namespace's directory, so it
+    /// can't be empty, `.`, `..` or have a path separator in it.
+    pub fn open_in(kv_dir: &Path, id: &str) -> Result<Self, failure::Error> {
+        let mut components = Path::new(id).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if name == id => Ok(LocalNamespace {
+                dir: kv_dir.join(id),
+            }),
+            _ => failure::bail!("\"{}\" is not a valid namespace id", id),
+        }
+    }
+
+    /// The value of `key` and its metadata, if it is set and hasn't expired.
+    pub fn get(&self, key: &str) -> Result<Option<ValueWithMetadata>, failure::Error> {
+        self.with_index(|index| {
+            let entry = match index.get(key) {
+                Some(entry) if !is_expired(entry) => entry,
+                _ => return Ok(None),
+            };
+            let value = fs::read(self.dir.join(BLOB_DIR).join(&entry.blob))?;
+            Ok(Some((value, entry.metadata.clone())))
+        })
+    }
+
+    /// Sets `key` to `value`, with the same limits as the API.
+    pub fn put(&self, key: &str, value: &[u8], options: &PutOptions) -> Result<(), failure::Error> {
+        self.put_all(&[(key, value, options)])
+    }
+
+    /// Sets several keys at once, taking the lock and writing the index only once.
+    /// Nothing is written if any of them is invalid.
+    pub fn put_all(&self, pairs: &[(&str, &[u8], &PutOptions)]) -> Result<(), failure::Error> {
+        let mut entries = Vec::with_capacity(pairs.len());
+        for (key, value, options) in pairs {
+            let expiration = match validate_put(key, value, options) {
+                Ok(expiration) => expiration,
+                Err(e) => failure::bail!("Could not write \"{}\": {}", key, e),
+            };
+            entries.push((key, value, expiration, options.metadata.clone()));
+        }
+
+        self.with_index(|index| {
+            let blob_dir = self.dir.join(BLOB_DIR);
+            fs::create_dir_all(&blob_dir)?;
+            let mut replaced = Vec::new();
+            for (key, value, expiration, metadata) in entries {
+                let blob = Uuid::new_v4().to_simple().to_string();
+                fs::write(blob_dir.join(&blob), value)?;
+
+                let entry = Entry {
+                    blob,
+                    expiration,
+                    metadata,
+                };
+                if let Some(old) = index.insert(key.to_string(), entry) {
+                    replaced.push(old);
+                }
+            }
+            self.write_index(index)?;
+            for old in &replaced {
+                self.remove_blob(old)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Removes `key`. Removing a key that isn't set is not an error.
+    pub fn delete(&self, key: &str) -> Result<(), failure::Error> {
+        self.delete_all(&[key])
+    }
+
+    /// Removes several keys at once, taking the lock and writing the index only once.
+    pub fn delete_all<K: AsRef<str>>(&self, keys: &[K]) -> Result<(), failure::Error> {
+        self.with_index(|index| {
+            let removed: Vec<Entry> = keys
+                .iter()
+                .filter_map(|key| index.remove(key.as_ref()))
+                .collect();
+            if !removed.is_empty() {
+                self.write_index(index)?;
+                for old in &removed {
+                    self.remove_blob(old)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Up to `limit` keys starting with `prefix`, in order, after the key `cursor` was returned
+    /// with. Cursors are the last key of the previous page, base64 encoded.
+    pub fn list(&self, options: &ListOptions) -> Result<KeyPage, failure::Error> {
+        let limit = match options.limit {
+            Some(0) => 1,
+            Some(limit) => limit.min(LIST_LIMIT),
+            None => LIST_LIMIT,
+        };
+        let after = match &options.cursor {
+            Some(cursor) => match base64::decode(cursor).map(String::from_utf8) {
+                Ok(Ok(after)) => Some(after),
+                _ => failure::bail!("Invalid cursor {}", cursor),
+            },
+            None => None,
+        };
+        let prefix = options.prefix.as_deref().unwrap_or_default();
+
+        self.with_index(|index| {
+            let mut keys = index
+                .iter()
+                .filter(|(name, _)| name.starts_with(prefix))
+                .filter(|(name, _)| match &after {
+                    Some(after) => *name > after,
+                    None => true,
+                })
+                .filter(|(_, entry)| !is_expired(entry))
+                .map(|(name, entry)| ListedKey {
+                    name: name.clone(),
+                    expiration: entry.expiration,
+                    metadata: entry.metadata.clone(),
+                });
+
+            let page: Vec<ListedKey> = keys.by_ref().take(limit).collect();
+            let list_complete = keys.next().is_none();
+            let cursor = if list_complete {
+                None
+            } else {
+                page.last().map(|key| base64::encode(&key.name))
+            };
+            Ok(KeyPage {
+                keys: page,
+                list_complete,
+                cursor,
+            })
+        })
+    }
+
+    // Runs `f` on the namespace's index while holding its lock, removing expired keys first.
+    fn with_index<T>(
+        &self,
+        f: impl FnOnce(&mut BTreeMap<String, Entry>) -> Result<T, failure::Error>,
+    ) -> Result<T, failure::Error> {
+        fs::create_dir_all(&self.dir)?;
+        let lock = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(self.dir.join(LOCK_FILE))?;
+        lock.lock_exclusive()?;
+
+        let result = self.read_index().and_then(|mut index| {
+            let expired: Vec<String> = index
+                .iter()
+                .filter(|(_, entry)| is_expired(entry))
+                .map(|(name, _)| name.clone())
+                .collect();
+            if !expired.is_empty() {
+                for name in &expired {
+                    if let Some(old) = index.remove(name) {
+                        self.remove_blob(&old)?;
+                    }
+                }
+                self.write_index(&index)?;
+            }
+            f(&mut index)
+        });
+
+        lock.unlock()?;
+        result
+    }
+
+    fn read_index(&self) -> Result<BTreeMap<String, Entry>, failure::Error> {
+        let path = self.dir.join(INDEX_FILE);
+        match File::open(&path) {
+            Ok(file) => match serde_json::from_reader(file) {
+                Ok(index) => Ok(index),
+                Err(e) => failure::bail!("Could not read {}: {}", path.display(), e),
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // written to a temporary file first, so a crash never leaves a partial index behind
+    fn write_index(&self, index: &BTreeMap<String, Entry>) -> Result<(), failure::Error> {
+        let path = self.dir.join(INDEX_FILE);
+        let tmp = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        fs::write(&tmp, serde_json::to_vec(index)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn remove_blob(&self, entry: &Entry) -> Result<(), failure::Error> {
+        match fs::remove_file(self.dir.join(BLOB_DIR).join(&entry.blob)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The store `wrangler dev --local` keeps its KV namespaces in, under `.wrangler/state/kv/`.
+pub struct LocalStore {
+    kv_dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new() -> Self {
+        LocalStore::in_dir(&default_kv_dir())
+    }
+
+    pub fn in_dir(kv_dir: &Path) -> Self {
+        LocalStore {
+            kv_dir: kv_dir.to_path_buf(),
+        }
+    }
+
+    fn namespace(&self, namespace_id: &str) -> Result<LocalNamespace, failure::Error> {
+        LocalNamespace::open_in(&self.kv_dir, namespace_id)
+    }
+}
+
+impl Default for LocalStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KvStore for LocalStore {
+    fn get(&self, namespace_id: &str, key: &str) -> Result<Option<Vec<u8>>, failure::Error> {
+        Ok(self
+            .namespace(namespace_id)?
+            .get(key)?
+            .map(|(value, _)| value))
+    }
+
+    fn put(
+        &self,
+        namespace_id: &str,
+        key: &str,
+        value: Vec<u8>,
+        options: &PutOptions,
+    ) -> Result<(), failure::Error> {
+        self.namespace(namespace_id)?.put(key, &value, options)
+    }
+
+    fn delete(&self, namespace_id: &str, key: &str) -> Result<(), failure::Error> {
+        self.namespace(namespace_id)?.delete(key)
+    }
+
+    fn list(&self, namespace_id: &str, options: &ListOptions) -> Result<KeyPage, failure::Error> {
+        self.namespace(namespace_id)?.list(options)
+    }
+
+    fn write_batch(&self, namespace_id: &str, pairs: &[BulkPair]) -> Result<(), failure::Error> {
+        let mut decoded = Vec::with_capacity(pairs.len());
+        for pair in pairs {
+            let value = if pair.base64 == Some(true) {
+                base64::decode(&pair.value)?
+            } else {
+                pair.value.clone().into_bytes()
+            };
+            let options = PutOptions {
+                expiration: pair.expiration.map(|e| e as u64),
+                expiration_ttl: pair.expiration_ttl.map(|ttl| ttl as u64),
+                metadata: pair.metadata.clone(),
+            };
+            decoded.push((value, options));
+        }
+        let pairs: Vec<(&str, &[u8], &PutOptions)> = pairs
+            .iter()
+            .zip(&decoded)
+            .map(|(pair, (value, options))| (pair.key.as_str(), value.as_slice(), options))
+            .collect();
+        self.namespace(namespace_id)?.put_all(&pairs)
+    }
+
+    fn delete_batch(&self, namespace_id: &str, keys: &[String]) -> Result<(), failure::Error> {
+        self.namespace(namespace_id)?.delete_all(keys)
+    }
+}
+
+fn default_kv_dir() -> PathBuf {
+    Path::new(LOCAL_STATE_DIR).join("state").join("kv")
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn is_expired(entry: &Entry) -> bool {
+    matches!(entry.expiration, Some(expiration) if expiration <= now())
+}
+
+// Checks a put is within the API's limits, returning the time it expires the key at.
+fn validate_put(
+    key: &str,
+    value: &[u8],
+    options: &PutOptions,
+) -> Result<Option<u64>, failure::Error> {
+    validate_key(key)?;
+    if value.len() > VALUE_MAX_SIZE {
+        failure::bail!("Values can be at most {} bytes", VALUE_MAX_SIZE)
+    }
+    if let Some(metadata) = &options.metadata {
+        if metadata.to_string().len() > METADATA_MAX_SIZE {
+            failure::bail!("Metadata can be at most {} bytes", METADATA_MAX_SIZE)
+        }
+    }
+    expiration(options)
+}
+
+fn validate_key(key: &str) -> Result<(), failure::Error> {
+    if key.is_empty() || key == "." || key == ".." {
+        failure::bail!("\"{}\" is not a valid key name", key)
+    }
+    if key.len() > KEY_MAX_SIZE {
+        failure::bail!("Key names can be at most {} bytes long", KEY_MAX_SIZE)
+    }
+    Ok(())
+}
+
+// The time `options` expire the key at, like the API works it out.
+fn expiration(options: &PutOptions) -> Result<Option<u64>, failure::Error> {
+    let now = now();
+    match (options.expiration_ttl, options.expiration) {
+        (Some(ttl), _) if ttl < EXPIRATION_MIN_TTL => failure::bail!(
+            "Invalid expiration_ttl of {}. Expiration TTL must be at least {}.",
+            ttl,
+            EXPIRATION_MIN_TTL
+        ),
+        (Some(ttl), _) => Ok(Some(now + ttl)),
+        (None, Some(expiration)) if expiration < now + EXPIRATION_MIN_TTL => failure::bail!(
+            "Invalid expiration of {}. Expiration times must be at least {} seconds in the future.",
+            expiration,
+            EXPIRATION_MIN_TTL
+        ),
+        (None, expiration) => Ok(expiration),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stores_values_and_lists_keys_in_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let namespace = LocalNamespace::open_in(dir.path(), "ns").unwrap();
+        let with_metadata = PutOptions {
+            metadata: Some(serde_json::json!({ "x": 1 })),
+            ..PutOptions::default()
+        };
+
+        namespace.put("b", b"2", &PutOptions::default()).unwrap();
+        namespace.put("a", b"1", &with_metadata).unwrap();
+        namespace.put("b", b"two", &PutOptions::default()).unwrap();
+
+        let (value, metadata) = namespace.get("a").unwrap().unwrap();
+        assert_eq!(value, b"1");
+        assert_eq!(metadata, Some(serde_json::json!({ "x": 1 })));
+        assert_eq!(namespace.get("b").unwrap().unwrap().0, b"two");
+
+        let first = namespace
+            .list(&ListOptions {
+                limit: Some(1),
+                ..ListOptions::default()
+            })
+            .unwrap();
+        assert_eq!(first.keys[0].name, "a");
+        assert!(!first.list_complete);
+        let second = namespace
+            .list(&ListOptions {
+                limit: Some(1),
+                cursor: first.cursor,
+                ..ListOptions::default()
+            })
+            .unwrap();
+        assert_eq!(second.keys[0].name, "b");
+        assert!(second.list_complete);
+
+        namespace.delete("a").unwrap();
+        assert_eq!(namespace.get("a").unwrap(), None);
+        // the replaced and deleted values are cleaned up
+        assert_eq!(
+            fs::read_dir(dir.path().join("ns").join(BLOB_DIR))
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn it_rejects_namespace_ids_that_are_not_a_directory_name() {
+        let dir = tempfile::tempdir().unwrap();
+        for id in &["", ".", "..", "../ns", "ns/..", "/ns", "a/b"] {
+            assert!(LocalNamespace::open_in(dir.path(), id).is_err(), "{}", id);
+        }
+        assert!(LocalNamespace::open_in(dir.path(), "ns").is_ok());
+    }
+
+    #[test]
+    fn it_expires_keys_like_the_api() {
+        let dir = tempfile::tempdir().unwrap();
+        let namespace = LocalNamespace::open_in(dir.path(), "ns").unwrap();
+
+        let too_short = PutOptions {
+            expiration_ttl: Some(30),
+            ..PutOptions::default()
+        };
+        assert!(namespace.put("a", b"1", &too_short).is_err());
+        let ttl = PutOptions {
+            expiration: Some(1),
+            expiration_ttl: Some(60),
+            ..PutOptions::default()
+        };
+        namespace.put("a", b"1", &ttl).unwrap();
+        let listed = namespace.list(&ListOptions::default()).unwrap();
+        assert!(listed.keys[0].expiration.unwrap() >= now() + 60);
+
+        // as if the key was written a minute ago
+        namespace
+            .with_index(|index| {
+                index.get_mut("a").unwrap().expiration = Some(now() - 1);
+                namespace.write_index(index)
+            })
+            .unwrap();
+        assert_eq!(namespace.get("a").unwrap(), None);
+        assert!(namespace
+            .list(&ListOptions::default())
+            .unwrap()
+            .keys
+            .is_empty());
+    }
+}
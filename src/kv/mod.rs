@@ -1,3 +1,8 @@
 pub mod bulk;
+pub mod diff;
 pub mod key;
+pub mod local;
 pub mod namespace;
+pub mod pairs;
+pub mod snapshot;
+pub mod store;
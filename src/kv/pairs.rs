@@ -0,0 +1,203 @@
+use std::io::{self, BufRead, Read};
+
+use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use serde::Deserialize;
+
+/// Reads key-value pairs one at a time, from a JSON array of them like
+/// `[{"key": "test_key", "value": "test_value"}, ...]` or from JSON lines with one pair on each,
+/// so that large files never have to fit in memory.
+///
+/// Each pair comes with the number of bytes read for it, to drive a progress bar.
+pub struct PairReader<R> {
+    reader: Counted<R>,
+    format: Option<Format>,
+    // how many pairs have been read, to point at the one that's invalid
+    read: usize,
+    done: bool,
+}
+
+enum Format {
+    Array,
+    Lines,
+}
+
+impl<R: BufRead> PairReader<R> {
+    pub fn new(reader: R) -> Self {
+        PairReader {
+            reader: Counted {
+                inner: reader,
+                count: 0,
+            },
+            format: None,
+            read: 0,
+            done: false,
+        }
+    }
+
+    fn read_pair(&mut self) -> Result<Option<KeyValuePair>, failure::Error> {
+        let format = match &self.format {
+            Some(format) => format,
+            None => {
+                // a JSON array starts with a bracket, after any whitespace
+                let array = self.reader.skip_whitespace()? == Some(b'[');
+                if array {
+                    self.reader.consume(1);
+                }
+                self.format
+                    .get_or_insert(if array { Format::Array } else { Format::Lines })
+            }
+        };
+
+        match format {
+            Format::Array => {
+                match self.reader.skip_whitespace()? {
+                    Some(b']') => return Ok(None),
+                    Some(b',') if self.read > 0 => {
+                        self.reader.consume(1);
+                        self.reader.skip_whitespace()?;
+                    }
+                    None => failure::bail!("the JSON array isn't closed with a ]"),
+                    _ if self.read > 0 => {
+                        failure::bail!("expected , or ] after pair {}", self.read)
+                    }
+                    _ => {}
+                }
+                let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+                match KeyValuePair::deserialize(&mut deserializer) {
+                    Ok(pair) => Ok(Some(pair)),
+                    Err(e) => {
+                        failure::bail!("pair {} of the JSON array is invalid: {}", self.read + 1, e)
+                    }
+                }
+            }
+            Format::Lines => loop {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(&line) {
+                    Ok(pair) => return Ok(Some(pair)),
+                    Err(e) => failure::bail!("line {} is invalid: {}", self.read + 1, e),
+                }
+            },
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PairReader<R> {
+    type Item = Result<(KeyValuePair, u64), failure::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let start = self.reader.count;
+        match self.read_pair() {
+            Ok(Some(pair)) => {
+                self.read += 1;
+                Some(Ok((pair, self.reader.count - start)))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(failure::format_err!(
+                    "Failed to decode JSON: {}. Please make sure to follow the format, [{{\"key\": \"test_key\", \"value\": \"test_value\"}}, ...], or put one such pair on each line",
+                    e
+                )))
+            }
+        }
+    }
+}
+
+// Counts the bytes read from `inner`.
+struct Counted<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: BufRead> Counted<R> {
+    // Skips whitespace, returning the byte after it.
+    fn skip_whitespace(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let buf = self.fill_buf()?;
+            match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(i) => {
+                    let next = buf[i];
+                    self.consume(i);
+                    return Ok(Some(next));
+                }
+                None if buf.is_empty() => return Ok(None),
+                None => {
+                    let len = buf.len();
+                    self.consume(len);
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Counted<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.count += amt as u64;
+        self.inner.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str) -> Result<Vec<(String, u64)>, failure::Error> {
+        PairReader::new(input.as_bytes())
+            .map(|pair| pair.map(|(pair, len)| (pair.key, len)))
+            .collect()
+    }
+
+    #[test]
+    fn it_reads_json_arrays_and_lines() {
+        let array = r#" [{"key": "a", "value": "1"} , {"key":"b","value":"2","base64":false}] "#;
+        assert_eq!(
+            read(array).unwrap(),
+            vec![("a".to_string(), 28), ("b".to_string(), 41)]
+        );
+        assert_eq!(read("[]").unwrap(), vec![]);
+
+        let lines = "{\"key\": \"a\", \"value\": \"1\"}\n\n{\"key\": \"b\", \"value\": \"2\"}\n";
+        assert_eq!(
+            read(lines).unwrap(),
+            vec![("a".to_string(), 27), ("b".to_string(), 28)]
+        );
+
+        let e = read(r#"[{"key": "a", "value": "1"} {"key": "b"}]"#).unwrap_err();
+        assert!(
+            e.to_string().contains("expected , or ] after pair 1"),
+            "{}",
+            e
+        );
+        let e = read(r#"[{"key": "a", "value": "1"}, {"key": "b"}]"#).unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("pair 2 of the JSON array is invalid"),
+            "{}",
+            e
+        );
+    }
+}
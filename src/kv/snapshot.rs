@@ -0,0 +1,142 @@
+use std::iter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use serde_json::value::Value as JsonValue;
+
+use crate::kv::bulk;
+use crate::kv::local::now;
+use crate::kv::store::{BulkPair, KvStore, ListedKey};
+
+// the API rejects expirations less than this many seconds in the future
+const EXPIRATION_MIN_TTL: u64 = 60;
+
+/// One key of a namespace snapshot. `wrangler kv:namespace export` writes these as JSON lines,
+/// and `wrangler kv:namespace import` reads them back. `wrangler kv:key get --json` prints one.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnapshotEntry {
+    pub key: String,
+    /// The value as text, or base64 encoded when it isn't UTF-8.
+    pub value: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub base64: bool,
+    /// Seconds since the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JsonValue>,
+}
+
+impl SnapshotEntry {
+    pub fn new(key: ListedKey, value: Vec<u8>) -> Self {
+        let (value, base64) = match String::from_utf8(value) {
+            Ok(text) => (text, false),
+            Err(e) => (base64::encode(e.into_bytes()), true),
+        };
+        SnapshotEntry {
+            key: key.name,
+            value,
+            base64,
+            expiration: key.expiration,
+            metadata: key.metadata,
+        }
+    }
+
+    /// The entry with its value base64 encoded, even if it is text.
+    pub fn into_base64(self) -> Self {
+        if self.base64 {
+            self
+        } else {
+            SnapshotEntry {
+                value: base64::encode(&self.value),
+                base64: true,
+                ..self
+            }
+        }
+    }
+
+    pub fn value(&self) -> Result<Vec<u8>, failure::Error> {
+        if self.base64 {
+            Ok(base64::decode(&self.value)?)
+        } else {
+            Ok(self.value.clone().into_bytes())
+        }
+    }
+
+    /// The entry as a pair for `kv::bulk::put_with_metadata`.
+    pub fn into_pair(self) -> BulkPair {
+        BulkPair {
+            key: self.key,
+            value: self.value,
+            expiration: self.expiration.map(|e| e as i64),
+            expiration_ttl: None,
+            base64: if self.base64 { Some(true) } else { None },
+            metadata: self.metadata,
+        }
+    }
+}
+
+/// Writes `entries` to a namespace with `kv::bulk::put_with_metadata`, reading them as they're
+/// needed. Each entry comes with how far it moves `progress_bar`. Entries that have expired, or
+/// are about to, are skipped. Returns how many entries were written and how many were skipped.
+pub fn write(
+    store: &dyn KvStore,
+    namespace_id: &str,
+    source: &str,
+    mut entries: impl Iterator<Item = Result<(SnapshotEntry, u64), failure::Error>> + Send,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<(usize, usize), failure::Error> {
+    let now = now();
+    let (written, expired) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    // the progress of a skipped entry is added to that of the next one written
+    let mut skipped_progress = 0;
+    let pairs = iter::from_fn(|| loop {
+        let (entry, progress) = match entries.next()? {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+        match entry.expiration {
+            Some(expiration) if expiration < now + EXPIRATION_MIN_TTL => {
+                expired.fetch_add(1, Ordering::SeqCst);
+                skipped_progress += progress;
+            }
+            _ => {
+                written.fetch_add(1, Ordering::SeqCst);
+                let progress = progress + skipped_progress;
+                skipped_progress = 0;
+                return Some(Ok((entry.into_pair(), progress)));
+            }
+        }
+    });
+
+    bulk::put_with_metadata(store, namespace_id, source, pairs, progress_bar)?;
+    Ok((written.into_inner(), expired.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_base64_encodes_values_that_are_not_text() {
+        let key = |name: &str| ListedKey {
+            name: name.to_string(),
+            expiration: None,
+            metadata: None,
+        };
+
+        let text = SnapshotEntry::new(key("text"), b"hello".to_vec());
+        assert_eq!(
+            serde_json::to_string(&text).unwrap(),
+            r#"{"key":"text","value":"hello"}"#
+        );
+
+        let binary = SnapshotEntry::new(key("binary"), vec![0, 159, 146, 150]);
+        let line = serde_json::to_string(&binary).unwrap();
+        assert_eq!(line, r#"{"key":"binary","value":"AJ+Slg==","base64":true}"#);
+
+        let read: SnapshotEntry = serde_json::from_str(&line).unwrap();
+        assert_eq!(read.value().unwrap(), vec![0, 159, 146, 150]);
+    }
+}
@@ -0,0 +1,402 @@
+// TODO: (gabbi) The get and put requests should use cloudflare-rs instead of our
+// http::legacy_auth_client when https://github.com/cloudflare/cloudflare-rs/issues/26 is
+// handled (this is because their bodies are the raw value, not json).
+// Listing keys doesn't use cloudflare-rs either, because its keys have no metadata.
+
+use std::fmt;
+use std::time::Duration;
+
+use cloudflare::endpoints::workerskv::delete_bulk::DeleteBulk;
+use cloudflare::endpoints::workerskv::delete_key::DeleteKey;
+use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use cloudflare::endpoints::workerskv::write_bulk::WriteBulk;
+use cloudflare::framework::apiclient::ApiClient;
+use cloudflare::framework::auth::Credentials;
+use cloudflare::framework::response::ApiFailure;
+use cloudflare::framework::{Environment, HttpApiClient, HttpApiClientConfig};
+use reqwest::blocking::multipart;
+use serde::{Deserialize, Serialize};
+use serde_json::value::Value as JsonValue;
+use url::Url;
+
+use crate::commands::kv::{format_error, url_encode_key, validate_target};
+use crate::http::{self, feature::headers};
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+
+// How long bulk writes and deletes may take.
+const BULK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Where the key-value pairs of KV namespaces are kept: Workers KV itself, or the local
+/// store `wrangler dev --local` uses.
+///
+/// Stores are shared between threads, as `kv::bulk` writes several batches at once.
+pub trait KvStore: Sync {
+    /// The value of `key`, if it is set.
+    fn get(&self, namespace_id: &str, key: &str) -> Result<Option<Vec<u8>>, failure::Error>;
+
+    /// The value of `key` with its metadata and expiration, if it is set.
+    fn get_with_metadata(
+        &self,
+        namespace_id: &str,
+        key: &str,
+    ) -> Result<Option<(ListedKey, Vec<u8>)>, failure::Error> {
+        // a key is listed before the others it's a prefix of
+        let page = self.list(
+            namespace_id,
+            &ListOptions {
+                prefix: Some(key.to_string()),
+                limit: Some(10),
+                cursor: None,
+            },
+        )?;
+        let listed = match page.keys.into_iter().next() {
+            Some(listed) if listed.name == key => listed,
+            _ => return Ok(None),
+        };
+        Ok(self.get(namespace_id, key)?.map(|value| (listed, value)))
+    }
+
+    fn put(
+        &self,
+        namespace_id: &str,
+        key: &str,
+        value: Vec<u8>,
+        options: &PutOptions,
+    ) -> Result<(), failure::Error>;
+
+    fn delete(&self, namespace_id: &str, key: &str) -> Result<(), failure::Error>;
+
+    /// One page of the keys in a namespace, in order.
+    fn list(&self, namespace_id: &str, options: &ListOptions) -> Result<KeyPage, failure::Error>;
+
+    /// Writes one batch of a bulk upload; see `kv::bulk::put`. Failures worth retrying are
+    /// `TransientError`s.
+    fn write_batch(&self, namespace_id: &str, pairs: &[BulkPair]) -> Result<(), failure::Error>;
+
+    /// Deletes one batch of a bulk delete; see `kv::bulk::delete`. Failures worth retrying are
+    /// `TransientError`s.
+    fn delete_batch(&self, namespace_id: &str, keys: &[String]) -> Result<(), failure::Error>;
+}
+
+/// A request that failed in a way that may not happen again: it was rate limited, the API
+/// had an internal error, or it couldn't be reached.
+#[derive(Debug)]
+pub struct TransientError(pub String);
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl failure::Fail for TransientError {}
+
+#[derive(Clone, Debug, Default)]
+pub struct PutOptions {
+    /// Seconds since the Unix epoch.
+    pub expiration: Option<u64>,
+    /// Seconds from now. Takes precedence over `expiration`.
+    pub expiration_ttl: Option<u64>,
+    pub metadata: Option<JsonValue>,
+}
+
+/// A key-value pair of a bulk write: a `KeyValuePair` with the metadata that cloudflare-rs's
+/// bulk writes can't carry.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BulkPair {
+    pub key: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_ttl: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JsonValue>,
+}
+
+impl From<KeyValuePair> for BulkPair {
+    fn from(pair: KeyValuePair) -> Self {
+        BulkPair {
+            key: pair.key,
+            value: pair.value,
+            expiration: pair.expiration,
+            expiration_ttl: pair.expiration_ttl,
+            base64: pair.base64,
+            metadata: None,
+        }
+    }
+}
+
+impl BulkPair {
+    fn to_key_value_pair(&self) -> KeyValuePair {
+        KeyValuePair {
+            key: self.key.clone(),
+            value: self.value.clone(),
+            expiration: self.expiration,
+            expiration_ttl: self.expiration_ttl,
+            base64: self.base64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    pub prefix: Option<String>,
+    /// Defaults to, and can be at most, 1000.
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+/// A listed key, in the shape the API uses.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ListedKey {
+    pub name: String,
+    /// Seconds since the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JsonValue>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct KeyPage {
+    pub keys: Vec<ListedKey>,
+    pub list_complete: bool,
+    /// Lists the next page when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListResponse {
+    result: Vec<ListedKey>,
+    result_info: Option<JsonValue>,
+}
+
+/// Workers KV, through the Cloudflare API.
+pub struct ApiStore {
+    account_id: String,
+    user: GlobalUser,
+}
+
+impl ApiStore {
+    pub fn new(target: &Target, user: &GlobalUser) -> Result<Self, failure::Error> {
+        validate_target(target)?;
+        Ok(ApiStore {
+            account_id: target.account_id.clone(),
+            user: user.clone(),
+        })
+    }
+
+    fn value_url(&self, namespace_id: &str, key: &str) -> String {
+        format!(
+            "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
+            self.account_id,
+            namespace_id,
+            url_encode_key(key)
+        )
+    }
+
+    // Create a special API client that has a longer timeout than usual, given that KV operations
+    // can be lengthy if payloads are large.
+    fn bulk_api_client(&self) -> Result<HttpApiClient, failure::Error> {
+        let config = HttpApiClientConfig {
+            http_timeout: BULK_TIMEOUT,
+            default_headers: headers(None),
+        };
+
+        HttpApiClient::new(
+            Credentials::from(self.user.to_owned()),
+            config,
+            Environment::Production,
+        )
+    }
+}
+
+impl KvStore for ApiStore {
+    fn get(&self, namespace_id: &str, key: &str) -> Result<Option<Vec<u8>>, failure::Error> {
+        let client = http::legacy_auth_client(&self.user);
+        let res = client.get(&self.value_url(namespace_id, key)).send()?;
+
+        let response_status = res.status();
+        if response_status.is_success() {
+            Ok(Some(res.bytes()?.to_vec()))
+        } else if response_status == reqwest::StatusCode::NOT_FOUND {
+            Ok(None)
+        } else {
+            // This is logic pulled from cloudflare-rs for pretty error formatting right now;
+            // it will be redundant when we switch to using cloudflare-rs for all API requests.
+            let errors = res.json().unwrap_or_default();
+            failure::bail!(
+                "{}",
+                format_error(ApiFailure::Error(response_status, errors))
+            )
+        }
+    }
+
+    fn put(
+        &self,
+        namespace_id: &str,
+        key: &str,
+        value: Vec<u8>,
+        options: &PutOptions,
+    ) -> Result<(), failure::Error> {
+        // Add expiration and expiration_ttl query options as necessary.
+        let mut query_params: Vec<(&str, String)> = vec![];
+        if let Some(exp) = options.expiration {
+            query_params.push(("expiration", exp.to_string()))
+        };
+        if let Some(ttl) = options.expiration_ttl {
+            query_params.push(("expiration_ttl", ttl.to_string()))
+        };
+        let url = Url::parse_with_params(&self.value_url(namespace_id, key), query_params)?;
+
+        let client = http::legacy_auth_client(&self.user);
+        let res = match &options.metadata {
+            Some(metadata) => {
+                let form = multipart::Form::new()
+                    .part("value", multipart::Part::bytes(value))
+                    .text("metadata", metadata.to_string());
+                client.put(url).multipart(form).send()?
+            }
+            None => client.put(url).body(value).send()?,
+        };
+
+        let response_status = res.status();
+        if response_status.is_success() {
+            Ok(())
+        } else {
+            let errors = res.json().unwrap_or_default();
+            failure::bail!(
+                "{}",
+                format_error(ApiFailure::Error(response_status, errors))
+            )
+        }
+    }
+
+    fn delete(&self, namespace_id: &str, key: &str) -> Result<(), failure::Error> {
+        let client = http::cf_v4_client(&self.user)?;
+        match client.request(&DeleteKey {
+            account_identifier: &self.account_id,
+            namespace_identifier: namespace_id,
+            key, // this is url encoded within cloudflare-rs
+        }) {
+            Ok(_) => Ok(()),
+            Err(e) => failure::bail!("{}", format_error(e)),
+        }
+    }
+
+    fn list(&self, namespace_id: &str, options: &ListOptions) -> Result<KeyPage, failure::Error> {
+        // limit defaults to 1000 (the maximum)
+        let mut query_params: Vec<(&str, String)> = vec![];
+        if let Some(limit) = options.limit {
+            query_params.push(("limit", limit.to_string()))
+        };
+        if let Some(cursor) = &options.cursor {
+            query_params.push(("cursor", cursor.to_owned()))
+        };
+        if let Some(prefix) = &options.prefix {
+            query_params.push(("prefix", prefix.to_owned()))
+        };
+        let url = Url::parse_with_params(
+            &format!(
+                "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/keys",
+                self.account_id, namespace_id
+            ),
+            query_params,
+        )?;
+
+        let client = http::legacy_auth_client(&self.user);
+        let res = client.get(url).send()?;
+
+        let response_status = res.status();
+        if response_status.is_success() {
+            let success: ListResponse = res.json()?;
+            let cursor = extract_cursor(success.result_info);
+            log::info!("{:?}", cursor);
+            Ok(KeyPage {
+                keys: success.result,
+                list_complete: cursor.is_none(),
+                cursor,
+            })
+        } else {
+            let errors = res.json().unwrap_or_default();
+            failure::bail!(
+                "{}",
+                format_error(ApiFailure::Error(response_status, errors))
+            )
+        }
+    }
+
+    fn write_batch(&self, namespace_id: &str, pairs: &[BulkPair]) -> Result<(), failure::Error> {
+        if pairs.iter().all(|pair| pair.metadata.is_none()) {
+            return match self.bulk_api_client()?.request(&WriteBulk {
+                account_identifier: &self.account_id,
+                namespace_identifier: namespace_id,
+                bulk_key_value_pairs: pairs.iter().map(BulkPair::to_key_value_pair).collect(),
+            }) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(batch_error(e)),
+            };
+        }
+
+        let url = format!(
+            "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/bulk",
+            self.account_id, namespace_id
+        );
+        let client = http::legacy_auth_client(&self.user);
+        let res = match client.put(&url).timeout(BULK_TIMEOUT).json(pairs).send() {
+            Ok(res) => res,
+            Err(e) => return Err(TransientError(e.to_string()).into()),
+        };
+
+        let response_status = res.status();
+        if response_status.is_success() {
+            Ok(())
+        } else {
+            let errors = res.json().unwrap_or_default();
+            Err(batch_error(ApiFailure::Error(response_status, errors)))
+        }
+    }
+
+    fn delete_batch(&self, namespace_id: &str, keys: &[String]) -> Result<(), failure::Error> {
+        match self.bulk_api_client()?.request(&DeleteBulk {
+            account_identifier: &self.account_id,
+            namespace_identifier: namespace_id,
+            bulk_keys: keys.to_owned(),
+        }) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(batch_error(e)),
+        }
+    }
+}
+
+fn batch_error(e: ApiFailure) -> failure::Error {
+    let transient = match &e {
+        ApiFailure::Error(status, _) => {
+            *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        }
+        ApiFailure::Invalid(_) => true,
+    };
+    let message = format_error(e);
+    if transient {
+        TransientError(message).into()
+    } else {
+        failure::err_msg(message)
+    }
+}
+
+// Returns Some(cursor) if cursor is non-empty, otherwise returns None.
+fn extract_cursor(result_info: Option<JsonValue>) -> Option<String> {
+    let result_info = result_info.unwrap();
+    let returned_cursor_value = &result_info["cursor"];
+    let returned_cursor = returned_cursor_value.as_str().unwrap().to_string();
+    if returned_cursor.is_empty() {
+        None
+    } else {
+        Some(returned_cursor)
+    }
+}
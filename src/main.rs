@@ -5,7 +5,7 @@ extern crate tokio;
 
 use std::convert::TryFrom;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{App, AppSettings, Arg, ArgGroup, SubCommand};
@@ -13,14 +13,17 @@ use exitfailure::ExitFailure;
 use url::Url;
 
 use wrangler::commands;
-use wrangler::commands::kv::key::{parse_metadata, KVMetaData};
+use wrangler::commands::kv::key::{parse_metadata, GetOptions, KVMetaData};
+use wrangler::deploy;
 use wrangler::installer;
+use wrangler::kv::store::ApiStore;
 use wrangler::preview::{HttpMethod, PreviewOpt};
 use wrangler::settings;
 use wrangler::settings::global_user::GlobalUser;
 use wrangler::settings::toml::TargetType;
 use wrangler::terminal::message::{Message, Output, StdOut};
 use wrangler::terminal::{emoji, interactive, styles};
+use wrangler::upload::form::SessionConfig;
 use wrangler::version::background_check_for_updates;
 
 fn main() -> Result<(), ExitFailure> {
@@ -81,6 +84,36 @@ fn run() -> Result<(), failure::Error> {
         .help("applies the command to the preview namespace when combined with --binding")
         .long("preview")
         .takes_value(false);
+    let kv_local_arg = Arg::with_name("local")
+        .help("applies the command to the local store `wrangler dev --local` uses, in which bindings use their preview namespace")
+        .long("local")
+        .takes_value(false);
+
+    // These args are for commands that compare or copy a namespace between environments
+    let kv_from_env_arg = Arg::with_name("from-env")
+        .help("the environment to take the namespace from. Defaults to the top level of the configuration")
+        .long("from-env")
+        .value_name("ENVIRONMENT NAME")
+        .takes_value(true);
+    let kv_to_env_arg = Arg::with_name("to-env")
+        .help("the environment the namespace is compared with. Defaults to the top level of the configuration")
+        .long("to-env")
+        .value_name("ENVIRONMENT NAME")
+        .takes_value(true);
+    let kv_from_preview_arg = Arg::with_name("from-preview")
+        .help("take the preview namespace of --from-env")
+        .long("from-preview")
+        .takes_value(false);
+    let kv_to_preview_arg = Arg::with_name("to-preview")
+        .help("use the preview namespace of --to-env")
+        .long("to-preview")
+        .takes_value(false);
+    let kv_prefix_arg = Arg::with_name("prefix")
+        .help("only include keys starting with this prefix")
+        .short("p")
+        .long("prefix")
+        .value_name("STRING")
+        .takes_value(true);
 
     // This arg is for any action that uses environments (e.g. KV subcommands, publish)
     let environment_arg = Arg::with_name("env")
@@ -156,6 +189,66 @@ fn run() -> Result<(), failure::Error> {
                         .arg(silent_verbose_arg.clone())
                         .arg(wrangler_file.clone())
                 )
+                .subcommand(
+                    SubCommand::with_name("diff")
+                        .about("List the keys that are added, changed or removed in a namespace compared with the same binding in another environment")
+                        .arg(kv_binding_arg.clone().required(true))
+                        .arg(kv_from_env_arg.clone())
+                        .arg(kv_from_preview_arg.clone())
+                        .arg(kv_to_env_arg.clone())
+                        .arg(kv_to_preview_arg.clone())
+                        .arg(kv_prefix_arg.clone())
+                        .arg(silent_verbose_arg.clone())
+                        .arg(wrangler_file.clone())
+                )
+                .subcommand(
+                    SubCommand::with_name("copy")
+                        .about("Make a namespace match the same binding in another environment, writing only the keys that differ")
+                        .arg(kv_binding_arg.clone().required(true))
+                        .arg(kv_from_env_arg.clone())
+                        .arg(kv_from_preview_arg.clone())
+                        .arg(kv_to_env_arg.clone().help("the environment to copy the namespace to. Defaults to the top level of the configuration"))
+                        .arg(kv_to_preview_arg.clone())
+                        .arg(kv_prefix_arg.clone())
+                        .arg(
+                            Arg::with_name("dry-run")
+                            .help("print the keys that would be copied and deleted, and don't change them")
+                            .long("dry-run")
+                            .takes_value(false)
+                        )
+                        .arg(silent_verbose_arg.clone())
+                        .arg(wrangler_file.clone())
+                )
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Write every key in a namespace, with its value, metadata and expiration, to stdout as JSON lines")
+                        .arg(kv_binding_arg.clone())
+                        .arg(kv_namespace_id_arg.clone())
+                        .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
+                        .group(kv_namespace_specifier_group.clone())
+                        .arg(environment_arg.clone())
+                        .arg(silent_verbose_arg.clone())
+                        .arg(wrangler_file.clone())
+                )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Load the keys in a file written by `kv:namespace export` into a namespace")
+                        .arg(kv_binding_arg.clone())
+                        .arg(kv_namespace_id_arg.clone())
+                        .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
+                        .group(kv_namespace_specifier_group.clone())
+                        .arg(environment_arg.clone())
+                        .arg(
+                            Arg::with_name("path")
+                            .help("the file to import. If an earlier import of it into the same namespace failed, it resumes where that one stopped")
+                            .required(true)
+                            .index(1)
+                        )
+                        .arg(silent_verbose_arg.clone())
+                        .arg(wrangler_file.clone())
+                )
                 .arg(silent_verbose_arg.clone())
         )
         .subcommand(
@@ -172,6 +265,7 @@ fn run() -> Result<(), failure::Error> {
                         .arg(kv_binding_arg.clone())
                         .arg(kv_namespace_id_arg.clone())
                         .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
                         .group(kv_namespace_specifier_group.clone())
                         .arg(environment_arg.clone())
                         .arg(
@@ -226,6 +320,7 @@ fn run() -> Result<(), failure::Error> {
                         .arg(kv_binding_arg.clone())
                         .arg(kv_namespace_id_arg.clone())
                         .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
                         .group(kv_namespace_specifier_group.clone())
                         .arg(environment_arg.clone())
                         .arg(
@@ -234,6 +329,32 @@ fn run() -> Result<(), failure::Error> {
                             .required(true)
                             .index(1)
                         )
+                        .arg(
+                            Arg::with_name("with-metadata")
+                            .help("print the key, value, metadata and expiration as JSON")
+                            .long("with-metadata")
+                            .takes_value(false)
+                        )
+                        .arg(
+                            Arg::with_name("json")
+                            .help("print the key and value as JSON, with values that aren't UTF-8 base64 encoded")
+                            .long("json")
+                            .takes_value(false)
+                        )
+                        .arg(
+                            Arg::with_name("base64")
+                            .help("base64 encode the value")
+                            .long("base64")
+                            .takes_value(false)
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                            .help("write the value to a file instead of stdout")
+                            .short("o")
+                            .long("output")
+                            .value_name("FILE")
+                            .takes_value(true)
+                        )
                         .arg(silent_verbose_arg.clone())
                         .arg(wrangler_file.clone())
                 )
@@ -243,6 +364,7 @@ fn run() -> Result<(), failure::Error> {
                         .arg(kv_binding_arg.clone())
                         .arg(kv_namespace_id_arg.clone())
                         .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
                         .group(kv_namespace_specifier_group.clone())
                         .arg(environment_arg.clone())
                         .arg(
@@ -260,6 +382,7 @@ fn run() -> Result<(), failure::Error> {
                         .arg(kv_binding_arg.clone())
                         .arg(kv_namespace_id_arg.clone())
                         .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
                         .group(kv_namespace_specifier_group.clone())
                         .arg(environment_arg.clone())
                         .arg(
@@ -270,6 +393,26 @@ fn run() -> Result<(), failure::Error> {
                             .value_name("STRING")
                             .takes_value(true),
                         )
+                        .arg(
+                            Arg::with_name("limit")
+                            .help("list only this many keys, at most 1000, with the cursor for the next ones")
+                            .long("limit")
+                            .value_name("COUNT")
+                            .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("cursor")
+                            .help("list the keys after the ones an earlier list returned this cursor with")
+                            .long("cursor")
+                            .value_name("CURSOR")
+                            .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("with-metadata")
+                            .help("include each key's metadata")
+                            .long("with-metadata")
+                            .takes_value(false),
+                        )
                         .arg(silent_verbose_arg.clone())
                         .arg(wrangler_file.clone())
                 )
@@ -288,14 +431,41 @@ fn run() -> Result<(), failure::Error> {
                         .arg(kv_binding_arg.clone())
                         .arg(kv_namespace_id_arg.clone())
                         .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
                         .group(kv_namespace_specifier_group.clone())
                         .arg(environment_arg.clone())
                         .arg(
                             Arg::with_name("path")
-                            .help("the JSON file of key-value pairs to upload, in form [{\"key\":..., \"value\":...}\"...]")
-                            .required(true)
+                            .help("the JSON file of key-value pairs to upload, in form [{\"key\":..., \"value\":...}\"...], or with one such pair on each line")
                             .index(1)
                         )
+                        .arg(
+                            Arg::with_name("dir")
+                            .help("a directory whose files to upload, each to the key of its path in it")
+                            .long("dir")
+                            .value_name("PATH")
+                            .takes_value(true)
+                        )
+                        .arg(
+                            Arg::with_name("csv")
+                            .help("a CSV file of key-value pairs to upload, with a header naming its columns: key, value, and optionally expiration_ttl and metadata")
+                            .long("csv")
+                            .value_name("FILE")
+                            .takes_value(true)
+                        )
+                        .arg(
+                            Arg::with_name("prefix")
+                            .help("the prefix to put before the path of each file uploaded with --dir")
+                            .long("prefix")
+                            .value_name("STRING")
+                            .takes_value(true)
+                            .requires("dir")
+                        )
+                        .group(
+                            ArgGroup::with_name("input")
+                            .args(&["path", "dir", "csv"])
+                            .required(true)
+                        )
                         .arg(wrangler_file.clone())
                         .arg(silent_verbose_arg.clone())
                 )
@@ -305,14 +475,26 @@ fn run() -> Result<(), failure::Error> {
                         .arg(kv_namespace_id_arg.clone())
                         .group(kv_namespace_specifier_group.clone())
                         .arg(kv_preview_arg.clone())
+                        .arg(kv_local_arg.clone())
                         .arg(environment_arg.clone())
                         .about("Delete multiple keys and their values from a namespace")
                         .arg(
                             Arg::with_name("path")
                             .help("the JSON file of key-value pairs to upload, in form [\"<example-key>\", ...]")
-                            .required(true)
                             .index(1)
                         )
+                        .arg(
+                            Arg::with_name("prefix")
+                            .help("deletes every key starting with this prefix instead")
+                            .long("prefix")
+                            .value_name("STRING")
+                            .takes_value(true)
+                        )
+                        .group(
+                            ArgGroup::with_name("keys")
+                            .args(&["path", "prefix"])
+                            .required(true)
+                        )
                         .arg(wrangler_file.clone())
                         .arg(silent_verbose_arg.clone())
                 )
@@ -377,6 +559,31 @@ fn run() -> Result<(), failure::Error> {
                         .arg(wrangler_file.clone())
                         .arg(silent_verbose_arg.clone())
                 )
+                .subcommand(
+                    SubCommand::with_name("bulk")
+                        .about("Create, update and optionally delete secrets so a script's secrets match a file")
+                        .arg(
+                            Arg::with_name("path")
+                            .help("a JSON file of secrets in form {\"<name>\": \"<value>\", ...}, or a dotenv file. Use - to read from stdin")
+                            .required(true)
+                            .index(1)
+                        )
+                        .arg(
+                            Arg::with_name("prune")
+                            .help("delete secrets on the script that aren't in the file")
+                            .long("prune")
+                            .takes_value(false)
+                        )
+                        .arg(
+                            Arg::with_name("dry-run")
+                            .help("print the planned changes, without values, and don't make them")
+                            .long("dry-run")
+                            .takes_value(false)
+                        )
+                        .arg(environment_arg.clone())
+                        .arg(wrangler_file.clone())
+                        .arg(silent_verbose_arg.clone())
+                )
         )
         .subcommand(
             SubCommand::with_name("generate")
@@ -450,6 +657,14 @@ fn run() -> Result<(), failure::Error> {
                         .long("env")
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::with_name("out")
+                        .help("write the built worker to a directory that `wrangler publish --from-artifact` can publish to any environment")
+                        .long("out")
+                        .alias("artifact")
+                        .takes_value(true)
+                        .value_name("DIR")
+                )
                 .arg(wrangler_file.clone())
                 .arg(silent_verbose_arg.clone()),
         )
@@ -544,6 +759,24 @@ fn run() -> Result<(), failure::Error> {
                         .long("upstream-protocol")
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::with_name("local")
+                        .help("run your worker with Node.js on this machine instead of on Cloudflare, with KV namespaces stored in .wrangler/state")
+                        .long("local")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::with_name("test-scheduled")
+                        .help("run the worker's scheduled handler when `s` is entered or /__scheduled is requested")
+                        .long("test-scheduled")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::with_name("run-scheduled")
+                        .help("like --test-scheduled, and also run the scheduled handler on the schedule in [triggers]")
+                        .long("run-scheduled")
+                        .takes_value(false)
+                )
         )
         .subcommand(
             SubCommand::with_name("publish")
@@ -573,8 +806,112 @@ fn run() -> Result<(), failure::Error> {
                     .long("output")
                     .takes_value(true)
                     .possible_value("json")
+                )
+                .arg(
+                    Arg::with_name("from-artifact")
+                        .help("publish a build artifact written by `wrangler build --out`, instead of building")
+                        .long("from-artifact")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .conflicts_with("dry-run")
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .help("build your worker and write what would be published to --outdir, without publishing it")
+                        .long("dry-run")
+                        .takes_value(false)
+                        .requires("outdir")
+                )
+                .arg(
+                    Arg::with_name("outdir")
+                        .help("the directory to write a dry run to")
+                        .long("outdir")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .requires("dry-run")
+                )
+                .arg(
+                    Arg::with_name("prune-routes")
+                        .help("delete routes that point to this worker, but are no longer in your configuration, including those in zones it was published to before, going by its deployment history")
+                        .long("prune-routes")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .help("reassign routes that point to another worker to this one")
+                        .long("force")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::with_name("force-full-sync")
+                        .help("read every file of your [site] and list every key of its namespace, instead of trusting what's cached from the last publish")
+                        .long("force-full-sync")
+                        .takes_value(false)
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("deployments")
+                .about(&*format!(
+                    "{} Show the deployments recorded by publish",
+                    emoji::UP
+                ))
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List the recorded deployments of a script, newest first")
+                        .arg(environment_arg.clone())
+                        .arg(wrangler_file.clone())
+                        .arg(silent_verbose_arg.clone())
+                        .arg(
+                            Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .possible_value("json")
+                        )
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("triggers")
+                .about(&*format!(
+                    "{} Inspect the crons in [triggers]",
+                    emoji::WORKER
+                ))
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("next")
+                        .about("Print the next times each cron fires, in UTC and local time")
+                        .arg(
+                            Arg::with_name("count")
+                                .help("how many times to print for each cron")
+                                .short("n")
+                                .long("count")
+                                .takes_value(true)
+                                .value_name("N")
+                                .default_value("5")
+                        )
+                        .arg(environment_arg.clone())
+                        .arg(wrangler_file.clone())
+                        .arg(silent_verbose_arg.clone())
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rollback")
+                .about(&*format!(
+                    "{} Publish a recorded deployment again, with its routes and schedules",
+                    emoji::UP
+                ))
+                .arg(
+                    Arg::with_name("to")
+                        .help("the id of the deployment to roll back to (find using `wrangler deployments list`). Defaults to the deployment before the latest one")
+                        .long("to")
+                        .takes_value(true)
+                        .value_name("ID")
+                )
+                .arg(environment_arg.clone())
+                .arg(wrangler_file.clone())
+                .arg(silent_verbose_arg.clone()),
+        )
         .subcommand(
             SubCommand::with_name("config")
                 .about(&*format!(
@@ -593,7 +930,27 @@ fn run() -> Result<(), failure::Error> {
                         .long("no-verify")
                         .takes_value(false),
                 )
-                .arg(silent_verbose_arg.clone()),
+                .arg(silent_verbose_arg.clone())
+                .subcommand(
+                    SubCommand::with_name("validate")
+                        .about("Check your configuration file and every environment in it for problems")
+                        .arg(wrangler_file.clone())
+                        .arg(silent_verbose_arg.clone())
+                )
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Show the configuration an environment resolves to, and where each value comes from")
+                        .arg(environment_arg.clone())
+                        .arg(wrangler_file.clone())
+                        .arg(silent_verbose_arg.clone())
+                        .arg(
+                            Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .possible_value("json")
+                        )
+                ),
         )
         .subcommand(
             SubCommand::with_name("subdomain")
@@ -659,25 +1016,46 @@ fn run() -> Result<(), failure::Error> {
     );
 
     if let Some(matches) = matches.subcommand_matches("config") {
-        // If api-key flag isn't present, use the default auth option (API token)
-        let default = !matches.is_present("api-key");
-
-        let user: GlobalUser = if default {
-            // API Tokens are the default
-            StdOut::billboard(&format!("To find your API Token, go to {}\nand create it using the \"Edit Cloudflare Workers\" template.\n\nConsider using {} which only requires your Cloudflare username and password.\n\nIf you are trying to use your Global API Key instead of an API Token\n{}, run {}.", api_token_url, wrangler_login_msg, not_recommended_msg, recommended_cmd_msg));
-            let api_token: String = interactive::get_user_input("Enter API Token: ");
-            GlobalUser::TokenAuth { api_token }
+        if let Some(validate_matches) = matches.subcommand_matches("validate") {
+            let config_path = Path::new(
+                validate_matches
+                    .value_of("config")
+                    .unwrap_or(commands::DEFAULT_CONFIG_PATH),
+            );
+            commands::config::validate(config_path)?;
+        } else if let Some(show_matches) = matches.subcommand_matches("show") {
+            let config_path = Path::new(
+                show_matches
+                    .value_of("config")
+                    .unwrap_or(commands::DEFAULT_CONFIG_PATH),
+            );
+            let env = show_matches.value_of("env");
+            if show_matches.value_of("output") == Some("json") {
+                commands::config::show(config_path, env, Output::Json)?;
+            } else {
+                commands::config::show(config_path, env, Output::PlainText)?;
+            }
         } else {
-            StdOut::billboard(&format!("We don't recommend using your Global API Key!\nPlease consider using an API Token instead.\n\n{}", token_support_url));
-            let email: String = interactive::get_user_input("Enter Email: ");
-            let api_key: String = interactive::get_user_input("Enter Global API Key: ");
+            // If api-key flag isn't present, use the default auth option (API token)
+            let default = !matches.is_present("api-key");
 
-            GlobalUser::GlobalKeyAuth { email, api_key }
-        };
+            let user: GlobalUser = if default {
+                // API Tokens are the default
+                StdOut::billboard(&format!("To find your API Token, go to {}\nand create it using the \"Edit Cloudflare Workers\" template.\n\nConsider using {} which only requires your Cloudflare username and password.\n\nIf you are trying to use your Global API Key instead of an API Token\n{}, run {}.", api_token_url, wrangler_login_msg, not_recommended_msg, recommended_cmd_msg));
+                let api_token: String = interactive::get_user_input("Enter API Token: ");
+                GlobalUser::TokenAuth { api_token }
+            } else {
+                StdOut::billboard(&format!("We don't recommend using your Global API Key!\nPlease consider using an API Token instead.\n\n{}", token_support_url));
+                let email: String = interactive::get_user_input("Enter Email: ");
+                let api_key: String = interactive::get_user_input("Enter Global API Key: ");
+
+                GlobalUser::GlobalKeyAuth { email, api_key }
+            };
 
-        let verify = !matches.is_present("no-verify");
+            let verify = !matches.is_present("no-verify");
 
-        commands::global_config(&user, verify)?;
+            commands::global_config(&user, verify)?;
+        }
     } else if let Some(matches) = matches.subcommand_matches("generate") {
         let name = matches.value_of("name").unwrap_or("worker");
         let site = matches.is_present("site");
@@ -738,6 +1116,7 @@ fn run() -> Result<(), failure::Error> {
         let env = matches.value_of("env");
         is_preview = true;
         let target = manifest.get_target(env, is_preview)?;
+        let dev_vars = settings::dev_vars::load(config_path, env)?;
 
         // the preview command can be called with or without a Global User having been config'd
         // so we convert this Result into an Option
@@ -775,6 +1154,7 @@ fn run() -> Result<(), failure::Error> {
             body,
             livereload,
             headless,
+            dev_vars,
         };
 
         commands::preview(target, user, options, verbose)?;
@@ -812,7 +1192,19 @@ fn run() -> Result<(), failure::Error> {
         let deployments = manifest.get_deployments(env)?;
         is_preview = true;
         let target = manifest.get_target(env, is_preview)?;
-        let user = settings::global_user::GlobalUser::new().ok();
+        let run_scheduled = matches.is_present("run-scheduled");
+        let session_config = SessionConfig {
+            config: None,
+            dev_vars: settings::dev_vars::load(config_path, env)?,
+            test_scheduled: run_scheduled || matches.is_present("test-scheduled"),
+        };
+        let local = matches.is_present("local");
+        // a local runtime needs no account
+        let user = if local {
+            None
+        } else {
+            settings::global_user::GlobalUser::new().ok()
+        };
         let verbose = matches.is_present("verbose");
 
         let local_protocol = Protocol::try_from(local_protocol_str.unwrap_or("http"))?;
@@ -827,6 +1219,9 @@ fn run() -> Result<(), failure::Error> {
             server_config,
             local_protocol,
             upstream_protocol,
+            local,
+            run_scheduled,
+            session_config,
             verbose,
         )?;
     } else if matches.subcommand_matches("whoami").is_some() {
@@ -835,9 +1230,6 @@ fn run() -> Result<(), failure::Error> {
 
         commands::whoami(&user)?;
     } else if let Some(matches) = matches.subcommand_matches("publish") {
-        log::info!("Getting User settings");
-        let user = settings::global_user::GlobalUser::new()?;
-
         let release = matches.is_present("release");
         if release {
             let publish_release_msg = styles::highlight("`wrangler publish --release`");
@@ -860,11 +1252,85 @@ fn run() -> Result<(), failure::Error> {
         let env = matches.value_of("env");
         let mut target = manifest.get_target(env, is_preview)?;
         let deploy_config = manifest.get_deployments(env)?;
-        if matches.is_present("output") && matches.value_of("output") == Some("json") {
-            commands::publish(&user, &mut target, deploy_config, Output::Json)?;
+        let output = if matches.is_present("output") && matches.value_of("output") == Some("json") {
+            Output::Json
+        } else {
+            Output::PlainText
+        };
+
+        log::info!("Getting User settings");
+        if matches.is_present("dry-run") {
+            // a dry run doesn't need credentials, but uses them to compare against what's
+            // already published
+            let user = settings::global_user::GlobalUser::new().ok();
+            let outdir = Path::new(matches.value_of("outdir").unwrap());
+            commands::publish::dry_run(user.as_ref(), &mut target, deploy_config, outdir, output)?;
         } else {
-            commands::publish(&user, &mut target, deploy_config, Output::PlainText)?;
+            let user = settings::global_user::GlobalUser::new()?;
+            let from_artifact = matches.value_of("from-artifact").map(Path::new);
+            let route_options = deploy::RouteOptions {
+                prune: matches.is_present("prune-routes"),
+                force: matches.is_present("force"),
+            };
+            commands::publish(
+                &user,
+                &mut target,
+                deploy_config,
+                route_options,
+                from_artifact,
+                matches.is_present("force-full-sync"),
+                output,
+            )?;
+        }
+    } else if let Some(matches) = matches.subcommand_matches("deployments") {
+        if let Some(list_matches) = matches.subcommand_matches("list") {
+            let config_path = Path::new(
+                list_matches
+                    .value_of("config")
+                    .unwrap_or(commands::DEFAULT_CONFIG_PATH),
+            );
+            let manifest = settings::toml::Manifest::new(config_path)?;
+            let env = list_matches.value_of("env");
+            let target = manifest.get_target(env, is_preview)?;
+            if list_matches.value_of("output") == Some("json") {
+                commands::deployments::list(&target, Output::Json)?;
+            } else {
+                commands::deployments::list(&target, Output::PlainText)?;
+            }
         }
+    } else if let Some(matches) = matches.subcommand_matches("triggers") {
+        if let Some(next_matches) = matches.subcommand_matches("next") {
+            let config_path = Path::new(
+                next_matches
+                    .value_of("config")
+                    .unwrap_or(commands::DEFAULT_CONFIG_PATH),
+            );
+            let manifest = settings::toml::Manifest::new(config_path)?;
+            let env = next_matches.value_of("env");
+            let deployments = manifest.get_deployments(env)?;
+            let count = match next_matches.value_of("count") {
+                Some(count) => match count.parse::<usize>() {
+                    Ok(count) if count > 0 => count,
+                    _ => failure::bail!("--count must be a number greater than 0"),
+                },
+                None => 5,
+            };
+            commands::triggers::next(&deployments, count)?;
+        }
+    } else if let Some(matches) = matches.subcommand_matches("rollback") {
+        log::info!("Getting User settings");
+        let user = settings::global_user::GlobalUser::new()?;
+
+        log::info!("Getting project settings");
+        let config_path = Path::new(
+            matches
+                .value_of("config")
+                .unwrap_or(commands::DEFAULT_CONFIG_PATH),
+        );
+        let manifest = settings::toml::Manifest::new(config_path)?;
+        let env = matches.value_of("env");
+        let target = manifest.get_target(env, is_preview)?;
+        commands::deployments::rollback(&user, &target, matches.value_of("to"))?;
     } else if let Some(matches) = matches.subcommand_matches("subdomain") {
         log::info!("Getting project settings");
         let config_path = Path::new(
@@ -959,11 +1425,17 @@ fn run() -> Result<(), failure::Error> {
                 let target = manifest.get_target(env, is_preview)?;
                 commands::secret::list_secrets(&user, &target)?;
             }
+            ("bulk", Some(bulk_matches)) => {
+                let env = bulk_matches.value_of("env");
+                let target = manifest.get_target(env, is_preview)?;
+                let path = bulk_matches.value_of("path").unwrap();
+                let prune = bulk_matches.is_present("prune");
+                let dry_run = bulk_matches.is_present("dry-run");
+                commands::secret::bulk(path, prune, dry_run, &user, &target)?;
+            }
             _ => unreachable!(),
         }
     } else if let Some(kv_matches) = matches.subcommand_matches("kv:namespace") {
-        let user = settings::global_user::GlobalUser::new()?;
-
         let (subcommand, subcommand_matches) = kv_matches.subcommand();
 
         let config_path = Path::new(
@@ -976,12 +1448,14 @@ fn run() -> Result<(), failure::Error> {
 
         match (subcommand, subcommand_matches) {
             ("create", Some(create_matches)) => {
+                let user = settings::global_user::GlobalUser::new()?;
                 is_preview = create_matches.is_present("preview");
                 let env = create_matches.value_of("env");
                 let binding = create_matches.value_of("binding").unwrap();
                 commands::kv::namespace::create(&manifest, is_preview, env, &user, binding)?;
             }
             ("delete", Some(delete_matches)) => {
+                let user = settings::global_user::GlobalUser::new()?;
                 is_preview = delete_matches.is_present("preview");
                 let env = delete_matches.value_of("env");
                 let target = manifest.get_target(env, is_preview)?;
@@ -997,14 +1471,74 @@ fn run() -> Result<(), failure::Error> {
                 commands::kv::namespace::delete(&target, &user, &namespace_id)?;
             }
             ("list", Some(list_matches)) => {
+                let user = settings::global_user::GlobalUser::new()?;
                 let env = list_matches.value_of("env");
                 let target = manifest.get_target(env, is_preview)?;
                 commands::kv::namespace::list(&target, &user)?;
             }
+            (subcommand @ "diff", Some(diff_matches))
+            | (subcommand @ "copy", Some(diff_matches)) => {
+                let binding = diff_matches.value_of("binding").unwrap();
+                let from_target = manifest.get_target(
+                    diff_matches.value_of("from-env"),
+                    diff_matches.is_present("from-preview"),
+                )?;
+                let to_target = manifest.get_target(
+                    diff_matches.value_of("to-env"),
+                    diff_matches.is_present("to-preview"),
+                )?;
+                let from_id = commands::kv::get_namespace_id(&from_target, binding)?;
+                let to_id = commands::kv::get_namespace_id(&to_target, binding)?;
+                if from_id == to_id {
+                    failure::bail!(
+                        "Both sides use namespace {} for {}; pick another environment with --from-env or --to-env, or a preview namespace with --from-preview or --to-preview",
+                        from_id,
+                        binding
+                    )
+                }
+
+                let user = settings::global_user::GlobalUser::new()?;
+                let from = ApiStore::new(&from_target, &user)?;
+                let to = ApiStore::new(&to_target, &user)?;
+                let prefix = diff_matches.value_of("prefix");
+                if subcommand == "diff" {
+                    commands::kv::namespace::diff(&from, &from_id, &to, &to_id, prefix)?;
+                } else {
+                    let dry_run = diff_matches.is_present("dry-run");
+                    commands::kv::namespace::copy(&from, &from_id, &to, &to_id, prefix, dry_run)?;
+                }
+            }
+            (subcommand @ "export", Some(snapshot_matches))
+            | (subcommand @ "import", Some(snapshot_matches)) => {
+                // the local store holds what `wrangler dev --local` writes to preview namespaces
+                is_preview =
+                    snapshot_matches.is_present("preview") || snapshot_matches.is_present("local");
+                let env = snapshot_matches.value_of("env");
+                let target = manifest.get_target(env, is_preview)?;
+                let namespace_id = match snapshot_matches.value_of("binding") {
+                    Some(namespace_binding) => {
+                        commands::kv::get_namespace_id(&target, namespace_binding)?
+                    }
+                    None => snapshot_matches
+                        .value_of("namespace-id")
+                        .unwrap() // clap configs ensure that if "binding" isn't present, "namespace-id" must be.
+                        .to_string(),
+                };
+                let store = commands::kv::store(&target, snapshot_matches.is_present("local"))?;
+                if subcommand == "export" {
+                    commands::kv::namespace::export(store.as_ref(), &namespace_id)?;
+                } else {
+                    let path = snapshot_matches.value_of("path").unwrap();
+                    commands::kv::namespace::import(
+                        store.as_ref(),
+                        &namespace_id,
+                        Path::new(path),
+                    )?;
+                }
+            }
             _ => unreachable!(),
         }
     } else if let Some(kv_matches) = matches.subcommand_matches("kv:key") {
-        let user = settings::global_user::GlobalUser::new()?;
         // Get environment and bindings
         let (subcommand, subcommand_matches) = kv_matches.subcommand();
 
@@ -1018,7 +1552,9 @@ fn run() -> Result<(), failure::Error> {
 
         let (target, namespace_id) = match subcommand_matches {
             Some(subcommand_matches) => {
-                is_preview = subcommand_matches.is_present("preview");
+                // the local store holds what `wrangler dev --local` writes to preview namespaces
+                is_preview = subcommand_matches.is_present("preview")
+                    || subcommand_matches.is_present("local");
                 let env = subcommand_matches.value_of("env");
                 let target = manifest.get_target(env, is_preview)?;
                 let namespace_id = match subcommand_matches.value_of("binding") {
@@ -1034,11 +1570,18 @@ fn run() -> Result<(), failure::Error> {
             }
             None => unreachable!(), // this is unreachable because all kv:key commands have required arguments.
         };
+        let store = commands::kv::store(&target, subcommand_matches.unwrap().is_present("local"))?;
 
         match (subcommand, subcommand_matches) {
             ("get", Some(get_key_matches)) => {
                 let key = get_key_matches.value_of("key").unwrap();
-                commands::kv::key::get(&target, &user, &namespace_id, key)?
+                let options = GetOptions {
+                    with_metadata: get_key_matches.is_present("with-metadata"),
+                    json: get_key_matches.is_present("json"),
+                    base64: get_key_matches.is_present("base64"),
+                    output: get_key_matches.value_of("output").map(PathBuf::from),
+                };
+                commands::kv::key::get(store.as_ref(), &namespace_id, key, &options)?
             }
             ("put", Some(put_key_matches)) => {
                 let key = put_key_matches.value_of("key").unwrap().to_string();
@@ -1066,15 +1609,31 @@ fn run() -> Result<(), failure::Error> {
                     expiration_ttl,
                     metadata,
                 };
-                commands::kv::key::put(&target, &user, kv_metadata)?
+                commands::kv::key::put(store.as_ref(), kv_metadata)?
             }
             ("delete", Some(delete_key_matches)) => {
                 let key = delete_key_matches.value_of("key").unwrap();
-                commands::kv::key::delete(&target, &user, &namespace_id, key)?
+                commands::kv::key::delete(store.as_ref(), &namespace_id, key)?
             }
             ("list", Some(list_key_matches)) => {
                 let prefix = list_key_matches.value_of("prefix");
-                commands::kv::key::list(&target, &user, &namespace_id, prefix)?
+                let limit = match list_key_matches.value_of("limit") {
+                    Some(limit) => match limit.parse::<usize>() {
+                        Ok(limit) if limit > 0 && limit <= 1000 => Some(limit),
+                        _ => failure::bail!("--limit must be a number from 1 to 1000"),
+                    },
+                    None => None,
+                };
+                let cursor = list_key_matches.value_of("cursor");
+                let with_metadata = list_key_matches.is_present("with-metadata");
+                commands::kv::key::list(
+                    store.as_ref(),
+                    &namespace_id,
+                    prefix,
+                    limit,
+                    cursor,
+                    with_metadata,
+                )?
             }
             _ => unreachable!(),
         }
@@ -1088,10 +1647,11 @@ fn run() -> Result<(), failure::Error> {
                 .unwrap_or(commands::DEFAULT_CONFIG_PATH),
         );
         let manifest = settings::toml::Manifest::new(config_path)?;
-        let user = settings::global_user::GlobalUser::new()?;
         let (target, namespace_id) = match subcommand_matches {
             Some(subcommand_matches) => {
-                is_preview = subcommand_matches.is_present("preview");
+                // the local store holds what `wrangler dev --local` writes to preview namespaces
+                is_preview = subcommand_matches.is_present("preview")
+                    || subcommand_matches.is_present("local");
                 let env = subcommand_matches.value_of("env");
                 let target = manifest.get_target(env, is_preview)?;
                 let namespace_id = match subcommand_matches.value_of("binding") {
@@ -1107,16 +1667,35 @@ fn run() -> Result<(), failure::Error> {
             }
             None => unreachable!(), // this is unreachable because all kv:key commands have required arguments.
         };
+        let store = commands::kv::store(&target, subcommand_matches.unwrap().is_present("local"))?;
 
         match (subcommand, subcommand_matches) {
             ("put", Some(put_bulk_matches)) => {
-                let path = put_bulk_matches.value_of("path").unwrap();
-                commands::kv::bulk::put(&target, &user, &namespace_id, Path::new(path))?
-            }
-            ("delete", Some(delete_bulk_matches)) => {
-                let path = delete_bulk_matches.value_of("path").unwrap();
-                commands::kv::bulk::delete(&target, &user, &namespace_id, Path::new(path))?
+                // clap ensures exactly one of "path", "dir" and "csv" is present
+                if let Some(dir) = put_bulk_matches.value_of("dir") {
+                    let prefix = put_bulk_matches.value_of("prefix").unwrap_or("");
+                    commands::kv::bulk::put_dir(
+                        store.as_ref(),
+                        &namespace_id,
+                        Path::new(dir),
+                        prefix,
+                    )?
+                } else if let Some(csv) = put_bulk_matches.value_of("csv") {
+                    commands::kv::bulk::put_csv(store.as_ref(), &namespace_id, Path::new(csv))?
+                } else {
+                    let path = put_bulk_matches.value_of("path").unwrap();
+                    commands::kv::bulk::put(store.as_ref(), &namespace_id, Path::new(path))?
+                }
             }
+            ("delete", Some(delete_bulk_matches)) => match delete_bulk_matches.value_of("prefix") {
+                Some(prefix) => {
+                    commands::kv::bulk::delete_prefix(store.as_ref(), &namespace_id, prefix)?
+                }
+                None => {
+                    let path = delete_bulk_matches.value_of("path").unwrap();
+                    commands::kv::bulk::delete(store.as_ref(), &namespace_id, Path::new(path))?
+                }
+            },
             _ => unreachable!(),
         }
     } else if let Some(matches) = matches.subcommand_matches("tail") {
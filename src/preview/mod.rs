@@ -10,6 +10,7 @@ pub use request_payload::RequestPayload;
 mod upload;
 pub use upload::upload;
 
+use std::collections::HashMap;
 use std::sync::mpsc::channel;
 use std::thread;
 
@@ -22,6 +23,7 @@ use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdOut};
 use crate::terminal::open_browser;
+use crate::upload::form::SessionConfig;
 use crate::watch::watch_and_build;
 use crate::{build::build_target, settings::toml::ScriptFormat};
 
@@ -39,8 +41,18 @@ pub fn preview(
     build_target(&target)?;
 
     let sites_preview: bool = target.site.is_some();
+    let session_config = SessionConfig {
+        dev_vars: options.dev_vars.clone(),
+        ..SessionConfig::default()
+    };
 
-    let script_id = upload(&mut target, user.as_ref(), sites_preview, verbose)?;
+    let script_id = upload(
+        &mut target,
+        user.as_ref(),
+        sites_preview,
+        &session_config,
+        verbose,
+    )?;
 
     let request_payload = RequestPayload::create(options.method, options.url, options.body);
 
@@ -70,6 +82,7 @@ pub fn preview(
         watch_for_changes(
             target,
             user.as_ref(),
+            &session_config,
             broadcaster,
             verbose,
             options.headless,
@@ -96,6 +109,8 @@ pub struct PreviewOpt {
     pub body: Option<String>,
     pub livereload: bool,
     pub headless: bool,
+    /// Variables from `.dev.vars`.
+    pub dev_vars: Option<HashMap<String, String>>,
 }
 
 fn client_request(payload: &RequestPayload, script_id: &str, sites_preview: bool) {
@@ -151,6 +166,7 @@ fn post(
 fn watch_for_changes(
     mut target: Target,
     user: Option<&GlobalUser>,
+    session_config: &SessionConfig,
     broadcaster: Sender,
     verbose: bool,
     headless: bool,
@@ -162,7 +178,7 @@ fn watch_for_changes(
     watch_and_build(&target, Some(tx))?;
 
     while rx.recv().is_ok() {
-        if let Ok(new_id) = upload(&mut target, user, sites_preview, verbose) {
+        if let Ok(new_id) = upload(&mut target, user, sites_preview, session_config, verbose) {
             let script_id = new_id.to_string();
 
             let msg = FiddleMessage {
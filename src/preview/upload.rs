@@ -5,12 +5,14 @@ use serde::Deserialize;
 
 use crate::http;
 use crate::kv::bulk;
+use crate::kv::store::ApiStore;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
-use crate::sites::{add_namespace, sync, AssetManifest};
+use crate::sites::{add_namespace, file_pairs, finish_sync, sync, AssetManifest};
 use crate::terminal::message::{Message, StdOut};
 use crate::terminal::styles;
 use crate::upload;
+use crate::upload::form::SessionConfig;
 
 #[derive(Debug, Deserialize)]
 struct Preview {
@@ -47,6 +49,7 @@ pub fn upload(
     target: &mut Target,
     user: Option<&GlobalUser>,
     sites_preview: bool,
+    session_config: &SessionConfig,
     verbose: bool,
 ) -> Result<String, failure::Error> {
     let preview = match &user {
@@ -63,27 +66,41 @@ pub fn upload(
 
                     let path = Path::new(&site_config.bucket);
                     let (to_upload, to_delete, asset_manifest) =
-                        sync(target, user, &site_namespace.id, path)?;
+                        sync(target, user, &site_namespace.id, path, false)?;
 
                     // First, upload all existing files in given directory
                     if verbose {
                         StdOut::info("Uploading updated files...");
                     }
 
-                    bulk::put(target, user, &site_namespace.id, to_upload, &None)?;
-
-                    let preview = authenticated_upload(&client, &target, Some(asset_manifest))?;
+                    let store = ApiStore::new(target, user)?;
+                    let source = path.display().to_string();
+                    bulk::put_with_metadata(
+                        &store,
+                        &site_namespace.id,
+                        &source,
+                        file_pairs(to_upload),
+                        &None,
+                    )?;
+
+                    let preview = authenticated_upload(
+                        &client,
+                        &target,
+                        Some(asset_manifest),
+                        session_config,
+                    )?;
                     if !to_delete.is_empty() {
                         if verbose {
                             StdOut::info("Deleting stale files...");
                         }
 
-                        bulk::delete(target, user, &site_namespace.id, to_delete, &None)?;
+                        bulk::delete(&store, &site_namespace.id, &source, to_delete, &None)?;
                     }
+                    finish_sync(&store, path, &site_namespace.id)?;
 
                     preview
                 } else {
-                    authenticated_upload(&client, &target, None)?
+                    authenticated_upload(&client, &target, None, session_config)?
                 }
             } else {
                 StdOut::warn(&format!(
@@ -95,7 +112,7 @@ pub fn upload(
                     failure::bail!(SITES_UNAUTH_PREVIEW_ERR)
                 }
 
-                unauthenticated_upload(&target)?
+                unauthenticated_upload(&target, session_config)?
             }
         }
         None => {
@@ -112,7 +129,7 @@ pub fn upload(
                 failure::bail!(SITES_UNAUTH_PREVIEW_ERR)
             }
 
-            unauthenticated_upload(&target)?
+            unauthenticated_upload(&target, session_config)?
         }
     };
 
@@ -146,6 +163,7 @@ fn authenticated_upload(
     client: &Client,
     target: &Target,
     asset_manifest: Option<AssetManifest>,
+    session_config: &SessionConfig,
 ) -> Result<Preview, failure::Error> {
     let create_address = format!(
         "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts/{}/preview",
@@ -153,7 +171,7 @@ fn authenticated_upload(
     );
     log::info!("address: {}", create_address);
 
-    let script_upload_form = upload::form::build(target, asset_manifest, None)?;
+    let script_upload_form = upload::form::build(target, asset_manifest, session_config)?;
 
     let res = client
         .post(&create_address)
@@ -177,7 +195,10 @@ fn authenticated_upload(
     Ok(Preview::from(response.result))
 }
 
-fn unauthenticated_upload(target: &Target) -> Result<Preview, failure::Error> {
+fn unauthenticated_upload(
+    target: &Target,
+    session_config: &SessionConfig,
+) -> Result<Preview, failure::Error> {
     let create_address = "https://cloudflareworkers.com/script";
     log::info!("address: {}", create_address);
 
@@ -199,7 +220,7 @@ fn unauthenticated_upload(target: &Target) -> Result<Preview, failure::Error> {
         target.site = None;
     }
 
-    let script_upload_form = upload::form::build(&target, None, None)?;
+    let script_upload_form = upload::form::build(&target, None, session_config)?;
     let client = http::client();
     let res = client
         .post(create_address)
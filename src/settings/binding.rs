@@ -8,6 +8,7 @@ pub enum Binding {
     KvNamespace { name: String, namespace_id: String },
     TextBlob { name: String, part: String },
     PlainText { name: String, text: String },
+    SecretText { name: String, text: String },
 }
 
 impl Binding {
@@ -26,4 +27,8 @@ impl Binding {
     pub fn new_plain_text(name: String, text: String) -> Binding {
         Binding::PlainText { name, text }
     }
+
+    pub fn new_secret_text(name: String, text: String) -> Binding {
+        Binding::SecretText { name, text }
+    }
 }
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file `wrangler dev` and `wrangler preview` read local-only variables from.
+/// It lives next to the configuration file and should not be checked in.
+pub const DEV_VARS_FILE: &str = ".dev.vars";
+
+/// Reads the variables for a `dev` or `preview` session from the directory containing
+/// `config_path`.
+///
+/// When an environment is used and `.dev.vars.<environment>` exists, it is read instead of
+/// `.dev.vars`, so that values meant for one environment never leak into another.
+pub fn load(
+    config_path: &Path,
+    environment: Option<&str>,
+) -> Result<Option<HashMap<String, String>>, failure::Error> {
+    let path = match dev_vars_path(config_path, environment) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    log::info!("Reading dev vars from {}", path.display());
+    let contents = fs::read_to_string(&path)?;
+    match parse(&contents) {
+        Ok(vars) => Ok(Some(vars.into_iter().collect())),
+        Err(e) => failure::bail!("Could not read {}: {}", path.display(), e),
+    }
+}
+
+fn dev_vars_path(config_path: &Path, environment: Option<&str>) -> Option<PathBuf> {
+    let dir = config_path.parent().unwrap_or_else(|| Path::new(""));
+    let default = dir.join(DEV_VARS_FILE);
+
+    let candidates = match environment {
+        Some(environment) => vec![
+            dir.join(format!("{}.{}", DEV_VARS_FILE, environment)),
+            default,
+        ],
+        None => vec![default],
+    };
+
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+/// Parses the contents of a dotenv file, in the order its entries appear.
+///
+/// Each line is `KEY=value`, optionally preceded by `export`. Blank lines and lines starting
+/// with `#` are skipped. Values may be wrapped in single quotes, which are taken literally,
+/// or double quotes, which may span lines and understand `\n`, `\t`, `\"` and `\\`.
+/// Unquoted values end at ` #`, which starts a comment.
+pub fn parse(contents: &str) -> Result<Vec<(String, String)>, failure::Error> {
+    let mut vars = Vec::new();
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = match line.find('=') {
+            Some(equals) => (line[..equals].trim(), line[equals + 1..].trim_start()),
+            None => failure::bail!("line {} should look like KEY=value", line_number),
+        };
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            failure::bail!(
+                "line {}: \"{}\" is not a valid variable name; use letters, digits and underscores",
+                line_number,
+                key
+            );
+        }
+
+        let value = if let Some(quoted) = value.strip_prefix('\'') {
+            match quoted.find('\'') {
+                Some(end) => quoted[..end].to_string(),
+                None => failure::bail!("line {}: missing closing '", line_number),
+            }
+        } else if let Some(quoted) = value.strip_prefix('"') {
+            let mut quoted = quoted.to_string();
+            loop {
+                if let Some(end) = closing_quote(&quoted) {
+                    quoted.truncate(end);
+                    break unescape(&quoted);
+                }
+                match lines.next() {
+                    Some((_, next)) => {
+                        quoted.push('\n');
+                        quoted.push_str(next);
+                    }
+                    None => failure::bail!("line {}: missing closing \"", line_number),
+                }
+            }
+        } else {
+            let value = match value.find(" #") {
+                Some(comment) => &value[..comment],
+                None => value,
+            };
+            value.trim_end().to_string()
+        };
+
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+// The position of the first `"` in `s` that isn't escaped with a backslash.
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn it_parses_dotenv_files() {
+        let contents = r#"
+# comments and blank lines are skipped
+
+API_TOKEN=abc123
+export REGION = us-east # where to send things
+SINGLE='literal \n $HOME'
+DOUBLE="line one\nline \"two\""
+MULTI="first
+second"
+EMPTY=
+"#;
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            pairs(&[
+                ("API_TOKEN", "abc123"),
+                ("REGION", "us-east"),
+                ("SINGLE", "literal \\n $HOME"),
+                ("DOUBLE", "line one\nline \"two\""),
+                ("MULTI", "first\nsecond"),
+                ("EMPTY", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn it_rejects_malformed_lines() {
+        let missing_equals = parse("A=1\nNOT_A_VAR\n").unwrap_err();
+        assert!(missing_equals.to_string().contains("line 2"));
+
+        let bad_name = parse("MY-VAR=1").unwrap_err();
+        assert!(bad_name.to_string().contains("MY-VAR"));
+
+        let unterminated = parse("A=\"never closed\nB=2").unwrap_err();
+        assert!(unterminated.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn it_prefers_the_environment_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wrangler.toml");
+        fs::write(dir.path().join(".dev.vars"), "SECRET=dev\nONLY_DEV=1").unwrap();
+        fs::write(dir.path().join(".dev.vars.staging"), "SECRET=staging").unwrap();
+
+        let vars = load(&config_path, None).unwrap().unwrap();
+        assert_eq!(vars.get("SECRET").map(String::as_str), Some("dev"));
+
+        let vars = load(&config_path, Some("staging")).unwrap().unwrap();
+        assert_eq!(vars.get("SECRET").map(String::as_str), Some("staging"));
+        assert!(!vars.contains_key("ONLY_DEV"));
+
+        let vars = load(&config_path, Some("production")).unwrap().unwrap();
+        assert_eq!(vars.get("SECRET").map(String::as_str), Some("dev"));
+
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(
+            load(&empty.path().join("wrangler.toml"), None).unwrap(),
+            None
+        );
+    }
+}
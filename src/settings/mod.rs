@@ -1,4 +1,5 @@
 pub mod binding;
+pub mod dev_vars;
 mod environment;
 mod global_config;
 pub mod global_user;
@@ -6,3 +7,7 @@ pub mod toml;
 
 pub use environment::{Environment, QueryEnvironment};
 pub use global_config::{get_global_config_path, get_wrangler_home_dir, DEFAULT_CONFIG_FILE_NAME};
+
+/// The directory in a project where `wrangler dev --local` keeps its files and local state.
+/// Changes in it never trigger a rebuild.
+pub const LOCAL_STATE_DIR: &str = ".wrangler";
@@ -6,7 +6,7 @@ use serde_with::rust::string_empty_as_none;
 
 use crate::settings::toml::builder::Builder;
 use crate::settings::toml::kv_namespace::ConfigKvNamespace;
-use crate::settings::toml::route::RouteConfig;
+use crate::settings::toml::route::{ConfigRoute, RouteConfig};
 use crate::settings::toml::site::Site;
 use crate::settings::toml::triggers::Triggers;
 
@@ -18,7 +18,7 @@ pub struct Environment {
     pub workers_dev: Option<bool>,
     #[serde(default, with = "string_empty_as_none")]
     pub route: Option<String>,
-    pub routes: Option<Vec<String>>,
+    pub routes: Option<Vec<ConfigRoute>>,
     #[serde(default, with = "string_empty_as_none")]
     pub zone_id: Option<String>,
     pub webpack_config: Option<String>,
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use config::{Config, File};
+use toml::value::{Table, Value};
+
+use super::source_map::{join_path, normalize_key, parent_path};
+
+const EXTENDS_KEY: &str = "extends";
+
+/// Where a key in a merged configuration was written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin {
+    pub file: PathBuf,
+    /// The key's path within `file`, which differs from its path in the merged
+    /// configuration when `file` was included by an environment.
+    pub key: String,
+}
+
+/// Remembers which file set each key of a configuration that `extends` other files.
+#[derive(Clone, Debug, Default)]
+pub struct Provenance {
+    root: PathBuf,
+    origins: HashMap<String, Origin>,
+}
+
+// Two manifests with the same configuration are equal, however it was put together.
+impl PartialEq for Provenance {
+    fn eq(&self, _other: &Provenance) -> bool {
+        true
+    }
+}
+
+impl Provenance {
+    fn new(root: &Path) -> Provenance {
+        Provenance {
+            root: root.to_path_buf(),
+            origins: HashMap::new(),
+        }
+    }
+
+    // Returns the file that set `path` (or its closest recorded parent), and where to find
+    // it within that file.
+    pub fn locate(&self, path: &str) -> Option<Origin> {
+        let mut ancestor = path;
+        loop {
+            if let Some(origin) = self.origins.get(ancestor) {
+                return Some(Origin {
+                    file: origin.file.clone(),
+                    key: format!("{}{}", origin.key, &path[ancestor.len()..]),
+                });
+            }
+            ancestor = parent_path(ancestor)?;
+        }
+    }
+
+    /// The file `path` was included from, if it wasn't set in the configuration file itself.
+    pub fn included_from(&self, path: &str) -> Option<PathBuf> {
+        self.locate(path)
+            .map(|origin| origin.file)
+            .filter(|file| file != &self.root)
+    }
+
+    fn record(&mut self, prefix: &str, local_prefix: &str, table: &Table, file: &Path) {
+        for (key, value) in table {
+            let key = normalize_key(key);
+            let path = join_path(prefix, &key);
+            let local_path = join_path(local_prefix, &key);
+            if let Value::Table(table) = value {
+                self.record(&path, &local_path, table, file);
+            }
+            self.origins.insert(
+                path,
+                Origin {
+                    file: file.to_path_buf(),
+                    key: local_path,
+                },
+            );
+        }
+    }
+}
+
+/// Reads a configuration file, deep-merged over every file it `extends`.
+///
+/// `extends = "path"` may be set at the top level, where the other file is merged under
+/// this one, or in an `[env.<name>]` table, where the other file's top level is merged
+/// under that environment. Paths are relative to the file that names them. Tables are
+/// merged key by key; any other value, including arrays like `kv_namespaces`, replaces
+/// the one it extends.
+pub(super) fn load(config_path: &Path) -> Result<(Table, Provenance), failure::Error> {
+    let mut loader = Loader {
+        chain: Vec::new(),
+        provenance: Provenance::new(config_path),
+    };
+    let table = loader.load(config_path, "")?;
+    Ok((table, loader.provenance))
+}
+
+/// Whether a configuration file's own contents use `extends` anywhere.
+pub(super) fn uses_extends(table: &Table) -> bool {
+    table.contains_key(EXTENDS_KEY)
+        || environments(table).any(|(_, environment)| environment.contains_key(EXTENDS_KEY))
+}
+
+fn environments(table: &Table) -> impl Iterator<Item = (&String, &Table)> {
+    table
+        .get("env")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, environment)| environment.as_table().map(|t| (name, t)))
+}
+
+struct Loader {
+    // the files currently being loaded, outermost first
+    chain: Vec<(PathBuf, PathBuf)>,
+    provenance: Provenance,
+}
+
+impl Loader {
+    // Loads `path` and everything it extends, recording its keys under `prefix`.
+    fn load(&mut self, path: &Path, prefix: &str) -> Result<Table, failure::Error> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(start) = self.chain.iter().position(|(c, _)| c == &canonical) {
+            let cycle = self.chain[start..]
+                .iter()
+                .map(|(_, p)| p.display().to_string())
+                .chain(std::iter::once(path.display().to_string()))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            failure::bail!(
+                "Your configuration files extend each other in a loop: {}",
+                cycle
+            )
+        }
+        self.chain.push((canonical, path.to_path_buf()));
+
+        let mut table = read_table(path)?;
+
+        let mut merged = match extends(&mut table, path, prefix)? {
+            Some(parent) => self.load(&parent, prefix)?,
+            None => Table::new(),
+        };
+
+        // environments can only be configured at the top level of a configuration file
+        let mut environment_bases = Vec::new();
+        if let (true, Some(Value::Table(environments))) = (prefix.is_empty(), table.get_mut("env"))
+        {
+            for (name, environment) in environments.iter_mut() {
+                if let Value::Table(environment) = environment {
+                    let env_prefix = join_path(prefix, &format!("env.{}", name));
+                    if let Some(parent) = extends(environment, path, &env_prefix)? {
+                        environment_bases.push((name.clone(), self.load(&parent, &env_prefix)?));
+                    }
+                }
+            }
+        }
+
+        // recorded after everything this file extends, so that its own keys win
+        self.provenance.record(prefix, "", &table, path);
+
+        for (name, base) in environment_bases {
+            if let Some(Value::Table(environments)) = table.get_mut("env") {
+                if let Some(Value::Table(environment)) = environments.remove(&name) {
+                    environments.insert(name, Value::Table(merge(base, environment)));
+                }
+            }
+        }
+        merged = merge(merged, table);
+
+        self.chain.pop();
+        Ok(merged)
+    }
+}
+
+// Removes `extends` from `table`, returning the path it names relative to `path`.
+fn extends(
+    table: &mut Table,
+    path: &Path,
+    prefix: &str,
+) -> Result<Option<PathBuf>, failure::Error> {
+    match table.remove(EXTENDS_KEY) {
+        Some(Value::String(parent)) => {
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            Ok(Some(dir.join(parent)))
+        }
+        Some(other) => failure::bail!(
+            "`{}` in {} must be the path to another configuration file, not a {}",
+            join_path(prefix, EXTENDS_KEY),
+            path.display(),
+            other.type_str()
+        ),
+        None => Ok(None),
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table, failure::Error> {
+    let name = path
+        .to_str()
+        .expect("project config path should be a string");
+
+    let mut config = Config::new();
+    config.merge(File::with_name(name))?;
+
+    let mut table: Table = match config.try_into() {
+        Ok(table) => table,
+        Err(e) => failure::bail!("Could not read {}: {}", path.display(), e),
+    };
+
+    // so that a file using `kv-namespaces` can extend one using `kv_namespaces`
+    normalize_keys(&mut table);
+    if let Some(Value::Table(environments)) = table.get_mut("env") {
+        for (_, environment) in environments.iter_mut() {
+            if let Value::Table(environment) = environment {
+                normalize_keys(environment);
+            }
+        }
+    }
+
+    Ok(table)
+}
+
+fn normalize_keys(table: &mut Table) {
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let normalized = normalize_key(&key);
+        if normalized != key {
+            if let Some(value) = table.remove(&key) {
+                table.insert(normalized, value);
+            }
+        }
+    }
+}
+
+// Merges `overlay` over `base`, table by table.
+fn merge(mut base: Table, overlay: Table) -> Table {
+    for (key, value) in overlay {
+        let value = match (base.remove(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => Value::Table(merge(base, overlay)),
+            (_, value) => value,
+        };
+        base.insert(key, value);
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn it_merges_tables_and_replaces_everything_else() {
+        let base = table(
+            r#"
+            account_id = "base"
+            routes = ["a/*", "b/*"]
+            [vars]
+            ONE = "1"
+            TWO = "2"
+            "#,
+        );
+        let overlay = table(
+            r#"
+            routes = ["c/*"]
+            [vars]
+            TWO = "two"
+            "#,
+        );
+
+        let merged = merge(base, overlay);
+
+        assert_eq!(
+            merged,
+            table(
+                r#"
+                account_id = "base"
+                routes = ["c/*"]
+                [vars]
+                ONE = "1"
+                TWO = "two"
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn it_locates_keys_from_their_closest_recorded_parent() {
+        let mut provenance = Provenance::new(Path::new("wrangler.toml"));
+        provenance.record(
+            "env.staging",
+            "",
+            &table("kv-namespaces = [{ binding = \"A\" }]"),
+            Path::new("staging.toml"),
+        );
+
+        assert_eq!(
+            provenance.locate("env.staging.kv_namespaces[0].id"),
+            Some(Origin {
+                file: PathBuf::from("staging.toml"),
+                key: "kv_namespaces[0].id".to_string(),
+            })
+        );
+        assert_eq!(
+            provenance.included_from("env.staging.kv_namespaces"),
+            Some(PathBuf::from("staging.toml"))
+        );
+        assert_eq!(provenance.locate("account_id"), None);
+    }
+}
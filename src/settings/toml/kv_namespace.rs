@@ -11,7 +11,7 @@ pub struct ConfigKvNamespace {
     pub preview_id: Option<String>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct KvNamespace {
     pub id: String,
     pub binding: String,
@@ -4,7 +4,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use config::{Config, File};
+use config::{Config, ConfigError, File, FileFormat};
 
 use serde::{Deserialize, Serialize};
 use serde_with::rust::string_empty_as_none;
@@ -14,8 +14,10 @@ use crate::deploy::{self, DeployTarget, DeploymentSet};
 use crate::settings::toml::builder::Builder;
 use crate::settings::toml::dev::Dev;
 use crate::settings::toml::environment::Environment;
+use crate::settings::toml::extends::{self, Provenance};
 use crate::settings::toml::kv_namespace::{ConfigKvNamespace, KvNamespace};
-use crate::settings::toml::route::RouteConfig;
+use crate::settings::toml::resolve::{self, Resolution};
+use crate::settings::toml::route::{ConfigRoute, RouteConfig};
 use crate::settings::toml::site::Site;
 use crate::settings::toml::target_type::TargetType;
 use crate::settings::toml::triggers::Triggers;
@@ -37,7 +39,7 @@ pub struct Manifest {
     pub workers_dev: Option<bool>,
     #[serde(default, with = "string_empty_as_none")]
     pub route: Option<String>,
-    pub routes: Option<Vec<String>>,
+    pub routes: Option<Vec<ConfigRoute>>,
     #[serde(default, with = "string_empty_as_none")]
     pub zone_id: Option<String>,
     pub webpack_config: Option<String>,
@@ -53,6 +55,9 @@ pub struct Manifest {
     pub vars: Option<HashMap<String, String>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
     pub triggers: Option<Triggers>,
+    // which file each key came from, when the configuration file `extends` others
+    #[serde(skip)]
+    pub provenance: Provenance,
 }
 
 impl Manifest {
@@ -63,20 +68,37 @@ impl Manifest {
             message.push_str("; run `wrangler init` to create one.");
         }
         failure::ensure!(config_path.exists(), message);
-        let config = read_config(config_path)?;
 
-        let manifest: Manifest = match config.try_into() {
+        let manifest = Manifest::from_config_path(config_path)?;
+
+        check_for_duplicate_names(&manifest)?;
+
+        Ok(manifest)
+    }
+
+    // Reads a manifest (merged with any `CF_*` environment variables) without checking it
+    // beyond what deserializing requires.
+    pub(super) fn from_config_path(config_path: &Path) -> Result<Self, failure::Error> {
+        let (config, provenance) = read_config(config_path)?;
+
+        let mut manifest: Manifest = match config.try_into() {
             Ok(m) => m,
             Err(e) => {
                 if e.to_string().contains("unknown field `kv-namespaces`") {
                     failure::bail!("kv-namespaces should not live under the [site] table in your configuration file; please move it above [site].")
-                } else {
-                    failure::bail!(e)
                 }
+                // point at the file the bad key came from, which may be one this extends
+                let file = match &e {
+                    ConfigError::Type { key: Some(key), .. } => provenance
+                        .locate(key)
+                        .map(|origin| origin.file)
+                        .unwrap_or_else(|| config_path.to_path_buf()),
+                    _ => config_path.to_path_buf(),
+                };
+                failure::bail!("{} in {}", e, file.display())
             }
         };
-
-        check_for_duplicate_names(&manifest)?;
+        manifest.provenance = provenance;
 
         Ok(manifest)
     }
@@ -208,13 +230,13 @@ impl Manifest {
         let mut add_routed_deployments =
             |route_config: &RouteConfig| -> Result<(), failure::Error> {
                 if route_config.is_zoned() {
-                    let zoned = deploy::ZonedTarget::build(&script, route_config)?;
+                    let zoned_targets = deploy::ZonedTarget::build(&script, route_config)?;
                     // This checks all of the configured routes for the wildcard ending and warns
                     // the user that their site may not work as expected without it.
                     if self.site.is_some() {
-                        let no_star_routes = zoned
-                            .routes
+                        let no_star_routes = zoned_targets
                             .iter()
+                            .flat_map(|zoned| &zoned.routes)
                             .filter(|r| !r.pattern.ends_with('*'))
                             .map(|r| r.pattern.as_str())
                             .collect::<Vec<_>>();
@@ -226,7 +248,7 @@ impl Manifest {
                         }
                     }
 
-                    deployments.push(DeployTarget::Zoned(zoned));
+                    deployments.extend(zoned_targets.into_iter().map(DeployTarget::Zoned));
                 }
 
                 if route_config.is_zoneless() {
@@ -305,60 +327,17 @@ impl Manifest {
         environment_name: Option<&str>,
         preview: bool,
     ) -> Result<Target, failure::Error> {
-        // Site projects are always webpack for now; don't let toml override this.
-        let target_type = match self.site {
-            Some(_) => TargetType::Webpack,
-            None => self.target_type.clone(),
-        };
-
-        /*
-        From https://developers.cloudflare.com/workers/cli-wrangler/configuration#keys
-        Top level: required to be configured at the top level of your wrangler.toml only; multiple environments on the same project must share this property
-
-        Inherited: Can be configured at the top level and/or environment. If the property is defined only at the top level, the environment will use the property value from the top level. If the property is defined in the environment, the environment value will override the top level value.
-
-        Not inherited: Must be defined for every environment individually.
-        */
-        let mut target = Target {
-            target_type,                                 // Top level
-            account_id: self.account_id.clone(),         // Inherited
-            webpack_config: self.webpack_config.clone(), // Inherited
-            build: self.build.clone(),                   // Inherited
-            // importantly, the top level name will be modified
-            // to include the name of the environment
-            name: self.name.clone(), // Inherited
-            kv_namespaces: get_namespaces(self.kv_namespaces.clone(), preview)?, // Not inherited
-            site: self.site.clone(), // Inherited
-            vars: self.vars.clone(), // Not inherited
-            text_blobs: self.text_blobs.clone(), // Inherited
-        };
-
-        let environment = self.get_environment(environment_name)?;
-
-        if let Some(environment) = environment {
-            target.name = self.worker_name(environment_name);
-            if let Some(account_id) = &environment.account_id {
-                target.account_id = account_id.clone();
-            }
-            if let Some(webpack_config) = &environment.webpack_config {
-                target.webpack_config = Some(webpack_config.clone());
-            }
-            if let Some(build) = &environment.build {
-                target.build = Some(build.clone());
-            }
-
-            // don't inherit kv namespaces because it is an anti-pattern to use the same namespaces across multiple environments
-            target.kv_namespaces = get_namespaces(environment.kv_namespaces.clone(), preview)?;
-
-            if let Some(site) = &environment.site {
-                target.site = Some(site.clone());
-            }
-
-            // don't inherit vars
-            target.vars = environment.vars.clone();
-        }
+        Ok(self.resolve(environment_name, preview)?.target)
+    }
 
-        Ok(target)
+    /// Resolves the effective configuration for an environment, recording where each field
+    /// came from. See `Inheritance` for which fields environments inherit.
+    pub fn resolve(
+        &self,
+        environment_name: Option<&str>,
+        preview: bool,
+    ) -> Result<Resolution, failure::Error> {
+        resolve::resolve(self, environment_name, preview)
     }
 
     pub fn get_environment(
@@ -490,35 +469,21 @@ impl FromStr for Manifest {
     }
 }
 
-fn read_config(config_path: &Path) -> Result<Config, failure::Error> {
+fn read_config(config_path: &Path) -> Result<(Config, Provenance), failure::Error> {
     let mut config = Config::new();
 
-    let config_str = config_path
-        .to_str()
-        .expect("project config path should be a string");
-    config.merge(File::with_name(config_str))?;
+    let (table, provenance) = extends::load(config_path)?;
+    let merged = toml::to_string(&toml::Value::Table(table))?;
+    config.merge(File::from_str(&merged, FileFormat::Toml))?;
 
     // Eg.. `CF_ACCOUNT_AUTH_KEY=farts` would set the `account_auth_key` key
     config.merge(config::Environment::with_prefix("CF"))?;
 
-    Ok(config)
+    Ok((config, provenance))
 }
 
 fn check_for_duplicate_names(manifest: &Manifest) -> Result<(), failure::Error> {
-    let mut names: HashSet<String> = HashSet::new();
-    let mut duplicate_names: HashSet<String> = HashSet::new();
-    names.insert(manifest.name.to_string());
-    if let Some(environments) = &manifest.env {
-        for (_, environment) in environments.iter() {
-            if let Some(name) = &environment.name {
-                if names.contains(name) && !duplicate_names.contains(name) {
-                    duplicate_names.insert(name.to_string());
-                } else {
-                    names.insert(name.to_string());
-                }
-            }
-        }
-    }
+    let duplicate_names = find_duplicate_names(manifest);
     let duplicate_name_string = duplicate_names
         .clone()
         .into_iter()
@@ -540,7 +505,25 @@ fn check_for_duplicate_names(manifest: &Manifest) -> Result<(), failure::Error>
     Ok(())
 }
 
-fn get_namespaces(
+pub(super) fn find_duplicate_names(manifest: &Manifest) -> HashSet<String> {
+    let mut names: HashSet<String> = HashSet::new();
+    let mut duplicate_names: HashSet<String> = HashSet::new();
+    names.insert(manifest.name.to_string());
+    if let Some(environments) = &manifest.env {
+        for (_, environment) in environments.iter() {
+            if let Some(name) = &environment.name {
+                if names.contains(name) && !duplicate_names.contains(name) {
+                    duplicate_names.insert(name.to_string());
+                } else {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+    }
+    duplicate_names
+}
+
+pub(super) fn get_namespaces(
     kv_namespaces: Option<Vec<ConfigKvNamespace>>,
     preview: bool,
 ) -> Result<Vec<KvNamespace>, failure::Error> {
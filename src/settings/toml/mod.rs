@@ -1,24 +1,32 @@
 mod builder;
 mod dev;
 mod environment;
+mod extends;
 mod kv_namespace;
 mod manifest;
+mod resolve;
 mod route;
 mod script_format;
 mod site;
+mod source_map;
 mod target;
 mod target_type;
 mod triggers;
+mod validate;
 
 pub use builder::Builder;
 pub use environment::Environment;
+pub use extends::{Origin, Provenance};
 pub use kv_namespace::{ConfigKvNamespace, KvNamespace};
 pub use manifest::Manifest;
-pub use route::{Route, RouteConfig};
+pub use resolve::{Inheritance, Resolution, ResolvedField, Source};
+pub use route::{ConfigRoute, Route, RouteConfig, RouteTable, Zone};
 pub use script_format::ScriptFormat;
-pub use site::Site;
+pub use site::{CacheControlRule, Site};
+pub use source_map::Location;
 pub use target::Target;
 pub use target_type::TargetType;
+pub use validate::{validate, Diagnostic};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,344 @@
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::environment::Environment;
+use super::extends::Provenance;
+use super::manifest::get_namespaces;
+use super::target_type::TargetType;
+use super::{Manifest, Target};
+
+/// How a field set in an environment relates to the same field at the top level.
+///
+/// From https://developers.cloudflare.com/workers/cli-wrangler/configuration#keys
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Inheritance {
+    /// Can only be configured at the top level; every environment shares it.
+    #[serde(rename = "top-level-only")]
+    TopLevel,
+    /// Can be configured at the top level and/or in an environment. The environment's
+    /// value wins, and the top-level value is used when the environment has none.
+    Inherited,
+    /// Must be configured in every environment that needs it; the top-level value only
+    /// applies when no environment is used.
+    NotInherited,
+}
+
+impl fmt::Display for Inheritance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = match self {
+            Inheritance::TopLevel => "top-level only",
+            Inheritance::Inherited => "inherited",
+            Inheritance::NotInherited => "not inherited",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+/// Where the resolved value of a field came from.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "name", rename_all = "kebab-case")]
+pub enum Source {
+    TopLevel,
+    Environment(String),
+    /// A `CF_*` environment variable, which overrides the top-level value in the file.
+    EnvironmentVariable(String),
+    Default,
+    Unset,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::TopLevel => write!(f, "from the top level"),
+            Source::Environment(name) => write!(f, "from [env.{}]", name),
+            Source::EnvironmentVariable(name) => write!(f, "from ${}", name),
+            Source::Default => write!(f, "wrangler default"),
+            Source::Unset => write!(f, "not set"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResolvedField {
+    pub name: &'static str,
+    pub inheritance: Inheritance,
+    pub source: Source,
+    /// The file the value was set in, when it's one the configuration file extends.
+    pub file: Option<PathBuf>,
+    pub value: Option<toml::Value>,
+    pub note: Option<String>,
+}
+
+/// The effective configuration for one environment (or the top level), along with where
+/// each field came from.
+#[derive(Clone, Debug)]
+pub struct Resolution {
+    pub environment: Option<String>,
+    pub target: Target,
+    pub fields: Vec<ResolvedField>,
+}
+
+impl Resolution {
+    pub fn field(&self, name: &str) -> Option<&ResolvedField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+pub fn resolve(
+    manifest: &Manifest,
+    environment_name: Option<&str>,
+    preview: bool,
+) -> Result<Resolution, failure::Error> {
+    let environment = manifest.get_environment(environment_name)?;
+    let mut resolver = Resolver {
+        provenance: &manifest.provenance,
+        environment_name,
+        environment,
+        fields: Vec::new(),
+    };
+
+    // Site projects are always webpack for now; don't let toml override this.
+    let target_type = match manifest.site {
+        Some(_) => {
+            resolver.record(
+                "type",
+                Inheritance::TopLevel,
+                Source::Default,
+                Some(&TargetType::Webpack),
+                Some("projects with a [site] are always built with webpack".to_string()),
+            );
+            TargetType::Webpack
+        }
+        None => {
+            let source = top_level_source("type", true);
+            resolver.record(
+                "type",
+                Inheritance::TopLevel,
+                source,
+                Some(&manifest.target_type),
+                None,
+            );
+            manifest.target_type.clone()
+        }
+    };
+
+    // importantly, the top level name will be modified to include the name of the environment
+    let name = manifest.worker_name(environment_name);
+    let (source, note) = match (environment_name, environment) {
+        (Some(_), Some(environment)) if environment.name.is_some() => (resolver.env_source(), None),
+        (Some(environment_name), _) => (
+            top_level_source("name", !manifest.name.is_empty()),
+            Some(format!(
+                "[env.{0}] has no name, so \"-{0}\" is appended to the top-level name",
+                environment_name
+            )),
+        ),
+        (None, _) => (top_level_source("name", !manifest.name.is_empty()), None),
+    };
+    resolver.record("name", Inheritance::Inherited, source, Some(&name), note);
+
+    let account_id = match environment.and_then(|e| e.account_id.as_ref()) {
+        Some(account_id) => {
+            let source = resolver.env_source();
+            resolver.record(
+                "account_id",
+                Inheritance::Inherited,
+                source,
+                Some(account_id),
+                None,
+            );
+            account_id.clone()
+        }
+        None => {
+            let is_set = !manifest.account_id.is_empty();
+            let source = top_level_source("account_id", is_set);
+            let value = Some(&manifest.account_id).filter(|_| is_set);
+            resolver.record("account_id", Inheritance::Inherited, source, value, None);
+            manifest.account_id.clone()
+        }
+    };
+
+    let webpack_config = resolver.inherited("webpack_config", &manifest.webpack_config, |e| {
+        &e.webpack_config
+    });
+    let build = resolver.inherited("build", &manifest.build, |e| &e.build);
+    let site = resolver.inherited("site", &manifest.site, |e| &e.site);
+    let text_blobs = resolver.inherited("text_blobs", &manifest.text_blobs, |e| &e.text_blobs);
+
+    // don't inherit kv namespaces because it is an anti-pattern to use the same namespaces
+    // across multiple environments
+    let config_kv_namespaces =
+        resolver.not_inherited("kv_namespaces", &manifest.kv_namespaces, |e| {
+            &e.kv_namespaces
+        });
+    let kv_namespaces = get_namespaces(config_kv_namespaces.clone(), preview)?;
+    // show the namespaces that will actually be bound, which depends on `preview`
+    if let (Some(_), Some(field)) = (&config_kv_namespaces, resolver.fields.last_mut()) {
+        field.value = toml::Value::try_from(&kv_namespaces).ok();
+    }
+
+    let vars = resolver.not_inherited("vars", &manifest.vars, |e| &e.vars);
+
+    // These don't end up in the target, but decide where the worker is deployed.
+    // `workers_dev` is only inherited by environments that set none of `workers_dev`,
+    // `route` or `routes`.
+    match environment.filter(|e| has_routing(e)) {
+        Some(_) => {
+            resolver.not_inherited("workers_dev", &manifest.workers_dev, |e| &e.workers_dev);
+            if let (Some(_), Some(field)) = (manifest.workers_dev, resolver.fields.last_mut()) {
+                field.note = Some(
+                    "the top-level workers_dev is not used by environments that set route or routes"
+                        .to_string(),
+                );
+            }
+        }
+        None => {
+            resolver.inherited("workers_dev", &manifest.workers_dev, |e| &e.workers_dev);
+        }
+    }
+    resolver.not_inherited("route", &manifest.route, |e| &e.route);
+    resolver.not_inherited("routes", &manifest.routes, |e| &e.routes);
+    resolver.inherited("zone_id", &manifest.zone_id, |e| &e.zone_id);
+    if let Some(environment) = environment {
+        if has_routing(environment) && environment.zone_id.is_none() && manifest.zone_id.is_some() {
+            if let Some(field) = resolver.fields.last_mut() {
+                field.note = Some(format!(
+                    "routes in [env.{}] are deployed to the top-level zone_id",
+                    environment_name.unwrap_or_default()
+                ));
+            }
+        }
+    }
+    resolver.inherited("triggers", &manifest.triggers, |e| &e.triggers);
+
+    let target = Target {
+        target_type,
+        account_id,
+        webpack_config,
+        build,
+        name,
+        kv_namespaces,
+        site,
+        vars,
+        text_blobs,
+    };
+
+    Ok(Resolution {
+        environment: environment_name.map(str::to_string),
+        target,
+        fields: resolver.fields,
+    })
+}
+
+fn has_routing(environment: &Environment) -> bool {
+    environment.workers_dev.is_some() || environment.route.is_some() || environment.routes.is_some()
+}
+
+// `read_config` merges every `CF_*` environment variable over the top level of the file,
+// e.g. `CF_ACCOUNT_ID` replaces `account_id`.
+fn environment_variable(key: &str) -> Option<String> {
+    let prefixed = format!("cf_{}", key);
+    env::vars()
+        .map(|(name, _)| name)
+        .find(|name| name.to_lowercase() == prefixed)
+}
+
+fn top_level_source(key: &str, is_set: bool) -> Source {
+    if let Some(name) = environment_variable(key) {
+        Source::EnvironmentVariable(name)
+    } else if is_set {
+        Source::TopLevel
+    } else {
+        Source::Unset
+    }
+}
+
+struct Resolver<'a> {
+    provenance: &'a Provenance,
+    environment_name: Option<&'a str>,
+    environment: Option<&'a Environment>,
+    fields: Vec<ResolvedField>,
+}
+
+impl<'a> Resolver<'a> {
+    fn env_source(&self) -> Source {
+        Source::Environment(self.environment_name.unwrap_or_default().to_string())
+    }
+
+    fn record<T: Serialize>(
+        &mut self,
+        name: &'static str,
+        inheritance: Inheritance,
+        source: Source,
+        value: Option<&T>,
+        note: Option<String>,
+    ) {
+        let file = match &source {
+            Source::TopLevel => self.provenance.included_from(name),
+            Source::Environment(environment) => self
+                .provenance
+                .included_from(&format!("env.{}.{}", environment, name)),
+            _ => None,
+        };
+        self.fields.push(ResolvedField {
+            name,
+            inheritance,
+            source,
+            file,
+            value: value.and_then(|value| toml::Value::try_from(value).ok()),
+            note,
+        });
+    }
+
+    fn inherited<T: Clone + Serialize>(
+        &mut self,
+        name: &'static str,
+        top_level: &Option<T>,
+        in_environment: fn(&Environment) -> &Option<T>,
+    ) -> Option<T> {
+        let (value, source) = match self.environment.map(in_environment) {
+            Some(value @ Some(_)) => (value, self.env_source()),
+            _ => (top_level, top_level_source(name, top_level.is_some())),
+        };
+        self.record(name, Inheritance::Inherited, source, value.as_ref(), None);
+        value.clone()
+    }
+
+    fn not_inherited<T: Clone + Serialize>(
+        &mut self,
+        name: &'static str,
+        top_level: &Option<T>,
+        in_environment: fn(&Environment) -> &Option<T>,
+    ) -> Option<T> {
+        let (value, source, note) = match self.environment.map(in_environment) {
+            Some(value) => {
+                let source = match value {
+                    Some(_) => self.env_source(),
+                    None => Source::Unset,
+                };
+                let note = match (value, top_level) {
+                    (None, Some(_)) => Some(format!(
+                        "the top-level {} is not used by environments; set it in [env.{}]",
+                        name,
+                        self.environment_name.unwrap_or_default()
+                    )),
+                    _ => None,
+                };
+                (value, source, note)
+            }
+            None => (top_level, top_level_source(name, top_level.is_some()), None),
+        };
+        self.record(
+            name,
+            Inheritance::NotInherited,
+            source,
+            value.as_ref(),
+            note,
+        );
+        value.clone()
+    }
+}
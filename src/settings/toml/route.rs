@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_with::rust::string_empty_as_none;
 
 use cloudflare::endpoints::workers::WorkersRoute;
 
@@ -19,11 +20,64 @@ impl From<&WorkersRoute> for Route {
     }
 }
 
+/// An entry of `routes` in the configuration file: either a pattern, deployed to the
+/// `zone_id` of its environment, or a table naming the pattern's own zone.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ConfigRoute {
+    Pattern(String),
+    Table(RouteTable),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteTable {
+    pub pattern: String,
+    #[serde(default, with = "string_empty_as_none")]
+    pub zone_id: Option<String>,
+    #[serde(default, with = "string_empty_as_none")]
+    pub zone_name: Option<String>,
+}
+
+/// The zone a route is deployed to.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Zone {
+    Id(String),
+    Name(String),
+}
+
+impl ConfigRoute {
+    pub fn pattern(&self) -> &str {
+        match self {
+            ConfigRoute::Pattern(pattern) => pattern,
+            ConfigRoute::Table(table) => &table.pattern,
+        }
+    }
+
+    /// The zone set on the route itself, if any.
+    pub fn zone(&self) -> Option<Zone> {
+        match self {
+            ConfigRoute::Pattern(_) => None,
+            ConfigRoute::Table(table) => match (&table.zone_id, &table.zone_name) {
+                (Some(zone_id), _) => Some(Zone::Id(zone_id.clone())),
+                (None, Some(zone_name)) => Some(Zone::Name(zone_name.clone())),
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+impl From<String> for ConfigRoute {
+    fn from(pattern: String) -> ConfigRoute {
+        ConfigRoute::Pattern(pattern)
+    }
+}
+
 #[derive(Debug)]
 pub struct RouteConfig {
     pub workers_dev: Option<bool>,
     pub route: Option<String>,
-    pub routes: Option<Vec<String>>,
+    pub routes: Option<Vec<ConfigRoute>>,
     pub zone_id: Option<String>,
     pub account_id: Option<String>,
 }
@@ -39,8 +93,11 @@ impl RouteConfig {
         }
     }
 
-    pub fn routes(&self) -> impl Iterator<Item = &String> {
-        self.route.iter().chain(self.routes.iter().flatten())
+    pub fn routes(&self) -> impl Iterator<Item = &str> {
+        self.route
+            .iter()
+            .map(String::as_str)
+            .chain(self.routes.iter().flatten().map(ConfigRoute::pattern))
     }
 
     pub fn is_zoneless(&self) -> bool {
@@ -48,7 +105,13 @@ impl RouteConfig {
     }
 
     pub fn is_zoned(&self) -> bool {
-        self.has_routes_defined() && self.zone_id.is_some()
+        self.has_routes_defined()
+            && (self.zone_id.is_some()
+                || self
+                    .routes
+                    .iter()
+                    .flatten()
+                    .any(|route| route.zone().is_some()))
     }
 
     pub fn workers_dev_false_by_itself(&self) -> bool {
@@ -16,6 +16,23 @@ pub struct Site {
     pub entry_point: Option<PathBuf>,
     pub include: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    pub cache_control: Option<Vec<CacheControlRule>>,
+    /// How many published versions to keep the files of, counting the latest.
+    pub retain_versions: Option<usize>,
+    /// How long to keep the files of versions published before the latest, like "24h".
+    pub retain_for: Option<String>,
+    /// Encodings to also store compressed variants of text files in, "br" and/or "gzip". Files
+    /// too large to store whole aren't compressed.
+    pub precompress: Option<Vec<String>>,
+}
+
+/// Sets the `Cache-Control` of the site's files whose path in the bucket matches `glob`,
+/// unless an earlier rule matched.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheControlRule {
+    pub glob: String,
+    pub value: String,
 }
 
 impl Site {
@@ -62,6 +79,10 @@ impl Default for Site {
             entry_point: Some(PathBuf::from(SITE_ENTRY_POINT)),
             include: None,
             exclude: None,
+            cache_control: None,
+            retain_versions: None,
+            retain_for: None,
+            precompress: None,
         }
     }
 }
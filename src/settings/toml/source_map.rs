@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A 1-based line and column in a configuration file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// Maps dotted key paths in a TOML document (e.g. `env.staging.kv_namespaces[1].id`)
+// to the place they were written. toml-rs only tells us where syntax and type errors
+// happen, so this lets us point at the right line for problems we find after
+// deserializing. It only understands as much TOML as it needs to find keys; values
+// are skipped over, not parsed.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    locations: HashMap<String, Location>,
+}
+
+impl SourceMap {
+    pub fn parse(source: &str) -> SourceMap {
+        let mut scanner = Scanner::new(source);
+        scanner.scan();
+        SourceMap {
+            locations: scanner.locations,
+        }
+    }
+
+    // Returns the location of `path`, or of its closest parent that appears in the
+    // document, e.g. `env.staging` when `env.staging.route` was never written.
+    pub fn locate(&self, path: &str) -> Option<Location> {
+        let mut path = path;
+        loop {
+            if let Some(location) = self.locations.get(path) {
+                return Some(*location);
+            }
+            path = parent_path(path)?;
+        }
+    }
+}
+
+pub(super) fn parent_path(path: &str) -> Option<&str> {
+    if path.ends_with(']') {
+        path.rfind('[').map(|i| &path[..i])
+    } else {
+        path.rfind('.').map(|i| &path[..i])
+    }
+}
+
+pub(super) fn join_path(table: &str, key: &str) -> String {
+    if table.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", table, key)
+    }
+}
+
+// `kv-namespaces` is accepted as an alias of `kv_namespaces`, so both are recorded
+// under the canonical name.
+pub(super) fn normalize_key(key: &str) -> String {
+    match key {
+        "kv-namespaces" => "kv_namespaces".to_string(),
+        _ => key.to_string(),
+    }
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    locations: HashMap<String, Location>,
+    array_table_counts: HashMap<String, usize>,
+}
+
+impl Scanner {
+    fn new(source: &str) -> Scanner {
+        Scanner {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+            locations: HashMap::new(),
+            array_table_counts: HashMap::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn location(&self) -> Location {
+        Location {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn record(&mut self, path: String, location: Location) {
+        self.locations.entry(path).or_insert(location);
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ' ' || c == '\t' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    // skips whitespace, newlines and comments, as allowed inside arrays
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' | '\n' => {
+                    self.bump();
+                }
+                '#' => self.skip_line(),
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn scan(&mut self) {
+        let mut table = String::new();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => break,
+                Some('[') => table = self.table_header(),
+                Some(_) => {
+                    let start = self.location();
+                    let key = self.key();
+                    if key.is_empty() {
+                        // not something we understand; move on to the next line
+                        self.skip_line();
+                        continue;
+                    }
+                    let path = join_path(&table, &key);
+                    self.record(path.clone(), start);
+                    self.skip_whitespace();
+                    if self.peek() == Some('=') {
+                        self.bump();
+                        self.skip_whitespace();
+                        self.value(&path);
+                    }
+                    self.skip_line();
+                }
+            }
+        }
+    }
+
+    fn table_header(&mut self) -> String {
+        self.bump();
+        let is_array = self.peek() == Some('[');
+        if is_array {
+            self.bump();
+        }
+        self.skip_whitespace();
+        let start = self.location();
+        let path = self.key();
+        self.skip_line();
+
+        // record each parent table, so `[env.staging]` can be found by `env` too
+        let mut parent = path.as_str();
+        while let Some(p) = parent_path(parent) {
+            self.record(p.to_string(), start);
+            parent = p;
+        }
+        self.record(path.clone(), start);
+
+        if is_array {
+            let count = self.array_table_counts.entry(path.clone()).or_insert(0);
+            let table = format!("{}[{}]", path, count);
+            *count += 1;
+            self.record(table.clone(), start);
+            table
+        } else {
+            path
+        }
+    }
+
+    // Reads a (possibly dotted or quoted) key, normalizing each segment.
+    fn key(&mut self) -> String {
+        let mut segments = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let segment = match self.peek() {
+                Some(q) if q == '"' || q == '\'' => self.quoted(q),
+                _ => {
+                    let mut segment = String::new();
+                    while let Some(c) = self.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            segment.push(c);
+                            self.bump();
+                        } else {
+                            break;
+                        }
+                    }
+                    segment
+                }
+            };
+            if segment.is_empty() {
+                break;
+            }
+            segments.push(normalize_key(&segment));
+            self.skip_whitespace();
+            if self.peek() == Some('.') {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        segments.join(".")
+    }
+
+    fn quoted(&mut self, quote: char) -> String {
+        let mut contents = String::new();
+        let multiline = self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote);
+        if multiline {
+            for _ in 0..3 {
+                self.bump();
+            }
+            while self.peek().is_some() {
+                if self.peek() == Some(quote)
+                    && self.peek_at(1) == Some(quote)
+                    && self.peek_at(2) == Some(quote)
+                {
+                    for _ in 0..3 {
+                        self.bump();
+                    }
+                    break;
+                }
+                if let Some(c) = self.bump() {
+                    if c == '\\' && quote == '"' {
+                        self.bump();
+                    }
+                    contents.push(c);
+                }
+            }
+        } else {
+            self.bump();
+            while let Some(c) = self.bump() {
+                if c == quote || c == '\n' {
+                    break;
+                }
+                if c == '\\' && quote == '"' {
+                    if let Some(escaped) = self.bump() {
+                        contents.push(escaped);
+                    }
+                    continue;
+                }
+                contents.push(c);
+            }
+        }
+        contents
+    }
+
+    fn value(&mut self, path: &str) {
+        match self.peek() {
+            Some(q) if q == '"' || q == '\'' => {
+                self.quoted(q);
+            }
+            Some('[') => {
+                self.bump();
+                let mut index = 0;
+                loop {
+                    self.skip_trivia();
+                    match self.peek() {
+                        None => break,
+                        Some(']') => {
+                            self.bump();
+                            break;
+                        }
+                        Some(',') => {
+                            self.bump();
+                            index += 1;
+                        }
+                        Some(_) => {
+                            let item = format!("{}[{}]", path, index);
+                            let start = self.location();
+                            self.record(item.clone(), start);
+                            self.value(&item);
+                        }
+                    }
+                }
+            }
+            Some('{') => {
+                self.bump();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None | Some('\n') => break,
+                        Some('}') => {
+                            self.bump();
+                            break;
+                        }
+                        Some(',') => {
+                            self.bump();
+                        }
+                        Some(_) => {
+                            let start = self.location();
+                            let key = self.key();
+                            if key.is_empty() {
+                                self.bump();
+                                continue;
+                            }
+                            let item = join_path(path, &key);
+                            self.record(item.clone(), start);
+                            self.skip_whitespace();
+                            if self.peek() == Some('=') {
+                                self.bump();
+                                self.skip_whitespace();
+                                self.value(&item);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                while let Some(c) = self.peek() {
+                    if c == ',' || c == ']' || c == '}' || c == '\n' || c == '#' {
+                        break;
+                    }
+                    self.bump();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"name = "worker"
+type = "webpack" # a comment
+routes = [
+    "example.com/*",
+    "example.org/*", # trailing comma
+]
+
+[env.staging]
+zone_id = "abc"
+kv_namespaces = [ { binding = "A", id = "1" }, { binding = "B", preview_id = "2" } ]
+
+[[env.production.kv-namespaces]]
+binding = "C"
+
+[[env.production.kv-namespaces]]
+binding = "D"
+id = "4"
+"#;
+
+    fn at(line: usize, column: usize) -> Option<Location> {
+        Some(Location { line, column })
+    }
+
+    #[test]
+    fn it_locates_top_level_keys_and_array_items() {
+        let map = SourceMap::parse(TOML);
+        assert_eq!(map.locate("name"), at(1, 1));
+        assert_eq!(map.locate("type"), at(2, 1));
+        assert_eq!(map.locate("routes[1]"), at(5, 5));
+    }
+
+    #[test]
+    fn it_locates_inline_tables() {
+        let map = SourceMap::parse(TOML);
+        assert_eq!(map.locate("env.staging"), at(8, 2));
+        assert_eq!(map.locate("env.staging.zone_id"), at(9, 1));
+        assert_eq!(map.locate("env.staging.kv_namespaces[1]"), at(10, 48));
+        assert_eq!(
+            map.locate("env.staging.kv_namespaces[1].preview_id"),
+            at(10, 65)
+        );
+    }
+
+    #[test]
+    fn it_locates_arrays_of_tables_by_index() {
+        let map = SourceMap::parse(TOML);
+        assert_eq!(map.locate("env.production.kv_namespaces[0]"), at(12, 3));
+        assert_eq!(map.locate("env.production.kv_namespaces[1].id"), at(17, 1));
+    }
+
+    #[test]
+    fn it_falls_back_to_the_closest_parent() {
+        let map = SourceMap::parse(TOML);
+        assert_eq!(map.locate("env.staging.route"), at(8, 2));
+        assert_eq!(map.locate("env.production.kv_namespaces[0].id"), at(12, 3));
+        assert_eq!(map.locate("account_id"), None);
+    }
+}
@@ -42,6 +42,7 @@ fn it_can_get_multiple_deployments() {
                 id: None,
             }],
             zone_id: ZONE_ID.to_owned(),
+            zone_name: None,
         }),
         DeployTarget::Zoneless(ZonelessTarget {
             account_id: ACCOUNT_ID.to_owned(),
@@ -129,6 +130,7 @@ fn it_can_get_a_single_route_zoned_get_deployments() {
     }];
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -151,6 +153,7 @@ fn it_can_get_a_single_route_zoned_get_deployments_workers_dev_false() {
     }];
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
     let environment = None;
@@ -333,6 +336,7 @@ fn it_can_get_a_multi_route_zoned_get_deployments() {
         .collect();
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -364,6 +368,7 @@ fn it_can_get_a_multi_route_zoned_get_deployments_workers_dev_false() {
         .collect();
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -396,6 +401,7 @@ fn it_can_get_multi_route_with_route() {
         .collect();
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -486,6 +492,7 @@ fn it_can_get_a_route_with_routes() {
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         routes: expected_routes,
         zone_id: ZONE_ID.to_owned(),
+        zone_name: None,
     })];
 
     let environment = None;
@@ -494,6 +501,69 @@ fn it_can_get_a_route_with_routes() {
     assert_eq!(actual_deployments, expected_deployments);
 }
 
+#[test]
+fn it_gets_a_zoned_deployment_per_zone() {
+    let script_name = "multi_zone";
+    let toml_string = format!(
+        r#"
+        name = "{}"
+        type = "webpack"
+        zone_id = "{}"
+        routes = [
+            "hostname.tld/*",
+            {{ pattern = "other.tld/*", zone_name = "other.tld" }},
+            {{ pattern = "blog.hostname.tld/*", zone_id = "{}" }},
+            {{ pattern = "third.tld/*", zone_id = "thirdzoneid" }},
+        ]
+        "#,
+        script_name, ZONE_ID, ZONE_ID
+    );
+    let manifest = Manifest::from_str(&toml_string).unwrap();
+
+    let route = |pattern: &str| Route {
+        script: Some(script_name.to_string()),
+        pattern: pattern.to_string(),
+        id: None,
+    };
+    let expected_deployments = vec![
+        DeployTarget::Zoned(ZonedTarget {
+            routes: vec![route("hostname.tld/*"), route("blog.hostname.tld/*")],
+            zone_id: ZONE_ID.to_owned(),
+            zone_name: None,
+        }),
+        DeployTarget::Zoned(ZonedTarget {
+            routes: vec![route("other.tld/*")],
+            zone_id: String::new(),
+            zone_name: Some("other.tld".to_owned()),
+        }),
+        DeployTarget::Zoned(ZonedTarget {
+            routes: vec![route("third.tld/*")],
+            zone_id: "thirdzoneid".to_owned(),
+            zone_name: None,
+        }),
+    ];
+
+    let environment = None;
+    let actual_deployments = manifest.get_deployments(environment).unwrap();
+
+    assert_eq!(actual_deployments, expected_deployments);
+}
+
+#[test]
+fn it_errors_on_routes_without_a_zone() {
+    let toml_string = r#"
+        name = "multi_zone"
+        type = "webpack"
+        routes = [
+            { pattern = "other.tld/*", zone_name = "other.tld" },
+            "hostname.tld/*",
+        ]
+        "#;
+    let manifest = Manifest::from_str(toml_string).unwrap();
+
+    assert!(manifest.get_deployments(None).is_err());
+}
+
 #[test]
 fn it_gets_deployments_with_route_and_workers_dev_true() {
     let script_name = "route_and_workers_dev";
@@ -659,6 +729,7 @@ fn when_top_level_empty_env_zoned_single_route() {
     }];
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -739,6 +810,7 @@ fn when_top_level_empty_zoned_multi_route_env() {
 
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -868,6 +940,7 @@ fn when_top_level_zoneless_env_zoned_single_route() {
     }];
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -938,6 +1011,7 @@ fn when_top_level_zoneless_env_zoned_multi_route_route_key_present() {
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         routes: expected_routes,
         zone_id: ZONE_ID.to_owned(),
+        zone_name: None,
     })];
 
     let actual_deployments = manifest.get_deployments(Some(TEST_ENV_NAME)).unwrap();
@@ -991,6 +1065,7 @@ fn when_top_level_zoneless_env_zoned_multi_route() {
 
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -1091,6 +1166,7 @@ fn when_top_level_zoned_env_zoned_single_route_zone_id_missing() {
     }];
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: ZONE_ID.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -1121,6 +1197,7 @@ fn when_top_level_zoned_env_zoned_single_route() {
     }];
     let expected_deployments = vec![DeployTarget::Zoned(ZonedTarget {
         zone_id: env_zone_id.to_string(),
+        zone_name: None,
         routes: expected_routes,
     })];
 
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+use super::toml_fixture_path;
+use crate::settings::toml::{validate, KvNamespace, Location, Manifest};
+
+fn extends_fixture_path(fixture: &str) -> PathBuf {
+    toml_fixture_path(&format!("extends/{}", fixture))
+}
+
+fn vars(pairs: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+    Some(
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    )
+}
+
+#[test]
+fn it_merges_the_file_it_extends() {
+    env::remove_var("CF_ACCOUNT_ID");
+    env::remove_var("CF_ZONE_ID");
+    let manifest = Manifest::new(&extends_fixture_path("wrangler")).unwrap();
+    let target = manifest.get_target(None, false).unwrap();
+
+    assert_eq!(target.name, "worker");
+    assert_eq!(target.account_id, "shared-account");
+    assert_eq!(
+        target.vars,
+        vars(&[("REGION", "us"), ("LOG_LEVEL", "warn")])
+    );
+    assert_eq!(
+        target.kv_namespaces,
+        vec![KvNamespace {
+            id: "shared-kv".to_string(),
+            binding: "SHARED".to_string(),
+        }]
+    );
+
+    assert_eq!(
+        manifest.provenance.included_from("account_id"),
+        Some(extends_fixture_path("base"))
+    );
+    assert_eq!(manifest.provenance.included_from("name"), None);
+}
+
+#[test]
+fn it_merges_files_extended_by_an_environment() {
+    env::remove_var("CF_ACCOUNT_ID");
+    env::remove_var("CF_ZONE_ID");
+    let manifest = Manifest::new(&extends_fixture_path("wrangler")).unwrap();
+    let target = manifest.get_target(Some("staging"), false).unwrap();
+
+    assert_eq!(target.account_id, "shared-account");
+    assert_eq!(
+        target.vars,
+        vars(&[("REGION", "eu"), ("LOG_LEVEL", "debug")])
+    );
+    assert_eq!(target.kv_namespaces[0].id, "staging-kv");
+    assert!(manifest.get_deployments(Some("staging")).is_ok());
+
+    assert_eq!(
+        manifest
+            .provenance
+            .included_from("env.staging.kv_namespaces[0].id"),
+        Some(extends_fixture_path("staging"))
+    );
+    let resolution = manifest.resolve(Some("staging"), false).unwrap();
+    assert_eq!(
+        resolution.field("zone_id").unwrap().file,
+        Some(extends_fixture_path("base"))
+    );
+}
+
+#[test]
+fn it_detects_files_that_extend_each_other() {
+    let error = Manifest::new(&extends_fixture_path("loop_a"))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("extend each other in a loop"), "{}", error);
+    assert!(error.ends_with("loop_a.toml"), "{}", error);
+    assert!(error.contains("loop_b.toml -> "), "{}", error);
+}
+
+#[test]
+fn it_names_the_file_a_type_error_comes_from() {
+    let error = Manifest::new(&extends_fixture_path("bad_type"))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("workers_dev"), "{}", error);
+    assert!(error.ends_with("bad_type.toml"), "{}", error);
+}
+
+#[test]
+fn it_validates_problems_where_they_were_written() {
+    env::remove_var("CF_ACCOUNT_ID");
+    env::remove_var("CF_ZONE_ID");
+    let diagnostics = validate(&extends_fixture_path("broken")).unwrap();
+
+    let same_ids = diagnostics
+        .iter()
+        .find(|d| d.message.contains("same namespace"))
+        .unwrap_or_else(|| panic!("{:#?}", diagnostics));
+    assert_eq!(same_ids.file, extends_fixture_path("broken_base"));
+    assert_eq!(
+        same_ids.location,
+        Some(Location {
+            line: 2,
+            column: 49
+        })
+    );
+}
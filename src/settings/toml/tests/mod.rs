@@ -1,4 +1,7 @@
 mod deployments;
+mod extends;
+mod resolve;
+mod validate;
 
 use super::*;
 
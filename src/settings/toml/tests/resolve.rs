@@ -0,0 +1,86 @@
+use std::env;
+
+use super::toml_fixture_path;
+use crate::settings::toml::{Inheritance, Manifest, Resolution, ResolvedField, Source};
+
+fn resolve(environment: Option<&str>) -> Resolution {
+    env::remove_var("CF_ACCOUNT_ID");
+    env::remove_var("CF_ZONE_ID");
+    let manifest = Manifest::new(&toml_fixture_path("resolve")).unwrap();
+    manifest.resolve(environment, false).unwrap()
+}
+
+fn field<'a>(resolution: &'a Resolution, name: &str) -> &'a ResolvedField {
+    resolution
+        .field(name)
+        .unwrap_or_else(|| panic!("no field {} in {:#?}", name, resolution.fields))
+}
+
+#[test]
+fn it_resolves_the_top_level() {
+    let resolution = resolve(None);
+
+    assert_eq!(resolution.target.name, "worker");
+    assert_eq!(field(&resolution, "account_id").source, Source::TopLevel);
+    assert_eq!(field(&resolution, "vars").source, Source::TopLevel);
+    assert_eq!(field(&resolution, "webpack_config").source, Source::Unset);
+    assert_eq!(field(&resolution, "webpack_config").value, None);
+    assert_eq!(
+        field(&resolution, "type").inheritance,
+        Inheritance::TopLevel
+    );
+}
+
+#[test]
+fn it_explains_what_an_environment_does_not_inherit() {
+    let resolution = resolve(Some("staging"));
+
+    assert_eq!(resolution.target.name, "worker-staging");
+    assert_eq!(resolution.target.account_id, "top-account");
+    assert!(resolution.target.kv_namespaces.is_empty());
+    assert_eq!(resolution.target.vars, None);
+
+    let account_id = field(&resolution, "account_id");
+    assert_eq!(account_id.inheritance, Inheritance::Inherited);
+    assert_eq!(account_id.source, Source::TopLevel);
+
+    for name in &["kv_namespaces", "vars"] {
+        let field = field(&resolution, name);
+        assert_eq!(field.inheritance, Inheritance::NotInherited);
+        assert_eq!(field.source, Source::Unset);
+        assert!(field
+            .note
+            .as_ref()
+            .unwrap()
+            .contains("is not used by environments"));
+    }
+
+    // setting `routes` stops `workers_dev` from being inherited
+    assert_eq!(field(&resolution, "workers_dev").source, Source::Unset);
+
+    let zone_id = field(&resolution, "zone_id");
+    assert_eq!(zone_id.source, Source::TopLevel);
+    assert!(zone_id.note.as_ref().unwrap().contains("top-level zone_id"));
+}
+
+#[test]
+fn it_prefers_values_set_in_the_environment() {
+    let resolution = resolve(Some("production"));
+    let from_production = Source::Environment("production".to_string());
+
+    assert_eq!(resolution.target.name, "worker-prod");
+    assert_eq!(resolution.target.account_id, "prod-account");
+    assert_eq!(field(&resolution, "name").source, from_production);
+    assert_eq!(field(&resolution, "account_id").source, from_production);
+
+    let kv_namespaces = field(&resolution, "kv_namespaces");
+    assert_eq!(kv_namespaces.source, from_production);
+    assert_eq!(
+        kv_namespaces.value.as_ref().unwrap()[0]["id"].as_str(),
+        Some("prod-kv")
+    );
+    assert_eq!(
+        field(&resolution, "workers_dev").value,
+        Some(toml::Value::Boolean(true))
+    );
+}
@@ -0,0 +1,60 @@
+use std::env;
+
+use super::toml_fixture_path;
+use crate::settings::toml::{validate, Diagnostic, Location};
+
+fn find<'a>(diagnostics: &'a [Diagnostic], needle: &str) -> &'a Diagnostic {
+    diagnostics
+        .iter()
+        .find(|d| d.message.contains(needle))
+        .unwrap_or_else(|| {
+            panic!(
+                "no diagnostic containing {:?} in {:#?}",
+                needle, diagnostics
+            )
+        })
+}
+
+fn at(line: usize, column: usize) -> Option<Location> {
+    Some(Location { line, column })
+}
+
+#[test]
+fn it_finds_no_problems_in_a_valid_config() {
+    env::remove_var("CF_ACCOUNT_ID");
+    env::remove_var("CF_ZONE_ID");
+    let diagnostics = validate(&toml_fixture_path("valid")).unwrap();
+
+    assert_eq!(diagnostics, vec![]);
+}
+
+#[test]
+fn it_reports_every_problem_with_its_location() {
+    env::remove_var("CF_ACCOUNT_ID");
+    env::remove_var("CF_ZONE_ID");
+    let diagnostics = validate(&toml_fixture_path("misconfigured")).unwrap();
+
+    let same_ids = find(&diagnostics, "same namespace for `id` and `preview_id`");
+    assert_eq!(same_ids.location, at(9, 1));
+    assert_eq!(same_ids.environment, None);
+
+    let no_zone = find(&diagnostics, "no `zone_id`");
+    assert_eq!(no_zone.location, at(12, 1));
+    assert_eq!(no_zone.environment, Some("staging".to_string()));
+
+    let duplicate_name = find(&diagnostics, "used more than once");
+    assert_eq!(duplicate_name.location, at(15, 1));
+
+    let missing_id = find(&diagnostics, "has no `id`");
+    assert_eq!(missing_id.location, at(17, 19));
+    assert_eq!(missing_id.environment, Some("production".to_string()));
+}
+
+#[test]
+fn it_reports_type_errors_with_their_location() {
+    let diagnostics = validate(&toml_fixture_path("invalid_type")).unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].location.map(|l| l.line), Some(3));
+    assert!(!diagnostics[0].message.contains("at line"));
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use toml::value::Table;
+
+use super::extends::{uses_extends, Origin, Provenance};
+use super::kv_namespace::ConfigKvNamespace;
+use super::manifest::find_duplicate_names;
+use super::route::{ConfigRoute, RouteTable};
+use super::source_map::{join_path, Location, SourceMap};
+use super::Manifest;
+
+/// A single problem found in a configuration file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub location: Option<Location>,
+    pub environment: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(location) = self.location {
+            write!(f, ":{}", location)?;
+        }
+        if let Some(environment) = &self.environment {
+            write!(f, ": [env.{}]", environment)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+struct Diagnostics {
+    file: PathBuf,
+    provenance: Provenance,
+    source_maps: HashMap<PathBuf, Option<SourceMap>>,
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    // Points at `path` in whichever file set it, which is not necessarily the file being
+    // validated when it `extends` others.
+    fn push(&mut self, path: &str, environment: Option<&str>, message: String) {
+        let origin = self.provenance.locate(path).unwrap_or_else(|| Origin {
+            file: self.file.clone(),
+            key: path.to_string(),
+        });
+        let location = self
+            .source_maps
+            .entry(origin.file.clone())
+            .or_insert_with(|| source_map(&origin.file))
+            .as_ref()
+            .and_then(|source_map| source_map.locate(&origin.key));
+        self.push_in(origin.file, location, environment, message);
+    }
+
+    fn push_at(&mut self, location: Option<Location>, environment: Option<&str>, message: String) {
+        self.push_in(self.file.clone(), location, environment, message);
+    }
+
+    fn push_in(
+        &mut self,
+        file: PathBuf,
+        location: Option<Location>,
+        environment: Option<&str>,
+        message: String,
+    ) {
+        let diagnostic = Diagnostic {
+            file,
+            location,
+            environment: environment.map(str::to_string),
+            message,
+        };
+        if !self.list.contains(&diagnostic) {
+            self.list.push(diagnostic);
+        }
+    }
+}
+
+// Only TOML files can be located into; other formats are still validated, just without
+// line numbers.
+fn source_map(file: &Path) -> Option<SourceMap> {
+    match file.extension() {
+        Some(extension) if extension != "toml" => None,
+        _ => fs::read_to_string(file)
+            .ok()
+            .map(|source| SourceMap::parse(&source)),
+    }
+}
+
+// Checks a configuration file for every problem we can find without talking to the API,
+// rather than stopping at the first one like `Manifest::new`. Each environment is
+// resolved the same way `publish`, `preview` and `dev` would resolve it. Only failing
+// to read the file at all is returned as an error.
+pub fn validate(config_path: &Path) -> Result<Vec<Diagnostic>, failure::Error> {
+    let source = fs::read_to_string(config_path)?;
+    let mut diagnostics = Diagnostics {
+        file: config_path.to_path_buf(),
+        provenance: Provenance::default(),
+        source_maps: HashMap::new(),
+        list: Vec::new(),
+    };
+
+    // syntax and type errors stop us from checking anything else, but toml-rs at least
+    // tells us where they are. Type errors can only be placed like this when the file
+    // doesn't extend another one, since it may not be a complete manifest on its own.
+    if source_map(config_path).is_some() {
+        let checked = toml::from_str::<Table>(&source).and_then(|table| {
+            if uses_extends(&table) {
+                Ok(())
+            } else {
+                toml::from_str::<Manifest>(&source).map(|_| ())
+            }
+        });
+        if let Err(e) = checked {
+            let location = e.line_col().map(|(line, column)| Location {
+                line: line + 1,
+                column: column + 1,
+            });
+            diagnostics.push_at(location, None, strip_position(&e.to_string()));
+            return Ok(diagnostics.list);
+        }
+    }
+
+    // this also merges in any files it extends, and overrides from `CF_*` environment variables
+    let manifest = match Manifest::from_config_path(config_path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            diagnostics.push_at(None, None, e.to_string());
+            return Ok(diagnostics.list);
+        }
+    };
+    diagnostics.provenance = manifest.provenance.clone();
+
+    check_names(&manifest, &mut diagnostics);
+
+    let mut environments: Vec<Option<&str>> = vec![None];
+    if let Some(env) = &manifest.env {
+        let mut names: Vec<&str> = env.keys().map(String::as_str).collect();
+        names.sort();
+        environments.extend(names.into_iter().map(Some));
+    }
+
+    for environment in environments {
+        let found = diagnostics.list.len();
+        check_kv_namespaces(&manifest, environment, &mut diagnostics);
+        check_routes(&manifest, environment, &mut diagnostics);
+
+        // Resolving an environment stops at its first problem, which is usually one we
+        // have already pointed at more precisely above.
+        if diagnostics.list.len() == found {
+            check_resolution(&manifest, environment, &mut diagnostics);
+        }
+    }
+
+    Ok(diagnostics.list)
+}
+
+fn table_path(environment: Option<&str>) -> String {
+    match environment {
+        Some(environment) => format!("env.{}", environment),
+        None => String::new(),
+    }
+}
+
+fn strip_position(message: &str) -> String {
+    lazy_static! {
+        static ref POSITION: Regex = Regex::new(r" at line \d+ column \d+$").unwrap();
+    }
+    POSITION.replace(message, "").to_string()
+}
+
+fn check_names(manifest: &Manifest, diagnostics: &mut Diagnostics) {
+    let duplicate_names = find_duplicate_names(manifest);
+    if let Some(env) = &manifest.env {
+        for (env_name, environment) in env {
+            if let Some(name) = &environment.name {
+                if duplicate_names.contains(name) {
+                    diagnostics.push(
+                        &format!("env.{}.name", env_name),
+                        Some(env_name),
+                        format!(
+                            "the name \"{}\" is used more than once; each name in your configuration file must be unique",
+                            name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn check_kv_namespaces(
+    manifest: &Manifest,
+    environment: Option<&str>,
+    diagnostics: &mut Diagnostics,
+) {
+    let kv_namespaces: &Option<Vec<ConfigKvNamespace>> = match environment {
+        Some(environment) => match manifest.get_environment(Some(environment)) {
+            Ok(Some(env)) => &env.kv_namespaces,
+            _ => return,
+        },
+        None => &manifest.kv_namespaces,
+    };
+
+    let table = table_path(environment);
+    let mut bindings: HashSet<&str> = HashSet::new();
+    for (i, ns) in kv_namespaces.iter().flatten().enumerate() {
+        let path = join_path(&table, &format!("kv_namespaces[{}]", i));
+
+        if ns.binding.is_empty() {
+            diagnostics.push(
+                &path,
+                environment,
+                "kv namespace is missing a binding".to_string(),
+            );
+        } else if !bindings.insert(&ns.binding) {
+            diagnostics.push(
+                &join_path(&path, "binding"),
+                environment,
+                format!("kv namespace binding \"{}\" is duplicated", ns.binding),
+            );
+        }
+
+        match (&ns.id, &ns.preview_id) {
+            (None, _) => diagnostics.push(
+                &path,
+                environment,
+                format!(
+                    "kv namespace \"{}\" has no `id`, which is required to publish",
+                    ns.binding
+                ),
+            ),
+            (Some(id), Some(preview_id)) if id == preview_id => diagnostics.push(
+                &join_path(&path, "preview_id"),
+                environment,
+                format!(
+                    "kv namespace \"{}\" uses the same namespace for `id` and `preview_id`, so previews would change production data",
+                    ns.binding
+                ),
+            ),
+            _ => {}
+        }
+
+        if ns.preview_id.is_none() {
+            diagnostics.push(
+                &path,
+                environment,
+                format!(
+                    "kv namespace \"{}\" has no `preview_id`, which is required by `wrangler dev` and `wrangler preview`",
+                    ns.binding
+                ),
+            );
+        }
+    }
+}
+
+fn check_routes(manifest: &Manifest, environment: Option<&str>, diagnostics: &mut Diagnostics) {
+    let (route, routes, zone_id) = match environment {
+        Some(environment) => match manifest.get_environment(Some(environment)) {
+            Ok(Some(env)) => (
+                &env.route,
+                &env.routes,
+                env.zone_id.as_ref().or(manifest.zone_id.as_ref()),
+            ),
+            _ => return,
+        },
+        None => (&manifest.route, &manifest.routes, manifest.zone_id.as_ref()),
+    };
+
+    let table = table_path(environment);
+    for (i, config_route) in routes.iter().flatten().enumerate() {
+        let path = join_path(&table, &format!("routes[{}]", i));
+        if config_route.pattern().is_empty() {
+            diagnostics.push(&path, environment, "route is empty".to_string());
+        }
+        if let ConfigRoute::Table(RouteTable {
+            zone_id: Some(_),
+            zone_name: Some(_),
+            ..
+        }) = config_route
+        {
+            diagnostics.push(
+                &path,
+                environment,
+                "route sets both `zone_id` and `zone_name`; `zone_name` is ignored".to_string(),
+            );
+        }
+    }
+
+    // routes that name their own zone don't need a `zone_id`
+    let has_routes = route.is_some()
+        || routes
+            .iter()
+            .flatten()
+            .any(|config_route| config_route.zone().is_none());
+    if has_routes && zone_id.is_none() {
+        let key = if route.is_some() { "route" } else { "routes" };
+        diagnostics.push(
+            &join_path(&table, key),
+            environment,
+            format!(
+                "`{}` is set but there is no `zone_id` to deploy it to{}",
+                key,
+                if environment.is_some() {
+                    " in this environment or at the top level"
+                } else {
+                    ""
+                }
+            ),
+        );
+    }
+}
+
+fn check_resolution(manifest: &Manifest, environment: Option<&str>, diagnostics: &mut Diagnostics) {
+    let table = table_path(environment);
+
+    if let Err(e) = manifest.get_target(environment, false) {
+        diagnostics.push(&table, environment, e.to_string());
+    }
+    if let Err(e) = manifest.get_target(environment, true) {
+        diagnostics.push(&table, environment, format!("when previewing: {}", e));
+    }
+    if let Err(e) = manifest.get_deployments(environment) {
+        diagnostics.push(&table, environment, e.to_string());
+    }
+}
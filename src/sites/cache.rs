@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
+use uuid::Uuid;
+
+use super::chunks::Chunk;
+use super::manifest::AssetManifest;
+use super::precompress::Precompressed;
+use crate::kv::store::{KvStore, PutOptions};
+use crate::settings::LOCAL_STATE_DIR;
+
+/// The key in a site's namespace that identifies the last sync to it, so that a cache can
+/// tell whether the namespace has changed since it was synced from this machine.
+pub const SYNC_KEY: &str = "__wrangler_site_sync";
+
+/// What's known about the files of a site's bucket from the last time it was synced, kept
+/// under `.wrangler/state/sites/`.
+///
+/// Files whose size and modification time haven't changed keep the key they were stored under
+/// before, so they don't have to be read again. And once a sync to a namespace has finished,
+/// the keys left in it are known, so the next sync needn't list them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SiteCache {
+    // where the cache is kept, or None for one that isn't
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    // the files of the bucket, by their url-safe path in it
+    files: HashMap<String, CachedFile>,
+    // the keys in each namespace after the last sync to it finished, by namespace id
+    namespaces: HashMap<String, SyncedNamespace>,
+    // the keys each namespace will have once the sync to it that has started finishes
+    pending: HashMap<String, Vec<String>>,
+}
+
+/// A file of a bucket as it was when it was last read.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedFile {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+    pub key: String,
+    pub hash: Option<String>,
+    #[serde(default)]
+    pub precompressed: Precompressed,
+    #[serde(default)]
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SyncedNamespace {
+    // the value of SYNC_KEY the sync wrote
+    sync_id: String,
+    keys: Vec<String>,
+}
+
+impl SiteCache {
+    /// The cache of the bucket at `bucket`, which is empty the first time.
+    pub fn open(bucket: &Path) -> Self {
+        let path = cache_path(bucket);
+        let mut cache: SiteCache = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        cache.path = Some(path);
+        cache
+    }
+
+    /// An empty cache for the bucket at `bucket`, for a sync that shouldn't trust what's
+    /// been cached. What it learns still replaces the old cache.
+    pub fn empty(bucket: &Path) -> Self {
+        SiteCache {
+            path: Some(cache_path(bucket)),
+            ..SiteCache::default()
+        }
+    }
+
+    /// The file cached at `url_safe_path`, if it hasn't changed since.
+    pub fn get(&self, url_safe_path: &str, metadata: &Metadata) -> Option<&CachedFile> {
+        let (modified_secs, modified_nanos) = modified(metadata)?;
+        self.files.get(url_safe_path).filter(|file| {
+            file.size == metadata.len()
+                && file.modified_secs == modified_secs
+                && file.modified_nanos == modified_nanos
+        })
+    }
+
+    /// Caches the key and hash of the file at `url_safe_path`, and its compressed variants or
+    /// chunks.
+    pub fn insert(
+        &mut self,
+        url_safe_path: String,
+        metadata: &Metadata,
+        key: String,
+        hash: Option<String>,
+        precompressed: Precompressed,
+        chunks: Vec<Chunk>,
+    ) {
+        if let Some((modified_secs, modified_nanos)) = modified(metadata) {
+            let file = CachedFile {
+                modified_secs,
+                modified_nanos,
+                size: metadata.len(),
+                key,
+                hash,
+                precompressed,
+                chunks,
+            };
+            self.files.insert(url_safe_path, file);
+        }
+    }
+
+    /// Forgets the files that are no longer in the bucket.
+    pub fn retain_files(&mut self, asset_manifest: &AssetManifest) {
+        self.files
+            .retain(|url_safe_path, _| asset_manifest.contains_key(url_safe_path));
+    }
+
+    /// The keys in a namespace after the last sync to it from here, if nothing has
+    /// synced to it since.
+    pub fn synced_keys(
+        &self,
+        store: &dyn KvStore,
+        namespace_id: &str,
+    ) -> Result<Option<Vec<String>>, failure::Error> {
+        let synced = match self.namespaces.get(namespace_id) {
+            Some(synced) => synced,
+            None => return Ok(None),
+        };
+        match store.get(namespace_id, SYNC_KEY)? {
+            Some(sync_id) if sync_id == synced.sync_id.as_bytes() => Ok(Some(synced.keys.clone())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Records the keys a namespace will have once the sync to it that's starting finishes,
+    /// and saves the cache. Unless the sync changes nothing, the keys cached for the namespace
+    /// can't be trusted until it finishes.
+    pub fn start_sync(
+        &mut self,
+        namespace_id: &str,
+        keys: Vec<String>,
+        changes: bool,
+    ) -> Result<(), failure::Error> {
+        if changes {
+            self.namespaces.remove(namespace_id);
+        }
+        self.pending.insert(namespace_id.to_string(), keys);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), failure::Error> {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, serde_json::to_vec(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records that a sync of the bucket at `bucket` to a namespace has finished, so that the next
+/// one can trust the keys it left there.
+pub fn finish_sync(
+    store: &dyn KvStore,
+    bucket: &Path,
+    namespace_id: &str,
+) -> Result<(), failure::Error> {
+    let mut cache = SiteCache::open(bucket);
+    let keys = match cache.pending.remove(namespace_id) {
+        Some(keys) => keys,
+        None => return Ok(()),
+    };
+
+    let sync_id = Uuid::new_v4().to_simple().to_string();
+    store.put(
+        namespace_id,
+        SYNC_KEY,
+        sync_id.clone().into_bytes(),
+        &PutOptions::default(),
+    )?;
+    cache
+        .namespaces
+        .insert(namespace_id.to_string(), SyncedNamespace { sync_id, keys });
+    cache.save()
+}
+
+fn cache_path(bucket: &Path) -> PathBuf {
+    let bucket = fs::canonicalize(bucket).unwrap_or_else(|_| bucket.to_path_buf());
+    let mut hasher = XxHash64::default();
+    hasher.write(bucket.to_string_lossy().as_bytes());
+    Path::new(LOCAL_STATE_DIR)
+        .join("state")
+        .join("sites")
+        .join(format!("{:x}.json", hasher.finish()))
+}
+
+fn modified(metadata: &Metadata) -> Option<(u64, u32)> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((modified.as_secs(), modified.subsec_nanos()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::settings::toml::{Site, Target};
+    use crate::sites::directory_files;
+
+    #[test]
+    fn it_keeps_the_keys_of_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("index.html");
+        fs::write(&file, "hello").unwrap();
+        let target = Target {
+            site: Some(Site::new("unused")),
+            ..Target::default()
+        };
+
+        let mut cache = SiteCache::default();
+        let (files, _, _) = directory_files(&target, dir.path(), &mut cache).unwrap();
+        let key = files[0].key.clone();
+        assert_eq!(cache.files["index.html"].key, key);
+
+        // a cached file isn't read again
+        cache.files.get_mut("index.html").unwrap().key = "cached".to_string();
+        let (files, _, _) = directory_files(&target, dir.path(), &mut cache).unwrap();
+        assert_eq!(files[0].key, "cached");
+
+        fs::write(&file, "hello, world").unwrap();
+        let (files, _, _) = directory_files(&target, dir.path(), &mut cache).unwrap();
+        assert_ne!(files[0].key, "cached");
+        assert_ne!(files[0].key, key);
+    }
+}
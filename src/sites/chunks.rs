@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{generate_path_and_key, VALUE_MAX_SIZE};
+
+/// Files larger than a value can hold are stored in chunks of this many bytes, but for the last.
+pub const CHUNK_SIZE: u64 = VALUE_MAX_SIZE;
+
+/// A part of a file too large to store whole, stored under a key hashed from its contents like
+/// a whole file's, so unchanged chunks aren't uploaded again.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Chunk {
+    pub key: String,
+    /// In bytes.
+    pub size: u64,
+    /// The hex encoded SHA-256 hash of the chunk.
+    pub hash: String,
+}
+
+/// Reads the file at `path` in `directory` a chunk at a time, returning its chunks in order and
+/// the hex encoded SHA-256 hash of the whole file.
+pub fn split(
+    path: &Path,
+    directory: &Path,
+    chunk_size: u64,
+) -> Result<(Vec<Chunk>, String), failure::Error> {
+    let mut file = File::open(path)?;
+    let mut file_hasher = Sha256::new();
+    let mut chunks = Vec::new();
+    loop {
+        let mut value = Vec::new();
+        (&mut file).take(chunk_size).read_to_end(&mut value)?;
+        if value.is_empty() {
+            break;
+        }
+        file_hasher.update(&value);
+        let (_, key) = generate_path_and_key(path, directory, Some(base64::encode(&value)))?;
+        chunks.push(Chunk {
+            key,
+            size: value.len() as u64,
+            hash: format!("{:x}", Sha256::digest(&value)),
+        });
+    }
+    Ok((chunks, format!("{:x}", file_hasher.finalize())))
+}
+
+/// Reads the chunk at `index` of the file at `path`.
+pub fn read(path: &Path, index: usize, chunk_size: u64) -> Result<Vec<u8>, failure::Error> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(index as u64 * chunk_size))?;
+    let mut value = Vec::new();
+    file.take(chunk_size).read_to_end(&mut value)?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn it_splits_files_into_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("video.mp4");
+        fs::write(&path, "aaaabbbbaaaacc").unwrap();
+
+        let (chunks, hash) = split(&path, dir.path(), 4).unwrap();
+        let sizes: Vec<_> = chunks.iter().map(|chunk| chunk.size).collect();
+        assert_eq!(sizes, vec![4, 4, 4, 2]);
+        assert_eq!(hash, format!("{:x}", Sha256::digest(b"aaaabbbbaaaacc")));
+        // chunks with the same contents share a key
+        assert_eq!(chunks[0].key, chunks[2].key);
+        assert_ne!(chunks[0].key, chunks[1].key);
+        assert!(chunks[1].key.starts_with("video.") && chunks[1].key.ends_with(".mp4"));
+
+        assert_eq!(read(&path, 1, 4).unwrap(), b"bbbb");
+        assert_eq!(read(&path, 3, 4).unwrap(), b"cc");
+    }
+}
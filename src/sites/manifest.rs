@@ -1,3 +1,196 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-pub type AssetManifest = HashMap<String, String>;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Maps the path of each file of a site to the asset it's stored as. Workers get it as
+/// `__STATIC_CONTENT_MANIFEST_V2`, see `manifest_bindings`.
+pub type AssetManifest = HashMap<String, AssetEntry>;
+
+/// The text blob binding that maps each path straight to its key, as
+/// @cloudflare/kv-asset-handler reads it.
+pub const MANIFEST_BINDING: &str = "__STATIC_CONTENT_MANIFEST";
+/// The text blob binding that holds the full `AssetManifest`.
+pub const MANIFEST_V2_BINDING: &str = "__STATIC_CONTENT_MANIFEST_V2";
+
+/// The text blobs a worker serving the site is bound to, by binding name.
+///
+/// `__STATIC_CONTENT_MANIFEST` keeps its original shape so deployed workers go on finding their
+/// assets; the metadata, chunks and encodings only go in `__STATIC_CONTENT_MANIFEST_V2`.
+pub fn manifest_bindings(
+    asset_manifest: &AssetManifest,
+) -> Result<Vec<(&'static str, String)>, failure::Error> {
+    let keys: HashMap<&str, &str> = asset_manifest
+        .iter()
+        .map(|(path, entry)| (path.as_str(), entry.key.as_str()))
+        .collect();
+
+    Ok(vec![
+        (MANIFEST_BINDING, serde_json::to_string(&keys)?),
+        (MANIFEST_V2_BINDING, serde_json::to_string(asset_manifest)?),
+    ])
+}
+
+/// Where a file of a site is stored, and what a worker needs to serve it.
+///
+/// Manifests from before assets had metadata map each path straight to its key, and
+/// deserialize to entries without metadata.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AssetEntry {
+    pub key: String,
+    #[serde(flatten)]
+    pub metadata: AssetMetadata,
+    /// For a file too large to store whole, the keys of its chunks in order. Nothing is stored
+    /// under `key` then, and the metadata's `size` is that of the whole file.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+    /// The compressed variants of the file, by the `Content-Encoding` they're served with.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub encodings: BTreeMap<String, AssetVariant>,
+}
+
+/// A compressed variant of a file of a site.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AssetVariant {
+    pub key: String,
+    /// In bytes, compressed.
+    pub size: u64,
+}
+
+/// What's known about a file of a site. Each asset carries this as its KV metadata.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AssetMetadata {
+    /// Guessed from the file's extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// In bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The hex encoded SHA-256 hash of the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// From the first of the `[site]`'s `cache_control` rules that matches the file's path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+    /// Set on the compressed variants of a file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+}
+
+impl AssetEntry {
+    pub fn new(key: String, metadata: AssetMetadata) -> Self {
+        AssetEntry {
+            key,
+            metadata,
+            chunks: Vec::new(),
+            encodings: BTreeMap::new(),
+        }
+    }
+
+    /// The keys of the file, or of its chunks, and of its variants.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        let whole = if self.chunks.is_empty() {
+            Some(&self.key)
+        } else {
+            None
+        };
+        whole
+            .into_iter()
+            .chain(&self.chunks)
+            .chain(self.encodings.values().map(|variant| &variant.key))
+    }
+}
+
+impl<'de> Deserialize<'de> for AssetEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Shape {
+            Key(String),
+            Entry {
+                key: String,
+                #[serde(flatten)]
+                metadata: AssetMetadata,
+                #[serde(default)]
+                chunks: Vec<String>,
+                #[serde(default)]
+                encodings: BTreeMap<String, AssetVariant>,
+            },
+        }
+
+        Ok(match Shape::deserialize(deserializer)? {
+            Shape::Key(key) => AssetEntry::new(key, AssetMetadata::default()),
+            Shape::Entry {
+                key,
+                metadata,
+                chunks,
+                encodings,
+            } => AssetEntry {
+                key,
+                metadata,
+                chunks,
+                encodings,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_manifests_with_and_without_metadata() {
+        let manifest: AssetManifest = serde_json::from_str(
+            r#"{
+                "index.html": "index.abc123.html",
+                "app.js": {"key": "app.def456.js", "content_type": "application/javascript", "size": 3}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest["index.html"],
+            AssetEntry::new("index.abc123.html".to_string(), AssetMetadata::default())
+        );
+        assert_eq!(manifest["app.js"].key, "app.def456.js");
+        assert_eq!(manifest["app.js"].metadata.size, Some(3));
+
+        let written = serde_json::to_value(&manifest["app.js"]).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!({"key": "app.def456.js", "content_type": "application/javascript", "size": 3})
+        );
+    }
+
+    #[test]
+    fn it_binds_the_original_manifest_as_path_to_key() {
+        let mut asset_manifest = AssetManifest::new();
+        let mut entry = AssetEntry::new(
+            "app.def456.js".to_string(),
+            AssetMetadata {
+                content_type: Some("application/javascript".to_string()),
+                size: Some(3),
+                ..AssetMetadata::default()
+            },
+        );
+        entry.encodings.insert(
+            "br".to_string(),
+            AssetVariant {
+                key: "app.def456.js.br".to_string(),
+                size: 2,
+            },
+        );
+        asset_manifest.insert("app.js".to_string(), entry);
+
+        let blobs = manifest_bindings(&asset_manifest).unwrap();
+        let names: Vec<&str> = blobs.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec![MANIFEST_BINDING, MANIFEST_V2_BINDING]);
+
+        let keys: HashMap<String, String> = serde_json::from_str(&blobs[0].1).unwrap();
+        let mut expected = HashMap::new();
+        expected.insert("app.js".to_string(), "app.def456.js".to_string());
+        assert_eq!(keys, expected);
+
+        let full: AssetManifest = serde_json::from_str(&blobs[1].1).unwrap();
+        assert_eq!(full, asset_manifest);
+    }
+}
@@ -1,27 +1,41 @@
 extern crate base64;
 
+mod cache;
+mod chunks;
 mod manifest;
+mod precompress;
 mod sync;
+mod versions;
 
-pub use manifest::AssetManifest;
-pub use sync::sync;
+pub use cache::{finish_sync, SiteCache};
+pub use manifest::{
+    manifest_bindings, AssetEntry, AssetManifest, AssetMetadata, AssetVariant, MANIFEST_BINDING,
+    MANIFEST_V2_BINDING,
+};
+pub use sync::{plan_sync, sync};
+pub use versions::{Retention, SiteVersions};
 
 use std::ffi::OsString;
 use std::fs;
 use std::hash::Hasher;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use failure::format_err;
+use globset::{Glob, GlobMatcher};
 use ignore::overrides::{Override, OverrideBuilder};
 use ignore::{Walk, WalkBuilder};
 use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
 use twox_hash::XxHash64;
 
-use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use chunks::CHUNK_SIZE;
+use precompress::{Encoding, Precompressed, Variant};
 
-use crate::kv::namespace::{upsert, UpsertedNamespace};
+use crate::http;
+use crate::kv::namespace::{list, upsert, UpsertedNamespace};
+use crate::kv::store::BulkPair;
 use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::{KvNamespace, Target};
+use crate::settings::toml::{KvNamespace, Site, Target};
 use crate::terminal::message::{Message, StdErr};
 pub const KEY_MAX_SIZE: usize = 512;
 // Oddly enough, metadata.len() returns a u64, not usize.
@@ -33,11 +47,7 @@ pub fn add_namespace(
     target: &mut Target,
     preview: bool,
 ) -> Result<KvNamespace, failure::Error> {
-    let title = if preview {
-        format!("__{}-{}", target.name, "workers_sites_assets_preview")
-    } else {
-        format!("__{}-{}", target.name, "workers_sites_assets")
-    };
+    let title = namespace_title(target, preview);
 
     let site_namespace = match upsert(target, &user, title)? {
         UpsertedNamespace::Created(namespace) => {
@@ -64,17 +74,105 @@ pub fn add_namespace(
     Ok(site_namespace)
 }
 
-// Returns the hashed key and value pair for all files in a directory.
+// Finds the static site assets KV namespace for a Target, without creating it.
+pub fn find_namespace(
+    user: &GlobalUser,
+    target: &Target,
+    preview: bool,
+) -> Result<Option<KvNamespace>, failure::Error> {
+    let title = namespace_title(target, preview);
+    let client = http::cf_v4_client(user)?;
+    let namespace = list(&client, target)?
+        .into_iter()
+        .find(|namespace| namespace.title == title)
+        .map(|namespace| KvNamespace {
+            binding: "__STATIC_CONTENT".to_string(),
+            id: namespace.id,
+        });
+
+    Ok(namespace)
+}
+
+fn namespace_title(target: &Target, preview: bool) -> String {
+    if preview {
+        format!("__{}-{}", target.name, "workers_sites_assets_preview")
+    } else {
+        format!("__{}-{}", target.name, "workers_sites_assets")
+    }
+}
+
+// Returns the hashed key and value pair for all files in a directory, with the metadata
+// workers serve them with.
 pub fn directory_keys_values(
     target: &Target,
     directory: &Path,
-) -> Result<(Vec<KeyValuePair>, AssetManifest, Vec<String>), failure::Error> {
+) -> Result<(Vec<BulkPair>, AssetManifest, Vec<String>), failure::Error> {
+    let (files, asset_manifest, file_list) =
+        directory_files(target, directory, &mut SiteCache::default())?;
+    let upload_vec = files
+        .iter()
+        .map(SiteFile::to_pair)
+        .collect::<Result<_, _>>()?;
+    Ok((upload_vec, asset_manifest, file_list))
+}
+
+/// The pairs that upload `files`, for `kv::bulk::put_with_metadata`. Each file is only read
+/// once the batch it's in is about to be sent.
+pub fn file_pairs(
+    files: Vec<SiteFile>,
+) -> impl Iterator<Item = Result<(BulkPair, u64), failure::Error>> + Send {
+    files.into_iter().map(|file| Ok((file.to_pair()?, 1)))
+}
+
+/// A file of a site, a compressed variant of one or a chunk of one, with the hashed key it's
+/// stored under and the metadata it's served with.
+pub struct SiteFile {
+    pub path: PathBuf,
+    pub key: String,
+    pub metadata: AssetMetadata,
+    pub encoding: Option<Encoding>,
+    /// The index of the chunk, for a file too large to store whole.
+    pub chunk: Option<usize>,
+}
+
+impl SiteFile {
+    /// Reads the file, compressing it for a variant, or the chunk of it into the pair that
+    /// uploads it.
+    pub fn to_pair(&self) -> Result<BulkPair, failure::Error> {
+        let mut value = match self.chunk {
+            Some(index) => chunks::read(&self.path, index, CHUNK_SIZE)?,
+            None => fs::read(&self.path)?,
+        };
+        if let Some(encoding) = self.encoding {
+            value = encoding.compress(&value)?;
+        }
+        Ok(BulkPair {
+            key: self.key.clone(),
+            // Need to base64 encode value
+            value: base64::encode(&value),
+            expiration: None,
+            expiration_ttl: None,
+            base64: Some(true),
+            metadata: Some(serde_json::to_value(&self.metadata)?),
+        })
+    }
+}
+
+// Returns the hashed key and metadata of all files in a directory, without reading those
+// `cache` has the key of.
+pub fn directory_files(
+    target: &Target,
+    directory: &Path,
+    cache: &mut SiteCache,
+) -> Result<(Vec<SiteFile>, AssetManifest, Vec<String>), failure::Error> {
     match &fs::metadata(directory) {
         Ok(file_type) if file_type.is_dir() => {
-            let mut upload_vec: Vec<KeyValuePair> = Vec::new();
+            let mut files: Vec<SiteFile> = Vec::new();
             let mut asset_manifest = AssetManifest::new();
             let mut file_list: Vec<String> = Vec::new();
             let dir_walker = get_dir_iterator(target, directory)?;
+            let cache_control = CacheControl::new(target.site.as_ref())?;
+            let encodings = precompress::encodings(target.site.as_ref())?;
             let spinner_style =
                 ProgressStyle::default_spinner().template("{spinner}   Preparing {msg}...");
             let spinner = ProgressBar::new_spinner().with_style(spinner_style);
@@ -87,30 +185,140 @@ pub fn directory_keys_values(
                     spinner.set_message(&format!("{}", path.display()));
 
                     file_list.push(path.to_str().unwrap().to_string());
-                    validate_file_size(&path)?;
 
-                    let value = std::fs::read(path)?;
-
-                    // Need to base64 encode value
-                    let b64_value = base64::encode(&value);
-
-                    let (url_safe_path, key) =
-                        generate_path_and_key(path, directory, Some(b64_value.clone()))?;
+                    let file_metadata = fs::metadata(path)?;
+                    // files too large to store whole are chunked, and not precompressed
+                    let chunked = file_metadata.len() > VALUE_MAX_SIZE;
+                    let content_type = mime_guess::from_path(path).first_raw();
+                    let wanted = if !chunked
+                        && precompress::compressible(content_type, file_metadata.len())
+                    {
+                        &encodings[..]
+                    } else {
+                        &[]
+                    };
+                    let (url_safe_path, _) = generate_path_and_key(path, directory, None)?;
+                    let cached = cache
+                        .get(&url_safe_path, &file_metadata)
+                        .filter(|cached| cached.precompressed.encodings == wanted)
+                        .cloned();
+                    let (key, hash, variants, file_chunks) = match cached {
+                        Some(cached) => (
+                            cached.key,
+                            cached.hash,
+                            cached.precompressed.variants,
+                            cached.chunks,
+                        ),
+                        None if chunked => {
+                            let (file_chunks, hash) = chunks::split(path, directory, CHUNK_SIZE)?;
+                            // the file's own key is hashed from its hash, as it isn't read whole
+                            let (_, key) =
+                                generate_path_and_key(path, directory, Some(hash.clone()))?;
+                            let hash = Some(hash);
+                            cache.insert(
+                                url_safe_path.clone(),
+                                &file_metadata,
+                                key.clone(),
+                                hash.clone(),
+                                Precompressed::default(),
+                                file_chunks.clone(),
+                            );
+                            (key, hash, Vec::new(), file_chunks)
+                        }
+                        None => {
+                            let value = std::fs::read(path)?;
+                            let (_, key) = generate_path_and_key(
+                                path,
+                                directory,
+                                Some(base64::encode(&value)),
+                            )?;
+                            let hash = Some(format!("{:x}", Sha256::digest(&value)));
+                            let variants = compress_variants(path, &key, &value, wanted)?;
+                            let precompressed = Precompressed {
+                                encodings: wanted.to_vec(),
+                                variants: variants.clone(),
+                            };
+                            cache.insert(
+                                url_safe_path.clone(),
+                                &file_metadata,
+                                key.clone(),
+                                hash.clone(),
+                                precompressed,
+                                Vec::new(),
+                            );
+                            (key, hash, variants, Vec::new())
+                        }
+                    };
 
                     validate_key_size(&key)?;
 
-                    upload_vec.push(KeyValuePair {
-                        key: key.clone(),
-                        value: b64_value,
-                        expiration: None,
-                        expiration_ttl: None,
-                        base64: Some(true),
-                    });
-
-                    asset_manifest.insert(url_safe_path, key);
+                    let metadata = AssetMetadata {
+                        content_type: content_type.map(str::to_string),
+                        size: Some(file_metadata.len()),
+                        hash,
+                        cache_control: cache_control.get(&url_safe_path),
+                        content_encoding: None,
+                    };
+                    let mut entry = AssetEntry::new(key.clone(), metadata.clone());
+
+                    for variant in variants {
+                        validate_key_size(&variant.key)?;
+                        let name = variant.encoding.name().to_string();
+                        entry.encodings.insert(
+                            name.clone(),
+                            AssetVariant {
+                                key: variant.key.clone(),
+                                size: variant.size,
+                            },
+                        );
+                        files.push(SiteFile {
+                            path: path.to_path_buf(),
+                            key: variant.key,
+                            metadata: AssetMetadata {
+                                size: Some(variant.size),
+                                hash: Some(variant.hash),
+                                content_encoding: Some(name),
+                                ..metadata.clone()
+                            },
+                            encoding: Some(variant.encoding),
+                            chunk: None,
+                        });
+                    }
+
+                    if file_chunks.is_empty() {
+                        files.push(SiteFile {
+                            path: path.to_path_buf(),
+                            key,
+                            metadata,
+                            encoding: None,
+                            chunk: None,
+                        });
+                    }
+                    for (index, chunk) in file_chunks.into_iter().enumerate() {
+                        validate_key_size(&chunk.key)?;
+                        // chunks of the file with the same contents are only stored once
+                        let stored = entry.chunks.contains(&chunk.key);
+                        entry.chunks.push(chunk.key.clone());
+                        if stored {
+                            continue;
+                        }
+                        files.push(SiteFile {
+                            path: path.to_path_buf(),
+                            key: chunk.key,
+                            metadata: AssetMetadata {
+                                size: Some(chunk.size),
+                                hash: Some(chunk.hash),
+                                ..AssetMetadata::default()
+                            },
+                            encoding: None,
+                            chunk: Some(index),
+                        });
+                    }
+                    asset_manifest.insert(url_safe_path, entry);
                 }
             }
-            Ok((upload_vec, asset_manifest, file_list))
+            cache.retain_files(&asset_manifest);
+            Ok((files, asset_manifest, file_list))
         }
         Ok(_file_type) => {
             // any other file types (files, symlinks)
@@ -121,6 +329,95 @@ pub fn directory_keys_values(
     }
 }
 
+// Compresses a file in each of `encodings`, keeping the variants that come out smaller. Only
+// their keys, sizes and hashes are kept; they're compressed again when they're uploaded.
+fn compress_variants(
+    path: &Path,
+    key: &str,
+    value: &[u8],
+    encodings: &[Encoding],
+) -> Result<Vec<Variant>, failure::Error> {
+    let mut variants = Vec::new();
+    for &encoding in encodings {
+        let compressed = encoding.compress(value)?;
+        if compressed.len() >= value.len() {
+            continue;
+        }
+        validate_value_size(path, Some(encoding), compressed.len() as u64)?;
+        let variant = Variant {
+            encoding,
+            key: encoding.key(key),
+            size: compressed.len() as u64,
+            hash: format!("{:x}", Sha256::digest(&compressed)),
+        };
+        variants.push(variant);
+    }
+    Ok(variants)
+}
+
+// The `cache_control` rules of a [site], to match against the url-safe paths of its files.
+struct CacheControl {
+    rules: Vec<(GlobMatcher, String)>,
+}
+
+impl CacheControl {
+    fn new(site: Option<&Site>) -> Result<Self, failure::Error> {
+        let mut rules = Vec::new();
+        if let Some(site_rules) = site.and_then(|site| site.cache_control.as_ref()) {
+            for rule in site_rules {
+                let glob = match Glob::new(&rule.glob) {
+                    Ok(glob) => glob,
+                    Err(e) => failure::bail!("Invalid cache_control glob in [site]: {}", e),
+                };
+                rules.push((glob.compile_matcher(), rule.value.clone()));
+            }
+        }
+        Ok(CacheControl { rules })
+    }
+
+    fn get(&self, url_safe_path: &str) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|(glob, _)| glob.is_match(url_safe_path))
+            .map(|(_, value)| value.clone())
+    }
+}
+
+// Returns each file in a directory with its key: its url-safe path in the directory after
+// `prefix`, without the hash Workers Sites adds. Files are ignored like they are in a [site]
+// bucket without `include` or `exclude`. Used by `wrangler kv:bulk put --dir`.
+pub fn directory_keys(
+    directory: &Path,
+    prefix: &str,
+) -> Result<Vec<(PathBuf, String)>, failure::Error> {
+    match &fs::metadata(directory) {
+        Ok(file_type) if file_type.is_dir() => {
+            let mut ignore = OverrideBuilder::new(directory);
+            add_default_ignores(&mut ignore)?;
+            let dir_walker = WalkBuilder::new(directory)
+                .standard_filters(false)
+                .overrides(ignore.build()?)
+                .build();
+
+            let mut keys = Vec::new();
+            for entry in dir_walker {
+                let entry = entry?;
+                let path = entry.path();
+                if path.is_file() {
+                    validate_file_size(path)?;
+                    let (url_safe_path, _) = generate_path_and_key(path, directory, None)?;
+                    let key = format!("{}{}", prefix, url_safe_path);
+                    validate_key_size(&key)?;
+                    keys.push((path.to_path_buf(), key));
+                }
+            }
+            Ok(keys)
+        }
+        Ok(_) => Err(format_err!("{} is not a directory", directory.display())),
+        Err(e) => Err(format_err!("{}", e)),
+    }
+}
+
 // Ensure that all files in upload directory do not exceed the MAX_VALUE_SIZE (this ensures that
 // no partial uploads happen). I don't like this functionality (and the similar key length checking
 // logic in validate_key_size()) because it duplicates the size checking the API already does--but
@@ -128,13 +425,25 @@ pub fn directory_keys_values(
 // from happening.
 fn validate_file_size(path: &Path) -> Result<(), failure::Error> {
     let metadata = fs::metadata(path)?;
-    let file_len = metadata.len();
+    validate_value_size(path, None, metadata.len())
+}
 
-    if file_len > VALUE_MAX_SIZE {
+// Compressed variants of a file are stored as values of their own, so are held to the limit too.
+fn validate_value_size(
+    path: &Path,
+    encoding: Option<Encoding>,
+    len: u64,
+) -> Result<(), failure::Error> {
+    if len > VALUE_MAX_SIZE {
+        let variant = match encoding {
+            Some(encoding) => format!(" compressed with {}", encoding.name()),
+            None => String::new(),
+        };
         failure::bail!(
-            "File `{}` of {} bytes exceeds the maximum value size limit of {} bytes",
+            "File `{}`{} of {} bytes exceeds the maximum value size limit of {} bytes",
             path.display(),
-            file_len,
+            variant,
+            len,
             VALUE_MAX_SIZE
         );
     }
@@ -173,33 +482,16 @@ fn get_dir_iterator(target: &Target, directory: &Path) -> Result<Walk, failure::
 
 fn build_ignore(target: &Target, directory: &Path) -> Result<Override, failure::Error> {
     let mut required_override = OverrideBuilder::new(directory);
-    let required_ignore = |builder: &mut OverrideBuilder| -> Result<(), failure::Error> {
-        for ignored in REQUIRED_IGNORE_FILES {
-            builder.add(&format!("!{}", ignored))?;
-            log::info!("Ignoring {}", ignored);
-        }
-
-        Ok(())
-    };
     if let Some(site) = &target.site {
         // If `include` present, use it and don't touch the `exclude` field
         if let Some(included) = &site.include {
-            required_ignore(&mut required_override)?;
+            add_required_ignores(&mut required_override)?;
             for i in included {
                 required_override.add(&i)?;
                 log::info!("Including {}", i);
             }
         } else {
-            // allow all files. This is required since without this the `.well-known`
-            // override would act as a allowlist
-            required_override.add("*")?;
-            // ignore hidden files and folders
-            required_override.add("!.*")?;
-            // but allow .well-known, this has precedence over hidden files since it's later
-            required_override.add(".well-known")?;
-            // add this AFTER since the `*` override would have precedence over this,
-            // making it useless
-            required_ignore(&mut required_override)?;
+            add_default_ignores(&mut required_override)?;
 
             // add any other excludes specified
             if let Some(excluded) = &site.exclude {
@@ -215,6 +507,29 @@ fn build_ignore(target: &Target, directory: &Path) -> Result<Override, failure::
     Ok(exclude)
 }
 
+fn add_required_ignores(builder: &mut OverrideBuilder) -> Result<(), failure::Error> {
+    for ignored in REQUIRED_IGNORE_FILES {
+        builder.add(&format!("!{}", ignored))?;
+        log::info!("Ignoring {}", ignored);
+    }
+
+    Ok(())
+}
+
+// Ignores what a [site] without `include` or `exclude` does.
+fn add_default_ignores(builder: &mut OverrideBuilder) -> Result<(), failure::Error> {
+    // allow all files. This is required since without this the `.well-known`
+    // override would act as a allowlist
+    builder.add("*")?;
+    // ignore hidden files and folders
+    builder.add("!.*")?;
+    // but allow .well-known, this has precedence over hidden files since it's later
+    builder.add(".well-known")?;
+    // add this AFTER since the `*` override would have precedence over this,
+    // making it useless
+    add_required_ignores(builder)
+}
+
 // Courtesy of Steve Klabnik's PoC :) Used for bulk operations (write, delete)
 fn generate_url_safe_path(path: &Path) -> Result<String, failure::Error> {
     // first, we have to re-build the paths: if we're on Windows, we have paths with
@@ -305,10 +620,10 @@ mod tests {
     use super::*;
     use regex::Regex;
     use std::fs;
-    use std::io::Write;
+    use std::io::{Read, Write};
     use std::path::{Path, PathBuf};
 
-    use crate::settings::toml::{Site, Target, TargetType};
+    use crate::settings::toml::{CacheControlRule, Site, Target, TargetType};
 
     fn make_target(site: Site) -> Target {
         Target {
@@ -538,6 +853,148 @@ mod tests {
         fs::remove_dir_all(test_dir).unwrap();
     }
 
+    #[test]
+    fn it_describes_assets_in_their_metadata() {
+        let mut site = Site::default();
+        site.bucket = PathBuf::from("fake");
+        site.cache_control = Some(vec![
+            CacheControlRule {
+                glob: "*.html".to_string(),
+                value: "no-cache".to_string(),
+            },
+            CacheControlRule {
+                glob: "**".to_string(),
+                value: "max-age=60".to_string(),
+            },
+        ]);
+        let target = make_target(site);
+
+        let test_dir = "test9";
+        // If test dir already exists, delete it.
+        if fs::metadata(test_dir).is_ok() {
+            fs::remove_dir_all(test_dir).unwrap();
+        }
+
+        fs::create_dir(test_dir).unwrap();
+        fs::write(format!("{}/index.html", test_dir), "hello").unwrap();
+        fs::write(format!("{}/app.js", test_dir), "").unwrap();
+
+        let (pairs, asset_manifest, _) =
+            directory_keys_values(&target, Path::new(test_dir)).unwrap();
+        let index = &asset_manifest["index.html"];
+        assert_eq!(
+            index.metadata,
+            AssetMetadata {
+                content_type: Some("text/html".to_string()),
+                size: Some(5),
+                hash: Some(
+                    "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string()
+                ),
+                cache_control: Some("no-cache".to_string()),
+                content_encoding: None,
+            }
+        );
+        let pair = pairs.iter().find(|pair| pair.key == index.key).unwrap();
+        assert_eq!(
+            pair.metadata,
+            Some(serde_json::to_value(&index.metadata).unwrap())
+        );
+        assert_eq!(
+            asset_manifest["app.js"].metadata.cache_control,
+            Some("max-age=60".to_string())
+        );
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn it_precompresses_text_files() {
+        let mut site = Site::default();
+        site.bucket = PathBuf::from("fake");
+        site.precompress = Some(vec!["gzip".to_string(), "br".to_string()]);
+        let target = make_target(site);
+
+        let test_dir = "test10";
+        // If test dir already exists, delete it.
+        if fs::metadata(test_dir).is_ok() {
+            fs::remove_dir_all(test_dir).unwrap();
+        }
+
+        fs::create_dir(test_dir).unwrap();
+        let html = "<p>hello</p>".repeat(200);
+        fs::write(format!("{}/index.html", test_dir), &html).unwrap();
+        fs::write(format!("{}/small.css", test_dir), "p {}").unwrap();
+        fs::write(format!("{}/image.png", test_dir), vec![0; 4096]).unwrap();
+
+        let (pairs, asset_manifest, _) =
+            directory_keys_values(&target, Path::new(test_dir)).unwrap();
+        assert_eq!(pairs.len(), 5);
+        assert!(asset_manifest["small.css"].encodings.is_empty());
+        assert!(asset_manifest["image.png"].encodings.is_empty());
+
+        let index = &asset_manifest["index.html"];
+        let encodings: Vec<_> = index.encodings.keys().map(String::as_str).collect();
+        assert_eq!(encodings, vec!["br", "gzip"]);
+        let gzip = &index.encodings["gzip"];
+        assert_eq!(gzip.key, format!("{}.gz", index.key));
+
+        let pair = pairs.iter().find(|pair| pair.key == gzip.key).unwrap();
+        let metadata: AssetMetadata =
+            serde_json::from_value(pair.metadata.clone().unwrap()).unwrap();
+        assert_eq!(metadata.content_encoding, Some("gzip".to_string()));
+        assert_eq!(metadata.content_type, Some("text/html".to_string()));
+        let value = base64::decode(&pair.value).unwrap();
+        assert_eq!(metadata.size, Some(value.len() as u64));
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&value[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, html);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn it_reads_files_only_as_their_pairs_are_taken() {
+        let file = |key: &str| SiteFile {
+            path: PathBuf::from("test-missing").join(key),
+            key: key.to_string(),
+            metadata: AssetMetadata::default(),
+            encoding: None,
+            chunk: None,
+        };
+        let mut pairs = file_pairs(vec![file("a"), file("b")]);
+
+        // nothing has been read yet, so the missing files only fail once they're taken
+        assert!(pairs.next().unwrap().is_err());
+        assert!(pairs.next().unwrap().is_err());
+        assert!(pairs.next().is_none());
+    }
+
+    #[test]
+    fn it_keys_directory_files_by_path_without_hash() {
+        let test_dir = "test8";
+        // If test dir already exists, delete it.
+        if fs::metadata(test_dir).is_ok() {
+            fs::remove_dir_all(test_dir).unwrap();
+        }
+
+        fs::create_dir_all(format!("{}/sub", test_dir)).unwrap();
+        fs::File::create(format!("{}/index.html", test_dir)).unwrap();
+        fs::File::create(format!("{}/sub/a b.txt", test_dir)).unwrap();
+        fs::File::create(format!("{}/.ignore_me.txt", test_dir)).unwrap();
+
+        let mut keys: Vec<_> = directory_keys(Path::new(test_dir), "assets/")
+            .unwrap()
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["assets/index.html", "assets/sub/a b.txt"]);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
     #[test]
     fn it_inserts_hash_before_extension() {
         let value = "<h1>Hello World!</h1>";
@@ -0,0 +1,121 @@
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::toml::Site;
+
+/// Files smaller than this aren't worth compressing ahead of time.
+pub const PRECOMPRESS_MIN_SIZE: u64 = 1024;
+
+// Content types other than text/* that compress well.
+const TEXT_LIKE_TYPES: &[&str] = &[
+    "application/javascript",
+    "application/json",
+    "application/manifest+json",
+    "application/xml",
+    "application/xhtml+xml",
+    "image/svg+xml",
+];
+
+/// An encoding the `precompress` of a [site] can store variants of its files in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Encoding {
+    #[serde(rename = "br")]
+    Brotli,
+    #[serde(rename = "gzip")]
+    Gzip,
+}
+
+impl Encoding {
+    fn parse(name: &str) -> Result<Self, failure::Error> {
+        match name {
+            "br" => Ok(Encoding::Brotli),
+            "gzip" => Ok(Encoding::Gzip),
+            _ => failure::bail!(
+                "precompress in [site] should list \"br\" and/or \"gzip\", not \"{}\"",
+                name
+            ),
+        }
+    }
+
+    /// As it appears in `Accept-Encoding` and `Content-Encoding`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// The key a file stored under `key` has a variant in this encoding stored under.
+    pub fn key(self, key: &str) -> String {
+        let extension = match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        };
+        format!("{}.{}", key, extension)
+    }
+
+    pub fn compress(self, value: &[u8]) -> Result<Vec<u8>, failure::Error> {
+        match self {
+            Encoding::Brotli => {
+                let mut compressed = Vec::new();
+                let params = brotli::enc::BrotliEncoderParams::default();
+                brotli::BrotliCompress(&mut &value[..], &mut compressed, &params)?;
+                Ok(compressed)
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(value)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+}
+
+/// The encodings the `precompress` of a [site] asks for, in a fixed order.
+pub fn encodings(site: Option<&Site>) -> Result<Vec<Encoding>, failure::Error> {
+    let mut encodings = Vec::new();
+    if let Some(names) = site.and_then(|site| site.precompress.as_ref()) {
+        for name in names {
+            encodings.push(Encoding::parse(name)?);
+        }
+    }
+    encodings.sort();
+    encodings.dedup();
+    Ok(encodings)
+}
+
+/// Whether a file is text-like and big enough to store compressed variants of.
+pub fn compressible(content_type: Option<&str>, size: u64) -> bool {
+    let text_like = match content_type {
+        Some(content_type) => {
+            content_type.starts_with("text/")
+                || content_type.ends_with("+json")
+                || content_type.ends_with("+xml")
+                || TEXT_LIKE_TYPES.contains(&content_type)
+        }
+        None => false,
+    };
+    text_like && size >= PRECOMPRESS_MIN_SIZE
+}
+
+/// A compressed variant of a file, stored under its own key.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Variant {
+    pub encoding: Encoding,
+    pub key: String,
+    /// In bytes, compressed.
+    pub size: u64,
+    /// The hex encoded SHA-256 hash of the compressed file.
+    pub hash: String,
+}
+
+/// The variants of a file, and the encodings they were made for. Variants that came out no
+/// smaller than the file aren't kept.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Precompressed {
+    pub encodings: Vec<Encoding>,
+    pub variants: Vec<Variant>,
+}
@@ -1,53 +1,109 @@
 use std::collections::HashSet;
 use std::path::Path;
 
-use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
-
-use super::directory_keys_values;
+use super::cache::{SiteCache, SYNC_KEY};
 use super::manifest::AssetManifest;
+use super::versions::{Retention, SiteVersions, VERSIONS_KEY};
+use super::{directory_files, SiteFile};
 use crate::commands::kv;
-use crate::http;
 use crate::kv::key::KeyList;
+use crate::kv::store::ApiStore;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdErr};
 
+// Finds the files to upload to a site's namespace and the keys to delete from it, leaving
+// them to be read as they're uploaded with `sites::file_pairs`. Unchanged files aren't read
+// again, and the namespace isn't listed when nothing else has synced to it since the last
+// sync from here, unless `force_full_sync` is set.
+//
+// Callers should call `sites::finish_sync` once they've uploaded and deleted them.
 pub fn sync(
     target: &Target,
     user: &GlobalUser,
     namespace_id: &str,
     path: &Path,
-) -> Result<(Vec<KeyValuePair>, Vec<String>, AssetManifest), failure::Error> {
+    force_full_sync: bool,
+) -> Result<(Vec<SiteFile>, Vec<String>, AssetManifest), failure::Error> {
+    let cache = if force_full_sync {
+        SiteCache::empty(path)
+    } else {
+        SiteCache::open(path)
+    };
+    sync_with(target, user, namespace_id, path, cache, true)
+}
+
+// Finds what `sync` would upload and delete, without saving anything to the site's cache, for
+// `publish --dry-run`.
+pub fn plan_sync(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    path: &Path,
+) -> Result<(Vec<SiteFile>, Vec<String>, AssetManifest), failure::Error> {
+    sync_with(
+        target,
+        user,
+        namespace_id,
+        path,
+        SiteCache::open(path),
+        false,
+    )
+}
+
+fn sync_with(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    path: &Path,
+    mut cache: SiteCache,
+    persist: bool,
+) -> Result<(Vec<SiteFile>, Vec<String>, AssetManifest), failure::Error> {
     kv::validate_target(target)?;
     // First, find all changed files in given local directory (aka files that are now stale
     // in Workers KV).
+    let (files, asset_manifest, _): (Vec<SiteFile>, AssetManifest, _) =
+        directory_files(target, path, &mut cache)?;
 
     // Get remote keys, which contain the hash of the file (value) as the suffix.
     // Turn it into a HashSet. This will be used by upload() to figure out which
     // files to exclude from upload (because their current version already exists in
     // the Workers KV remote).
-    let client = http::cf_v4_client(&user)?;
-    let remote_keys_iter = KeyList::new(target, client, namespace_id, None)?;
+    let store = ApiStore::new(target, user)?;
     let mut remote_keys: HashSet<String> = HashSet::new();
-    for remote_key in remote_keys_iter {
-        match remote_key {
-            Ok(remote_key) => {
-                remote_keys.insert(remote_key.name);
+    match cache.synced_keys(&store, namespace_id)? {
+        Some(synced_keys) => {
+            log::info!("Using the keys cached since the last sync");
+            remote_keys.extend(synced_keys);
+        }
+        None => {
+            for remote_key in KeyList::new(&store, namespace_id, None) {
+                remote_keys.insert(remote_key?.name);
             }
-            Err(e) => failure::bail!(kv::format_error(e)),
         }
     }
-
-    let (pairs, asset_manifest, _): (Vec<KeyValuePair>, AssetManifest, _) =
-        directory_keys_values(target, path)?;
-
-    let to_upload = filter_files(pairs.clone(), &remote_keys);
+    // the index of published versions and the last sync aren't files
+    remote_keys.remove(VERSIONS_KEY);
+    remote_keys.remove(SYNC_KEY);
 
     // Now delete files from Workers KV that exist in remote but no longer exist locally.
     // Get local keys
     let mut local_keys: HashSet<_> = HashSet::new();
-    for pair in pairs.iter() {
-        local_keys.insert(pair.key.clone());
+    for file in files.iter() {
+        local_keys.insert(file.key.clone());
+    }
+
+    let to_upload = filter_files(files, &remote_keys);
+
+    // Keep the files of the versions published before this one that [site] asks to retain
+    if let Some(retention) = target
+        .site
+        .as_ref()
+        .map(Retention::new)
+        .transpose()?
+        .flatten()
+    {
+        local_keys.extend(SiteVersions::get(&store, namespace_id)?.retained_keys(&retention));
     }
 
     // Find keys that are present in remote but not present in local, and
@@ -57,26 +113,34 @@ pub fn sync(
         .map(|key| key.to_owned())
         .collect();
 
+    if persist {
+        // the keys the namespace will have once they're uploaded and deleted
+        let mut synced_keys: Vec<String> = remote_keys.intersection(&local_keys).cloned().collect();
+        synced_keys.extend(to_upload.iter().map(|file| file.key.clone()));
+        let changes = !to_upload.is_empty() || !to_delete.is_empty();
+        cache.start_sync(namespace_id, synced_keys, changes)?;
+    }
+
     StdErr::success("Success");
     Ok((to_upload, to_delete, asset_manifest))
 }
 
-fn filter_files(pairs: Vec<KeyValuePair>, already_uploaded: &HashSet<String>) -> Vec<KeyValuePair> {
-    let mut filtered_pairs: Vec<KeyValuePair> = Vec::new();
-    for pair in pairs {
-        if !already_uploaded.contains(&pair.key) {
-            filtered_pairs.push(pair);
+fn filter_files(files: Vec<SiteFile>, already_uploaded: &HashSet<String>) -> Vec<SiteFile> {
+    let mut filtered_files: Vec<SiteFile> = Vec::new();
+    for file in files {
+        if !already_uploaded.contains(&file.key) {
+            filtered_files.push(file);
         }
     }
-    filtered_pairs
+    filtered_files
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::sites::generate_path_and_key;
+    use crate::sites::{generate_path_and_key, AssetMetadata};
     use std::collections::HashSet;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn it_can_filter_preexisting_files() {
@@ -97,43 +161,26 @@ mod tests {
         exclude_keys.insert(key_b_old);
 
         // local files (with b updated) to upload
-        let pairs_to_upload = vec![
-            KeyValuePair {
+        let files = vec![
+            SiteFile {
+                path: PathBuf::from("/a"), // This file remains unchanged
                 key: key_a_old,
-                value: "old".to_string(), // This value remains unchanged
-                expiration_ttl: None,
-                expiration: None,
-                base64: None,
+                metadata: AssetMetadata::default(),
+                encoding: None,
+                chunk: None,
             },
-            KeyValuePair {
+            SiteFile {
+                path: PathBuf::from("/b"), // Note this file has a new value
                 key: key_b_new.clone(),
-                value: "new".to_string(), // Note this pair has a new value
-                expiration_ttl: None,
-                expiration: None,
-                base64: None,
+                metadata: AssetMetadata::default(),
+                encoding: None,
+                chunk: None,
             },
         ];
 
-        let expected = vec![KeyValuePair {
-            key: key_b_new,
-            value: "new".to_string(),
-            expiration_ttl: None,
-            expiration: None,
-            base64: None,
-        }];
-        let actual = filter_files(pairs_to_upload, &exclude_keys);
-        check_kv_pairs_equality(expected, actual);
-    }
-
-    fn check_kv_pairs_equality(expected: Vec<KeyValuePair>, actual: Vec<KeyValuePair>) {
-        assert_eq!(expected.len(), actual.len());
-        for (idx, pair) in expected.into_iter().enumerate() {
-            // Ensure the expected key and value was returned in the filtered pair list
-            // Awkward field-by-field comparison below courtesy of not yet implementing
-            // PartialEq for KeyValuePair in cloudflare-rs :)
-            // TODO: (gabbi) Implement PartialEq for KeyValuePair in cloudflare-rs.
-            assert_eq!(pair.key, actual[idx].key);
-            assert_eq!(pair.value, actual[idx].value);
-        }
+        let actual = filter_files(files, &exclude_keys);
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].path, PathBuf::from("/b"));
+        assert_eq!(actual[0].key, key_b_new);
     }
 }
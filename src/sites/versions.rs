@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::manifest::{AssetEntry, AssetManifest};
+use crate::kv::local::now;
+use crate::kv::store::{KvStore, PutOptions};
+use crate::settings::toml::Site;
+
+/// The key of the index of published versions in a site's namespace.
+pub const VERSIONS_KEY: &str = "__wrangler_site_versions";
+
+/// How many of the versions of a site published before to keep the files of, from the
+/// `retain_versions` and `retain_for` of its [site].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Retention {
+    /// Counting the version being published.
+    versions: usize,
+    /// Versions published this recently are kept however many came after them.
+    duration: Option<Duration>,
+}
+
+impl Retention {
+    /// The retention a [site] asks for, if it asks to keep any versions before the last.
+    pub fn new(site: &Site) -> Result<Option<Self>, failure::Error> {
+        if site.retain_versions.is_none() && site.retain_for.is_none() {
+            return Ok(None);
+        }
+
+        let versions = site.retain_versions.unwrap_or(1);
+        if versions == 0 {
+            failure::bail!("retain_versions in [site] should be at least 1");
+        }
+        let duration = match &site.retain_for {
+            Some(retain_for) => match humantime::parse_duration(retain_for) {
+                Ok(duration) => Some(duration),
+                Err(e) => failure::bail!(
+                    "retain_for in [site] should be a duration like \"24h\" or \"7days\": {}",
+                    e
+                ),
+            },
+            None => None,
+        };
+        Ok(Some(Retention { versions, duration }))
+    }
+
+    // Whether to keep a version, `newer` versions after the latest, published at `published`.
+    fn retains(&self, newer: usize, published: u64, now: u64) -> bool {
+        let recent = match self.duration {
+            Some(duration) => now.saturating_sub(published) <= duration.as_secs(),
+            None => false,
+        };
+        newer < self.versions || recent
+    }
+}
+
+/// The keys each published version of a site referenced, oldest first. Kept in the site's
+/// namespace under `VERSIONS_KEY`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SiteVersions {
+    versions: Vec<SiteVersion>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct SiteVersion {
+    /// Seconds since the Unix epoch.
+    published: u64,
+    keys: Vec<String>,
+}
+
+impl SiteVersions {
+    /// The versions recorded in a namespace, if any.
+    pub fn get(store: &dyn KvStore, namespace_id: &str) -> Result<Self, failure::Error> {
+        match store.get(namespace_id, VERSIONS_KEY)? {
+            Some(value) => match serde_json::from_slice(&value) {
+                Ok(versions) => Ok(versions),
+                Err(e) => failure::bail!("The {} of the site is invalid: {}", VERSIONS_KEY, e),
+            },
+            None => Ok(SiteVersions::default()),
+        }
+    }
+
+    pub fn put(&self, store: &dyn KvStore, namespace_id: &str) -> Result<(), failure::Error> {
+        store.put(
+            namespace_id,
+            VERSIONS_KEY,
+            serde_json::to_vec(self)?,
+            &PutOptions::default(),
+        )
+    }
+
+    /// The keys of the versions to keep alongside the one being published.
+    pub fn retained_keys(&self, retention: &Retention) -> HashSet<String> {
+        let now = now();
+        self.versions
+            .iter()
+            .rev()
+            .enumerate()
+            .filter(|(i, version)| retention.retains(i + 1, version.published, now))
+            .flat_map(|(_, version)| version.keys.iter().cloned())
+            .collect()
+    }
+
+    /// Whether every one of `keys` belongs to a recorded version, so is still in the namespace.
+    pub fn hold(&self, keys: &HashSet<String>) -> bool {
+        keys.iter().all(|key| {
+            self.versions
+                .iter()
+                .any(|version| version.keys.binary_search(key).is_ok())
+        })
+    }
+
+    /// Records a published version, forgetting those no longer retained.
+    pub fn record(&mut self, asset_manifest: &AssetManifest, retention: &Retention) {
+        let now = now();
+        let mut keys: Vec<String> = asset_manifest
+            .values()
+            .flat_map(AssetEntry::keys)
+            .cloned()
+            .collect();
+        keys.sort();
+        self.versions.push(SiteVersion {
+            published: now,
+            keys,
+        });
+
+        let len = self.versions.len();
+        let mut newer = len;
+        self.versions.retain(|version| {
+            newer -= 1;
+            retention.retains(newer, version.published, now)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sites::AssetMetadata;
+
+    fn manifest(keys: &[&str]) -> AssetManifest {
+        keys.iter()
+            .map(|key| {
+                (
+                    key.to_string(),
+                    AssetEntry::new(key.to_string(), AssetMetadata::default()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_retains_the_keys_of_the_last_versions() {
+        let retention = Retention {
+            versions: 2,
+            duration: None,
+        };
+        let mut versions = SiteVersions::default();
+        versions.record(&manifest(&["a"]), &retention);
+        versions.record(&manifest(&["b"]), &retention);
+        versions.record(&manifest(&["c", "b"]), &retention);
+
+        // the next version published is one of the two retained
+        let retained = versions.retained_keys(&retention);
+        assert_eq!(
+            retained,
+            vec!["b", "c"].into_iter().map(String::from).collect()
+        );
+        assert_eq!(versions.versions.len(), 2);
+
+        let retention = Retention {
+            versions: 1,
+            duration: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(versions.retained_keys(&retention).len(), 2);
+    }
+
+    #[test]
+    fn it_holds_only_the_keys_of_recorded_versions() {
+        let retention = Retention {
+            versions: 2,
+            duration: None,
+        };
+        let mut versions = SiteVersions::default();
+        versions.record(&manifest(&["a"]), &retention);
+        versions.record(&manifest(&["b", "c"]), &retention);
+        versions.record(&manifest(&["c", "d"]), &retention);
+
+        let keys =
+            |keys: &[&str]| -> HashSet<String> { keys.iter().map(|key| key.to_string()).collect() };
+        assert!(versions.hold(&keys(&["b", "d"])));
+        assert!(!versions.hold(&keys(&["a"])));
+    }
+}
@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use crate::settings::binding::Binding;
+use crate::settings::toml::Target;
+use crate::sites::{manifest_bindings, AssetManifest};
+
+use super::form::{self, PartContent, SessionConfig, UploadForm};
+
+const METADATA_PART: &str = "metadata";
+
+/// Writes a built worker to `dir` as a build artifact: every part of its upload form, with a
+/// `form.json` listing them and a `metadata` part holding the bindings to those parts.
+///
+/// KV namespaces, `[vars]` and the site asset manifest differ between environments, so they
+/// are left out of the artifact and added by `read` when it is published.
+pub fn write(target: &Target, dir: &Path) -> Result<(), failure::Error> {
+    let mut code_only = target.clone();
+    code_only.kv_namespaces = Vec::new();
+    code_only.vars = None;
+
+    let form = form::build_upload_form(&code_only, None, &SessionConfig::default())?;
+    form.write(dir)
+}
+
+/// Reads a build artifact from `dir`, binding it to `target`'s KV namespaces and `[vars]`.
+pub fn read(
+    dir: &Path,
+    target: &Target,
+    asset_manifest: Option<AssetManifest>,
+) -> Result<UploadForm, failure::Error> {
+    let mut form = UploadForm::read(dir)?;
+
+    let metadata_part = match form.part_mut(METADATA_PART) {
+        Some(part) => part,
+        None => failure::bail!(
+            "{} is not a build artifact: it has no {} part",
+            dir.display(),
+            METADATA_PART
+        ),
+    };
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(&metadata_part.content.read_to_string()?)?;
+    let is_service_worker = metadata.get("body_part").is_some();
+
+    let mut bindings: Vec<Binding> = target.kv_namespaces.iter().map(|kv| kv.binding()).collect();
+    if let Some(vars) = &target.vars {
+        for (key, value) in vars.iter() {
+            bindings.push(Binding::new_plain_text(key.clone(), value.clone()));
+        }
+    }
+    // like `form::build`, only service-worker scripts can bind the site's asset manifest
+    let asset_manifest_blobs = match asset_manifest {
+        Some(asset_manifest) if is_service_worker => manifest_bindings(&asset_manifest)?,
+        _ => Vec::new(),
+    };
+    for (binding, _) in &asset_manifest_blobs {
+        bindings.push(Binding::new_text_blob(
+            binding.to_string(),
+            binding.to_string(),
+        ));
+    }
+
+    match metadata.get_mut("bindings").and_then(|b| b.as_array_mut()) {
+        Some(existing) => {
+            for binding in bindings {
+                existing.push(serde_json::to_value(binding)?);
+            }
+        }
+        None => failure::bail!(
+            "the {} part of {} has no bindings",
+            METADATA_PART,
+            dir.display()
+        ),
+    }
+    metadata_part.content = PartContent::Text(metadata.to_string());
+
+    for (binding, asset_manifest_blob) in asset_manifest_blobs {
+        form.text(binding, binding, "text/plain", asset_manifest_blob);
+    }
+
+    Ok(form)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    use crate::settings::toml::KvNamespace;
+    use crate::sites::{AssetEntry, AssetMetadata, MANIFEST_BINDING, MANIFEST_V2_BINDING};
+
+    #[test]
+    fn it_binds_an_artifact_to_the_environment_publishing_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("index.js");
+        fs::write(&script_path, "addEventListener('fetch', () => {})").unwrap();
+
+        let mut artifact = UploadForm::default();
+        artifact.text(
+            METADATA_PART,
+            "metadata.json",
+            "application/json",
+            r#"{"body_part":"index","bindings":[]}"#.to_string(),
+        );
+        artifact.file("index", "index.js", None, script_path);
+        let artifact_dir = dir.path().join("artifact");
+        artifact.write(&artifact_dir).unwrap();
+
+        let mut vars = HashMap::new();
+        vars.insert("STAGE".to_string(), "staging".to_string());
+        let target = Target {
+            kv_namespaces: vec![KvNamespace {
+                id: "staging-id".to_string(),
+                binding: "CACHE".to_string(),
+            }],
+            vars: Some(vars),
+            ..Target::default()
+        };
+        let mut asset_manifest = AssetManifest::new();
+        asset_manifest.insert(
+            "index.html".to_string(),
+            AssetEntry::new("index.abc123.html".to_string(), AssetMetadata::default()),
+        );
+
+        let mut form = read(&artifact_dir, &target, Some(asset_manifest)).unwrap();
+
+        let names: Vec<&str> = form.parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["metadata", "index", MANIFEST_BINDING, MANIFEST_V2_BINDING]
+        );
+
+        let metadata = form.part_mut(METADATA_PART).unwrap();
+        let metadata: serde_json::Value =
+            serde_json::from_str(&metadata.content.read_to_string().unwrap()).unwrap();
+        assert_eq!(
+            metadata["bindings"],
+            serde_json::json!([
+                { "type": "kv_namespace", "name": "CACHE", "namespace_id": "staging-id" },
+                { "type": "plain_text", "name": "STAGE", "text": "staging" },
+                { "type": "text_blob", "name": MANIFEST_BINDING, "part": MANIFEST_BINDING },
+                { "type": "text_blob", "name": MANIFEST_V2_BINDING, "part": MANIFEST_V2_BINDING },
+            ])
+        );
+    }
+}
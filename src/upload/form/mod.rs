@@ -1,11 +1,15 @@
 mod modules_worker;
 mod plain_text;
 mod project_assets;
+mod secret_text;
 mod service_worker;
+mod test_scheduled;
 mod text_blob;
+mod upload_form;
 mod wasm_module;
 
 use reqwest::blocking::multipart::Form;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -15,28 +19,54 @@ use ignore::WalkBuilder;
 
 use crate::settings::binding;
 use crate::settings::toml::{Builder, ScriptFormat, Target, TargetType};
-use crate::sites::AssetManifest;
+use crate::sites::{manifest_bindings, AssetManifest};
 use crate::wranglerjs;
 
 use plain_text::PlainText;
 use project_assets::{ModulesAssets, ServiceWorkerAssets};
+use secret_text::SecretText;
 use text_blob::TextBlob;
 use wasm_module::WasmModule;
 
+pub use test_scheduled::SCHEDULED_PATH;
+pub use upload_form::{FormPart, PartContent, UploadForm};
+
 // TODO: https://github.com/cloudflare/wrangler/issues/1083
 use super::{krate, Package};
 
 use self::project_assets::Module;
 
+/// What a `wrangler dev` or `wrangler preview` session adds to a target's upload form.
+/// Publishing uses the default, which adds nothing.
+#[derive(Clone, Debug, Default)]
+pub struct SessionConfig {
+    /// The `wrangler dev` session's routes, sent as a part of the form.
+    pub config: Option<serde_json::Value>,
+    /// Values from `.dev.vars`, bound as secrets.
+    pub dev_vars: Option<HashMap<String, String>>,
+    /// Whether to add a route that runs the worker's scheduled handler.
+    pub test_scheduled: bool,
+}
+
 pub fn build(
     target: &Target,
     asset_manifest: Option<AssetManifest>,
-    session_config: Option<serde_json::Value>,
+    session_config: &SessionConfig,
 ) -> Result<Form, failure::Error> {
+    build_upload_form(target, asset_manifest, session_config)?.to_form()
+}
+
+/// Collects everything that would be uploaded for `target`, without sending it.
+pub fn build_upload_form(
+    target: &Target,
+    asset_manifest: Option<AssetManifest>,
+    session_config: &SessionConfig,
+) -> Result<UploadForm, failure::Error> {
     let target_type = &target.target_type;
     let kv_namespaces = &target.kv_namespaces;
     let mut text_blobs: Vec<TextBlob> = Vec::new();
     let mut plain_texts: Vec<PlainText> = Vec::new();
+    let mut secret_texts: Vec<SecretText> = Vec::new();
     let mut wasm_modules: Vec<WasmModule> = Vec::new();
 
     if let Some(blobs) = &target.text_blobs {
@@ -46,13 +76,23 @@ pub fn build(
         }
     }
 
+    // dev vars are bound as secrets, and replace any [vars] of the same name
+    let dev_vars = session_config.dev_vars.as_ref();
     if let Some(vars) = &target.vars {
         for (key, value) in vars.iter() {
-            plain_texts.push(PlainText::new(key.clone(), value.clone())?)
+            if !matches!(dev_vars, Some(dev_vars) if dev_vars.contains_key(key)) {
+                plain_texts.push(PlainText::new(key.clone(), value.clone())?)
+            }
+        }
+    }
+
+    if let Some(dev_vars) = dev_vars {
+        for (key, value) in dev_vars.iter() {
+            secret_texts.push(SecretText::new(key.clone(), value.clone())?)
         }
     }
 
-    match target_type {
+    let mut form = match target_type {
         TargetType::Rust => {
             log::info!("Rust project detected. Publishing...");
             let name = krate::Krate::new("./")?.name.replace("-", "_");
@@ -72,9 +112,10 @@ pub fn build(
                 kv_namespaces.to_vec(),
                 text_blobs,
                 plain_texts,
+                secret_texts,
             )?;
 
-            service_worker::build_form(&assets, session_config)
+            service_worker::build_form(&assets, session_config.config.clone())
         }
         TargetType::JavaScript => match &target.build {
             Some(config) => match &config.upload_format {
@@ -90,9 +131,10 @@ pub fn build(
                         kv_namespaces.to_vec(),
                         text_blobs,
                         plain_texts,
+                        secret_texts,
                     )?;
 
-                    service_worker::build_form(&assets, session_config)
+                    service_worker::build_form(&assets, session_config.config.clone())
                 }
                 ScriptFormat::Modules => {
                     let package_dir = target.package_dir()?;
@@ -124,9 +166,10 @@ pub fn build(
                         modules,
                         kv_namespaces.to_vec(),
                         plain_texts,
+                        secret_texts,
                     )?;
 
-                    modules_worker::build_form(&assets, session_config)
+                    modules_worker::build_form(&assets, session_config.config.clone())
                 }
             },
             None => {
@@ -141,9 +184,10 @@ pub fn build(
                     kv_namespaces.to_vec(),
                     text_blobs,
                     plain_texts,
+                    secret_texts,
                 )?;
 
-                service_worker::build_form(&assets, session_config)
+                service_worker::build_form(&assets, session_config.config.clone())
             }
         },
         TargetType::Webpack => {
@@ -162,11 +206,11 @@ pub fn build(
             }
 
             if let Some(asset_manifest) = asset_manifest {
-                log::info!("adding __STATIC_CONTENT_MANIFEST");
-                let binding = "__STATIC_CONTENT_MANIFEST".to_string();
-                let asset_manifest_blob = get_asset_manifest_blob(asset_manifest)?;
-                let text_blob = TextBlob::new(asset_manifest_blob, binding)?;
-                text_blobs.push(text_blob);
+                for (binding, asset_manifest_blob) in manifest_bindings(&asset_manifest)? {
+                    log::info!("adding {}", binding);
+                    let text_blob = TextBlob::new(asset_manifest_blob, binding.to_string())?;
+                    text_blobs.push(text_blob);
+                }
             }
 
             let assets = ServiceWorkerAssets::new(
@@ -175,16 +219,18 @@ pub fn build(
                 kv_namespaces.to_vec(),
                 text_blobs,
                 plain_texts,
+                secret_texts,
             )?;
 
-            service_worker::build_form(&assets, session_config)
+            service_worker::build_form(&assets, session_config.config.clone())
         }
+    }?;
+
+    if session_config.test_scheduled {
+        test_scheduled::inject(&mut form)?;
     }
-}
 
-fn get_asset_manifest_blob(asset_manifest: AssetManifest) -> Result<String, failure::Error> {
-    let asset_manifest = serde_json::to_string(&asset_manifest)?;
-    Ok(asset_manifest)
+    Ok(form)
 }
 
 fn filestem_from_path(path: &PathBuf) -> Option<String> {
@@ -4,6 +4,7 @@ use failure::format_err;
 
 use super::binding::Binding;
 use super::plain_text::PlainText;
+use super::secret_text::SecretText;
 use super::text_blob::TextBlob;
 use super::wasm_module::WasmModule;
 use super::{filename_from_path, filestem_from_path};
@@ -18,6 +19,7 @@ pub struct ServiceWorkerAssets {
     pub kv_namespaces: Vec<KvNamespace>,
     pub text_blobs: Vec<TextBlob>,
     pub plain_texts: Vec<PlainText>,
+    pub secret_texts: Vec<SecretText>,
 }
 
 impl ServiceWorkerAssets {
@@ -27,6 +29,7 @@ impl ServiceWorkerAssets {
         kv_namespaces: Vec<KvNamespace>,
         text_blobs: Vec<TextBlob>,
         plain_texts: Vec<PlainText>,
+        secret_texts: Vec<SecretText>,
     ) -> Result<Self, failure::Error> {
         let script_name = filestem_from_path(&script_path).ok_or_else(|| {
             format_err!("filename should not be empty: {}", script_path.display())
@@ -39,6 +42,7 @@ impl ServiceWorkerAssets {
             kv_namespaces,
             text_blobs,
             plain_texts,
+            secret_texts,
         })
     }
 
@@ -61,6 +65,10 @@ impl ServiceWorkerAssets {
             let binding = plain_text.binding();
             bindings.push(binding);
         }
+        for secret_text in &self.secret_texts {
+            let binding = secret_text.binding();
+            bindings.push(binding);
+        }
 
         bindings
     }
@@ -132,6 +140,7 @@ pub struct ModulesAssets {
     pub modules: Vec<Module>,
     pub kv_namespaces: Vec<KvNamespace>,
     pub plain_texts: Vec<PlainText>,
+    pub secret_texts: Vec<SecretText>,
 }
 
 impl ModulesAssets {
@@ -140,12 +149,14 @@ impl ModulesAssets {
         modules: Vec<Module>,
         kv_namespaces: Vec<KvNamespace>,
         plain_texts: Vec<PlainText>,
+        secret_texts: Vec<SecretText>,
     ) -> Result<Self, failure::Error> {
         Ok(Self {
             main_module,
             modules,
             kv_namespaces,
             plain_texts,
+            secret_texts,
         })
     }
 
@@ -163,6 +174,10 @@ impl ModulesAssets {
             let binding = plain_text.binding();
             bindings.push(binding);
         }
+        for secret_text in &self.secret_texts {
+            let binding = secret_text.binding();
+            bindings.push(binding);
+        }
 
         bindings
     }
@@ -0,0 +1,18 @@
+use super::binding::Binding;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SecretText {
+    pub name: String,
+    pub value: String,
+}
+
+impl SecretText {
+    pub fn new(name: String, value: String) -> Result<Self, failure::Error> {
+        Ok(Self { name, value })
+    }
+
+    pub fn binding(&self) -> Binding {
+        Binding::new_secret_text(self.name.clone(), self.value.clone())
+    }
+}
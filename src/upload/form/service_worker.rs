@@ -1,9 +1,8 @@
-use reqwest::blocking::multipart::{Form, Part};
 use serde::Serialize;
 
 use crate::settings::binding::Binding;
 
-use super::ServiceWorkerAssets;
+use super::{filename_from_path, ServiceWorkerAssets, UploadForm};
 
 #[derive(Serialize, Debug)]
 struct Metadata {
@@ -14,65 +13,76 @@ struct Metadata {
 pub fn build_form(
     assets: &ServiceWorkerAssets,
     session_config: Option<serde_json::Value>,
-) -> Result<Form, failure::Error> {
-    let mut form = Form::new();
+) -> Result<UploadForm, failure::Error> {
+    let mut form = UploadForm::default();
 
     // The preview service in particular streams the request form, and requires that the
     // "metadata" part be set first, so this order is important.
-    form = add_metadata(form, assets)?;
-    form = add_files(form, assets)?;
+    add_metadata(&mut form, assets)?;
+    add_files(&mut form, assets)?;
     if let Some(session_config) = session_config {
-        form = add_session_config(form, session_config)?
+        add_session_config(&mut form, session_config)
     }
 
-    log::info!("building form");
-    log::info!("{:#?}", &form);
-
     Ok(form)
 }
 
-fn add_files(mut form: Form, assets: &ServiceWorkerAssets) -> Result<Form, failure::Error> {
-    form = form.file(assets.script_name(), assets.script_path())?;
+fn add_files(form: &mut UploadForm, assets: &ServiceWorkerAssets) -> Result<(), failure::Error> {
+    let script_path = assets.script_path();
+    let file_name = filename_from_path(&script_path)
+        .ok_or_else(|| failure::err_msg("a filename is required for the script"))?;
+    form.file(
+        &assets.script_name(),
+        &file_name,
+        Some("application/javascript"),
+        script_path,
+    );
 
     for wasm_module in &assets.wasm_modules {
-        form = form.file(wasm_module.filename(), wasm_module.path())?;
+        let path = wasm_module.path();
+        let file_name = filename_from_path(&path)
+            .ok_or_else(|| failure::err_msg("a filename is required for each wasm module"))?;
+        form.file(
+            &wasm_module.filename(),
+            &file_name,
+            Some("application/wasm"),
+            path,
+        );
     }
 
     for text_blob in &assets.text_blobs {
-        let part = Part::text(text_blob.data.clone())
-            .file_name(text_blob.binding.clone())
-            .mime_str("text/plain")?;
-
-        form = form.part(text_blob.binding.clone(), part);
+        form.text(
+            &text_blob.binding,
+            &text_blob.binding,
+            "text/plain",
+            text_blob.data.clone(),
+        );
     }
 
-    Ok(form)
+    Ok(())
 }
 
-fn add_metadata(mut form: Form, assets: &ServiceWorkerAssets) -> Result<Form, failure::Error> {
+fn add_metadata(form: &mut UploadForm, assets: &ServiceWorkerAssets) -> Result<(), failure::Error> {
     let metadata_json = serde_json::json!(&Metadata {
         body_part: assets.script_name(),
         bindings: assets.bindings(),
     });
 
-    let metadata = Part::text(metadata_json.to_string())
-        .file_name("metadata.json")
-        .mime_str("application/json")?;
-
-    form = form.part("metadata", metadata);
+    form.text(
+        "metadata",
+        "metadata.json",
+        "application/json",
+        metadata_json.to_string(),
+    );
 
-    Ok(form)
+    Ok(())
 }
 
-fn add_session_config(
-    mut form: Form,
-    session_config: serde_json::Value,
-) -> Result<Form, failure::Error> {
-    let wrangler_session_config = Part::text(session_config.to_string())
-        .file_name("")
-        .mime_str("application/json")?;
-
-    form = form.part("wrangler-session-config", wrangler_session_config);
-
-    Ok(form)
+fn add_session_config(form: &mut UploadForm, session_config: serde_json::Value) {
+    form.text(
+        "wrangler-session-config",
+        "",
+        "application/json",
+        session_config.to_string(),
+    );
 }
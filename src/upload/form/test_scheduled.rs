@@ -0,0 +1,204 @@
+use super::{PartContent, UploadForm};
+
+/// The path that runs a worker's scheduled handler when `wrangler dev --test-scheduled` is used.
+pub const SCHEDULED_PATH: &str = "/__scheduled";
+
+const METADATA_PART: &str = "metadata";
+const SESSION_CONFIG_PART: &str = "wrangler-session-config";
+const SHIM_MODULE: &str = "__wrangler_test_scheduled.mjs";
+
+// Prepended to service-worker scripts. It registers its own fetch listener before the script's
+// so it sees requests for the scheduled path first, and collects the script's scheduled
+// listeners to run them.
+const SERVICE_WORKER_SHIM: &str = r#"// added by `wrangler dev --test-scheduled`
+(function () {
+  const scheduledListeners = [];
+  const addEventListener = globalThis.addEventListener;
+  globalThis.addEventListener = function (type, listener, options) {
+    if (type === "scheduled") {
+      scheduledListeners.push(listener);
+    }
+    return addEventListener.call(this, type, listener, options);
+  };
+  addEventListener.call(globalThis, "fetch", (event) => {
+    const url = new URL(event.request.url);
+    if (url.pathname !== "__SCHEDULED_PATH__") {
+      return;
+    }
+    const cron = url.searchParams.get("cron") || "";
+    const pending = [];
+    const scheduledEvent = {
+      type: "scheduled",
+      cron,
+      scheduledTime: Date.now(),
+      waitUntil: (promise) => pending.push(promise),
+    };
+    event.respondWith(
+      (async () => {
+        try {
+          for (const listener of scheduledListeners) {
+            listener(scheduledEvent);
+          }
+          await Promise.all(pending);
+          return new Response(`Ran scheduled event "${cron}"\n`);
+        } catch (e) {
+          return new Response(`Scheduled event "${cron}" failed: ${e && e.stack || e}\n`, { status: 500 });
+        }
+      })()
+    );
+  });
+})();
+"#;
+
+// Becomes the main module of modules workers, wrapping the real one's default export.
+const MODULES_SHIM: &str = r#"// added by `wrangler dev --test-scheduled`
+import worker from "./__MAIN_MODULE__";
+export * from "./__MAIN_MODULE__";
+
+export default {
+  ...worker,
+  async fetch(request, env, ctx) {
+    const url = new URL(request.url);
+    if (url.pathname !== "__SCHEDULED_PATH__") {
+      return worker.fetch(request, env, ctx);
+    }
+    const cron = url.searchParams.get("cron") || "";
+    if (typeof worker.scheduled !== "function") {
+      return new Response("This worker has no scheduled handler\n", { status: 404 });
+    }
+    try {
+      await worker.scheduled({ cron, scheduledTime: Date.now(), noRetry() {} }, env, ctx);
+      return new Response(`Ran scheduled event "${cron}"\n`);
+    } catch (e) {
+      return new Response(`Scheduled event "${cron}" failed: ${e && e.stack || e}\n`, { status: 500 });
+    }
+  },
+};
+"#;
+
+/// Makes a worker's scheduled handler run when it receives a request for `SCHEDULED_PATH`,
+/// with the cron expression in the `cron` query parameter.
+pub fn inject(form: &mut UploadForm) -> Result<(), failure::Error> {
+    let metadata_part = match form.part_mut(METADATA_PART) {
+        Some(part) => part,
+        None => failure::bail!("the upload form has no {} part", METADATA_PART),
+    };
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(&metadata_part.content.read_to_string()?)?;
+
+    if let Some(main_module) = metadata["main_module"].as_str().map(str::to_string) {
+        metadata["main_module"] = SHIM_MODULE.into();
+        metadata_part.content = PartContent::Text(metadata.to_string());
+
+        let shim = MODULES_SHIM
+            .replace("__MAIN_MODULE__", &main_module)
+            .replace("__SCHEDULED_PATH__", SCHEDULED_PATH);
+        form.text(
+            SHIM_MODULE,
+            SHIM_MODULE,
+            "application/javascript+module",
+            shim,
+        );
+        // keep the session config last, after every module
+        if let Some(i) = form
+            .parts
+            .iter()
+            .position(|p| p.name == SESSION_CONFIG_PART)
+        {
+            let session_config = form.parts.remove(i);
+            form.parts.push(session_config);
+        }
+    } else if let Some(body_part) = metadata["body_part"].as_str() {
+        let script_part = match form.part_mut(body_part) {
+            Some(part) => part,
+            None => failure::bail!("the upload form has no {} part", body_part),
+        };
+        let script = script_part.content.read_to_string()?;
+        let shim = SERVICE_WORKER_SHIM.replace("__SCHEDULED_PATH__", SCHEDULED_PATH);
+        script_part.content = PartContent::Text(format!("{}{}", shim, script));
+        if script_part.content_type.is_none() {
+            script_part.content_type = Some("application/javascript".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(form: &UploadForm) -> serde_json::Value {
+        let part = form.part(METADATA_PART).unwrap();
+        serde_json::from_str(&part.content.read_to_string().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_wraps_the_main_module() {
+        let mut form = UploadForm::default();
+        form.text(
+            METADATA_PART,
+            "metadata.json",
+            "application/json",
+            r#"{"main_module":"index.mjs","bindings":[]}"#.to_string(),
+        );
+        form.text(
+            "index.mjs",
+            "index.mjs",
+            "application/javascript+module",
+            "export default {}".to_string(),
+        );
+        form.text(
+            SESSION_CONFIG_PART,
+            "",
+            "application/json",
+            "{}".to_string(),
+        );
+
+        inject(&mut form).unwrap();
+
+        assert_eq!(metadata(&form)["main_module"], SHIM_MODULE);
+        let names: Vec<&str> = form.parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![METADATA_PART, "index.mjs", SHIM_MODULE, SESSION_CONFIG_PART]
+        );
+        let shim = form
+            .part(SHIM_MODULE)
+            .unwrap()
+            .content
+            .read_to_string()
+            .unwrap();
+        assert!(shim.contains(r#"import worker from "./index.mjs";"#));
+        assert!(shim.contains(SCHEDULED_PATH));
+    }
+
+    #[test]
+    fn it_prepends_to_service_worker_scripts() {
+        let mut form = UploadForm::default();
+        form.text(
+            METADATA_PART,
+            "metadata.json",
+            "application/json",
+            r#"{"body_part":"script","bindings":[]}"#.to_string(),
+        );
+        form.text(
+            "script",
+            "index.js",
+            "application/javascript",
+            "addEventListener('scheduled', () => {})".to_string(),
+        );
+
+        inject(&mut form).unwrap();
+
+        assert_eq!(metadata(&form)["body_part"], "script");
+        let script = form
+            .part("script")
+            .unwrap()
+            .content
+            .read_to_string()
+            .unwrap();
+        assert!(script.starts_with("// added by `wrangler dev --test-scheduled`"));
+        assert!(script.ends_with("addEventListener('scheduled', () => {})"));
+    }
+}
@@ -0,0 +1,237 @@
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use reqwest::blocking::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
+
+const FORM_LISTING: &str = "form.json";
+
+/// A script upload form that hasn't been sent yet, so that it can also be written to disk.
+#[derive(Debug, Default)]
+pub struct UploadForm {
+    pub parts: Vec<FormPart>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FormPart {
+    pub name: String,
+    pub file_name: String,
+    /// When unset, the content type is guessed from the extension of `file_name`.
+    pub content_type: Option<String>,
+    #[serde(skip)]
+    pub content: PartContent,
+}
+
+#[derive(Debug)]
+pub enum PartContent {
+    Text(String),
+    File(PathBuf),
+}
+
+impl PartContent {
+    pub fn read_to_string(&self) -> Result<String, failure::Error> {
+        match self {
+            PartContent::Text(text) => Ok(text.clone()),
+            PartContent::File(path) => Ok(fs::read_to_string(path)?),
+        }
+    }
+}
+
+impl Default for PartContent {
+    fn default() -> Self {
+        PartContent::Text(String::new())
+    }
+}
+
+impl FormPart {
+    /// The content type the part is sent with.
+    pub fn resolved_content_type(&self) -> Option<String> {
+        self.content_type.clone().or_else(|| {
+            mime_guess::from_path(&self.file_name)
+                .first_raw()
+                .map(str::to_string)
+        })
+    }
+}
+
+impl UploadForm {
+    pub fn text(&mut self, name: &str, file_name: &str, content_type: &str, text: String) {
+        self.parts.push(FormPart {
+            name: name.to_string(),
+            file_name: file_name.to_string(),
+            content_type: Some(content_type.to_string()),
+            content: PartContent::Text(text),
+        });
+    }
+
+    pub fn file(&mut self, name: &str, file_name: &str, content_type: Option<&str>, path: PathBuf) {
+        self.parts.push(FormPart {
+            name: name.to_string(),
+            file_name: file_name.to_string(),
+            content_type: content_type.map(str::to_string),
+            content: PartContent::File(path),
+        });
+    }
+
+    pub fn part(&self, name: &str) -> Option<&FormPart> {
+        self.parts.iter().find(|part| part.name == name)
+    }
+
+    pub fn part_mut(&mut self, name: &str) -> Option<&mut FormPart> {
+        self.parts.iter_mut().find(|part| part.name == name)
+    }
+
+    pub fn to_form(&self) -> Result<Form, failure::Error> {
+        let mut form = Form::new();
+        for form_part in &self.parts {
+            let mut part = match &form_part.content {
+                PartContent::Text(text) => Part::text(text.clone()),
+                PartContent::File(path) => Part::file(path)?,
+            };
+            if let Some(content_type) = form_part.resolved_content_type() {
+                part = part.mime_str(&content_type)?;
+            }
+            part = part.file_name(form_part.file_name.clone());
+            form = form.part(form_part.name.clone(), part);
+        }
+
+        log::info!("building form");
+        log::info!("{:#?}", &form);
+
+        Ok(form)
+    }
+
+    /// A hash of every part of the form, for telling uploads apart.
+    pub fn content_hash(&self) -> Result<String, failure::Error> {
+        let mut hasher = XxHash64::default();
+        for part in &self.parts {
+            hasher.write(part.name.as_bytes());
+            hasher.write(part.file_name.as_bytes());
+            hasher.write(part.content_type.as_deref().unwrap_or_default().as_bytes());
+            match &part.content {
+                PartContent::Text(text) => hasher.write(text.as_bytes()),
+                PartContent::File(path) => hasher.write(&fs::read(path)?),
+            }
+        }
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Writes each part to a file in `dir` named after the part, along with a `form.json`
+    /// listing the parts in the order they would be sent.
+    pub fn write(&self, dir: &Path) -> Result<(), failure::Error> {
+        fs::create_dir_all(dir)?;
+        for part in &self.parts {
+            let path = dir.join(&part.name);
+            match &part.content {
+                PartContent::Text(text) => fs::write(path, text)?,
+                PartContent::File(file) => {
+                    fs::copy(file, path)?;
+                }
+            }
+        }
+        fs::write(
+            dir.join(FORM_LISTING),
+            serde_json::to_string_pretty(&self.parts)?,
+        )?;
+        Ok(())
+    }
+
+    /// Reads a form back from a directory it was written to.
+    pub fn read(dir: &Path) -> Result<UploadForm, failure::Error> {
+        let listing = dir.join(FORM_LISTING);
+        let contents = match fs::read_to_string(&listing) {
+            Ok(contents) => contents,
+            Err(e) => failure::bail!("Could not read {}: {}", listing.display(), e),
+        };
+        let mut parts: Vec<FormPart> = serde_json::from_str(&contents)?;
+        for part in &mut parts {
+            let path = dir.join(&part.name);
+            if !path.is_file() {
+                failure::bail!(
+                    "{} lists a part named {}, but {} does not exist",
+                    listing.display(),
+                    part.name,
+                    path.display()
+                )
+            }
+            part.content = PartContent::File(path);
+        }
+        Ok(UploadForm { parts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_writes_parts_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("index.js");
+        fs::write(&script_path, "addEventListener('fetch', () => {})").unwrap();
+
+        let mut form = UploadForm::default();
+        form.text(
+            "metadata",
+            "metadata.json",
+            "application/json",
+            r#"{"body_part":"index","bindings":[]}"#.to_string(),
+        );
+        form.file("index", "index.js", None, script_path);
+
+        let outdir = dir.path().join("form");
+        form.write(&outdir).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(outdir.join("index")).unwrap(),
+            "addEventListener('fetch', () => {})"
+        );
+        let listing: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(outdir.join("form.json")).unwrap()).unwrap();
+        assert_eq!(
+            listing,
+            serde_json::json!([
+                { "name": "metadata", "file_name": "metadata.json", "content_type": "application/json" },
+                { "name": "index", "file_name": "index.js", "content_type": null },
+            ])
+        );
+    }
+
+    #[test]
+    fn it_keeps_content_types_through_a_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("index.js");
+        fs::write(&script_path, "addEventListener('fetch', () => {})").unwrap();
+        let wasm_path = dir.path().join("module.wasm");
+        fs::write(&wasm_path, b"\0asm").unwrap();
+
+        let mut form = UploadForm::default();
+        form.file(
+            "script",
+            "index.js",
+            Some("application/javascript"),
+            script_path,
+        );
+        form.file("module", "module.wasm", None, wasm_path);
+
+        let outdir = dir.path().join("form");
+        form.write(&outdir).unwrap();
+        let read = UploadForm::read(&outdir).unwrap();
+
+        let content_types: Vec<Option<String>> = read
+            .parts
+            .iter()
+            .map(FormPart::resolved_content_type)
+            .collect();
+        assert_eq!(
+            content_types,
+            vec![
+                Some("application/javascript".to_string()),
+                Some("application/wasm".to_string())
+            ]
+        );
+        assert!(read.to_form().is_ok());
+    }
+}
@@ -1,26 +1,38 @@
+pub mod artifact;
 pub mod form;
 mod krate;
 pub mod package;
 
 pub use package::Package;
 
+use reqwest::blocking::multipart::Form;
 use reqwest::blocking::Client;
 
 use crate::settings::toml::Target;
 use crate::sites::AssetManifest;
+use crate::upload::form::SessionConfig;
 
 pub fn script(
     client: &Client,
     target: &Target,
     asset_manifest: Option<AssetManifest>,
+) -> Result<(), failure::Error> {
+    let script_upload_form = form::build(target, asset_manifest, &SessionConfig::default())?;
+
+    script_form(client, target, script_upload_form)
+}
+
+// Uploads an already-built form as `target`'s script.
+pub fn script_form(
+    client: &Client,
+    target: &Target,
+    script_upload_form: Form,
 ) -> Result<(), failure::Error> {
     let worker_addr = format!(
         "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts/{}",
         target.account_id, target.name,
     );
 
-    let script_upload_form = form::build(target, asset_manifest, None)?;
-
     let res = client
         .put(&worker_addr)
         .multipart(script_upload_form)
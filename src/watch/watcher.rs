@@ -5,6 +5,7 @@ use std::time::Duration;
 
 use failure::{format_err, Error};
 
+use crate::settings::LOCAL_STATE_DIR;
 use crate::terminal::message::{Message, StdOut};
 use log::info;
 
@@ -35,13 +36,18 @@ pub fn wait_for_changes(
 
 fn get_changed_path_from_event(event: DebouncedEvent) -> Result<Option<PathBuf>, Error> {
     info!("Detected Event {:?}", event);
-    match event {
-        DebouncedEvent::Error(error, _) => Err(format_err!("{:?}", error)),
-        DebouncedEvent::NoticeWrite(path) => Ok(Some(path)),
-        DebouncedEvent::Write(path) => Ok(Some(path)),
-        DebouncedEvent::NoticeRemove(path) => Ok(Some(path)),
-        DebouncedEvent::Remove(path) => Ok(Some(path)),
-        DebouncedEvent::Create(path) => Ok(Some(path)),
-        _ => Ok(None),
+    let path = match event {
+        DebouncedEvent::Error(error, _) => return Err(format_err!("{:?}", error)),
+        DebouncedEvent::NoticeWrite(path) => path,
+        DebouncedEvent::Write(path) => path,
+        DebouncedEvent::NoticeRemove(path) => path,
+        DebouncedEvent::Remove(path) => path,
+        DebouncedEvent::Create(path) => path,
+        _ => return Ok(None),
+    };
+    // `wrangler dev --local` writes its state into the project while it runs
+    if path.components().any(|c| c.as_os_str() == LOCAL_STATE_DIR) {
+        return Ok(None);
     }
+    Ok(Some(path))
 }